@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use chrono::Utc;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use zk_sample::{hash_activity, ActivityData, Setup};
+
+// N件の証明を1件ずつ検証する場合と、一括検証する場合の比較
+fn bench_batch_verification(c: &mut Criterion) {
+    let rng = &mut ChaCha20Rng::seed_from_u64(42);
+    let (prover, verifier) = Setup::generate_with_rng(rng);
+
+    let mut group = c.benchmark_group("verify");
+    for n in [1usize, 4, 16, 64] {
+        let items: Vec<_> = (0..n)
+            .map(|i| {
+                let activity = ActivityData::new(
+                    Utc::now(),
+                    hash_activity(&format!("bench_activity_{}", i)),
+                    [1u8; 32],
+                    [0u8; 32],
+                );
+                let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+                (proof, public_activity.public_inputs().unwrap().to_vec())
+            })
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("sequential", n), &items, |b, items| {
+            b.iter(|| {
+                items
+                    .iter()
+                    .map(|(proof, inputs)| verifier.verify_proof(proof, inputs).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batch", n), &items, |b, items| {
+            b.iter(|| verifier.verify_batch(items))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_batch_verification);
+criterion_main!(benches);
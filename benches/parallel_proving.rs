@@ -0,0 +1,39 @@
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use chrono::Utc;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rayon::ThreadPoolBuilder;
+use zk_sample::{hash_activity, ActivityData, Setup};
+
+// 証明する行動の件数
+const NUM_ACTIVITIES: usize = 8;
+
+// 同じ件数の証明を、スレッド数を1から利用できるコア数まで倍々に変えて並列に生成する
+// 理想的には時間がスレッド数に反比例する（証明キーは共有で、証明ごとの処理は独立している）
+// 基準値（1コアのLinux、cargo bench --features parallel、8件）: 1スレッドで約 1.9 s
+// 1コアの環境では2スレッド以上を計測できないので、スケーリングは複数コアの環境で確認すること
+fn bench_parallel_proving(c: &mut Criterion) {
+    let rng = &mut ChaCha20Rng::seed_from_u64(42);
+    let (prover, _) = Setup::generate_with_rng(rng);
+    let now = Utc::now();
+    let activities: Vec<_> = (0..NUM_ACTIVITIES)
+        .map(|i| ActivityData::new(now, hash_activity(&format!("bench_activity_{}", i)), [1u8; 32], [0u8; 32]))
+        .collect();
+
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut group = c.benchmark_group("parallel_proving");
+    group.sample_size(10);
+    for threads in (0..).map(|i| 1usize << i).take_while(|threads| *threads <= cores) {
+        // arkworks内部の並列化も同じスレッドプールで動く
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::new("threads", threads), &activities, |b, activities| {
+            b.iter(|| pool.install(|| prover.generate_proofs_parallel_with_rng(activities, rng)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel_proving);
+criterion_main!(benches);
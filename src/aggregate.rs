@@ -0,0 +1,599 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ark_bn254::{Bn254, Fr};
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, ProvingKey};
+use ark_r1cs_std::{
+    alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, fields::FieldVar, select::CondSelectGadget,
+    uint64::UInt64,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::activity::{check_not_future, checked_sub, hash_to_field_be, unix_seconds, ActivityData};
+use crate::circuit::{derive_nullifier, enforce_u64_geq, poseidon_hash_var};
+use crate::error::ZkError;
+use crate::pedersen::{PedersenCommitment, PedersenField};
+use crate::verifier::{DEFAULT_CLOCK_TOLERANCE, DEFAULT_FRESHNESS_WINDOW};
+
+// 公開入力のうち、Nによらない部分の数（cutoff, upper, threshold, count, commitment）
+// この後に行動ごとのnullifierがN個続く
+pub const NUM_AGGREGATE_PUBLIC_INPUTS: usize = 5;
+
+// N件の行動のうち、期間内（cutoff <= timestamp <= upper）のものがcount件あり、count >= threshold であることを
+// 1つの証明で示す回路。upperは証明した時刻で、未来の時刻を持つ行動は期間内として数えない
+// 行動ハッシュはすべて互いに異なることを課すので、同じ行動を重複して数えることはできない
+// （件数が足りない場合は、期間外の時刻を持つ別のハッシュで埋める）
+// 行動はすべて公開コミットメントを開く1つの秘密値に結び付け、行動ごとのnullifierもActivityCircuitと同じ式で公開する
+#[derive(Clone)]
+pub struct MultiActivityCircuit<const N: usize, F: PedersenField = Fr> {
+    // 公開入力
+    pub cutoff: u64,
+    pub upper: u64,
+    pub threshold: u64,
+    pub count: u64,
+    pub commitment: F,
+    pub nullifiers: [F; N],
+
+    // 秘密入力
+    pub user_secret: F,
+    pub salt: F,
+    pub activity_hashes: [F; N],
+    pub timestamps: [u64; N],
+}
+
+impl<const N: usize, F: PedersenField> MultiActivityCircuit<N, F> {
+    // 公開入力の数（Nによらない部分と、行動ごとのnullifier）
+    pub const NUM_PUBLIC_INPUTS: usize = NUM_AGGREGATE_PUBLIC_INPUTS + N;
+
+    // 行動はすべて同じ秘密値とソルト（同じコミットメント）を持つ必要がある（異なればDifferentUsers）
+    // すべて0の秘密値ではInvalidCommitment、秘密値かソルトが部分群の位数以上ならSecretOutOfRange
+    pub fn new(
+        activities: &[ActivityData; N],
+        cutoff: DateTime<Utc>,
+        upper: DateTime<Utc>,
+        threshold: u64,
+    ) -> Result<Self, ZkError> {
+        let first = activities.first().ok_or(ZkError::MissingField("activities"))?;
+        if activities.iter().any(|activity| activity.user_secret != first.user_secret || activity.salt != first.salt) {
+            return Err(ZkError::DifferentUsers);
+        }
+        if first.user_secret == [0u8; 32] {
+            return Err(ZkError::InvalidCommitment);
+        }
+        let user_secret = hash_to_field_be(&first.user_secret);
+        let salt = hash_to_field_be(&first.salt);
+        PedersenCommitment::check_opening(user_secret, salt)?;
+
+        let mut timestamps = [0u64; N];
+        for (timestamp, activity) in timestamps.iter_mut().zip(activities) {
+            *timestamp = unix_seconds(activity.timestamp)?;
+        }
+        let activity_hashes = activities.each_ref().map(|activity| hash_to_field_be(&activity.activity_hash));
+
+        let mut circuit = Self {
+            cutoff: unix_seconds(cutoff)?,
+            upper: unix_seconds(upper)?,
+            threshold,
+            count: 0,
+            commitment: PedersenCommitment::commit(user_secret, salt),
+            nullifiers: activity_hashes.map(|hash| derive_nullifier(user_secret, hash)),
+            user_secret,
+            salt,
+            activity_hashes,
+            timestamps,
+        };
+        circuit.count = circuit.count_in_window();
+        Ok(circuit)
+    }
+
+    // セットアップ用の空の回路（回路の形はNだけで決まる）
+    pub fn blank() -> Self {
+        Self {
+            cutoff: 0,
+            upper: 0,
+            threshold: 0,
+            count: 0,
+            commitment: F::zero(),
+            nullifiers: [F::zero(); N],
+            user_secret: F::zero(),
+            salt: F::zero(),
+            activity_hashes: [F::zero(); N],
+            timestamps: [0; N],
+        }
+    }
+
+    // 期間内（cutoff <= timestamp <= upper）の行動の件数
+    pub fn count_in_window(&self) -> u64 {
+        self.timestamps.iter().filter(|timestamp| (self.cutoff..=self.upper).contains(*timestamp)).count() as u64
+    }
+
+    // 回路に割り当てる公開入力（検証者はcutoffとthresholdを自分で決め、コミットメントを利用者のものと照らし合わせる）
+    pub fn public_inputs(&self) -> Vec<F> {
+        let mut inputs = vec![
+            F::from(self.cutoff),
+            F::from(self.upper),
+            F::from(self.threshold),
+            F::from(self.count),
+            self.commitment,
+        ];
+        inputs.extend_from_slice(&self.nullifiers);
+        inputs
+    }
+}
+
+impl<const N: usize, F: PedersenField> ConstraintSynthesizer<F> for MultiActivityCircuit<N, F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // 公開入力の割り当て（public_inputsと同じ並び）
+        let cutoff = FpVar::new_input(cs.clone(), || Ok(F::from(self.cutoff)))?;
+        let upper = FpVar::new_input(cs.clone(), || Ok(F::from(self.upper)))?;
+        let threshold = FpVar::new_input(cs.clone(), || Ok(F::from(self.threshold)))?;
+        let count = FpVar::new_input(cs.clone(), || Ok(F::from(self.count)))?;
+        let commitment = FpVar::new_input(cs.clone(), || Ok(self.commitment))?;
+        let nullifiers = self
+            .nullifiers
+            .iter()
+            .map(|nullifier| FpVar::new_input(cs.clone(), || Ok(*nullifier)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 秘密入力の割り当て（時刻はUInt64として割り当て、64ビットに収まることも課す）
+        let user_secret = FpVar::new_witness(cs.clone(), || Ok(self.user_secret))?;
+        let salt = FpVar::new_witness(cs.clone(), || Ok(self.salt))?;
+        let hashes = self
+            .activity_hashes
+            .iter()
+            .map(|hash| FpVar::new_witness(cs.clone(), || Ok(*hash)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // commitment == Pedersen(user_secret, salt) を課す（すべての行動を同じ利用者に結び付ける）
+        commitment.enforce_equal(&PedersenCommitment::commit_var(cs.clone(), &user_secret, &salt)?)?;
+
+        // nullifier_i == Poseidon(user_secret, hash_i) を課す（単独の行動の証明と同じnullifierになる）
+        for (nullifier, hash) in nullifiers.iter().zip(&hashes) {
+            nullifier.enforce_equal(&poseidon_hash_var(cs.clone(), &[user_secret.clone(), hash.clone()])?)?;
+        }
+
+        // 同じ行動を2回数えられないよう、ハッシュが互いに異なることを課す
+        for (i, hash) in hashes.iter().enumerate() {
+            for other in &hashes[i + 1..] {
+                hash.enforce_not_equal(other)?;
+            }
+        }
+
+        let mut in_window_count = FpVar::zero();
+        for timestamp in self.timestamps {
+            let timestamp_var = UInt64::new_witness(cs.clone(), || Ok(timestamp))?.to_fp()?;
+
+            // 下限より前でも、証明した時刻より後でもない行動だけを期間内として数える
+            let stale = less_than(cs.clone(), (timestamp, &timestamp_var), (self.cutoff, &cutoff))?;
+            let future = less_than(cs.clone(), (self.upper, &upper), (timestamp, &timestamp_var))?;
+            in_window_count += FpVar::from(!stale & !future);
+        }
+
+        // 公開されたcountが期間内の件数と一致し、count >= threshold であることを制約として課す
+        in_window_count.enforce_equal(&count)?;
+        enforce_u64_geq(cs, &count, &threshold, || Ok(self.count.wrapping_sub(self.threshold)))
+    }
+}
+
+// a < b を表すBooleanを割り当て、実際の大小関係と一致することを課す
+// a < b なら b - a - 1、そうでなければ a - b が64ビットに収まり、どちらか一方しか成り立たない
+fn less_than<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    (a, a_var): (u64, &FpVar<F>),
+    (b, b_var): (u64, &FpVar<F>),
+) -> Result<Boolean<F>, SynthesisError> {
+    let is_less = Boolean::new_witness(cs.clone(), || Ok(a < b))?;
+    let diff = UInt64::new_witness(cs, || Ok(if a < b { b - a - 1 } else { a - b }))?;
+    let expected = FpVar::conditionally_select(&is_less, &(b_var - a_var - FpVar::one()), &(a_var - b_var))?;
+    diff.to_fp()?.enforce_equal(&expected)?;
+    Ok(is_less)
+}
+
+// N件の行動の証明で検証者に渡す公開部分
+// 下限と上限（証明した時刻）は証明者が選んだ時刻で、検証者は自分の時計から見て受理できるかを判定する。
+// 閾値は検証者が求める件数以上でなければならない（count >= thresholdは回路が課す）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicMultiActivity<const N: usize, F: PrimeField = Fr> {
+    pub cutoff: DateTime<Utc>,
+    pub upper: DateTime<Utc>,
+    pub threshold: u64,
+    pub count: u64,
+    pub commitment: F,
+    pub nullifiers: [F; N],
+}
+
+impl<const N: usize, F: PrimeField> PublicMultiActivity<N, F> {
+    // MultiActivityCircuit::public_inputsと同じ並びの公開入力
+    pub fn public_inputs(&self) -> Result<Vec<F>, ZkError> {
+        let mut inputs = vec![
+            F::from(unix_seconds(self.cutoff)?),
+            F::from(unix_seconds(self.upper)?),
+            F::from(self.threshold),
+            F::from(self.count),
+            self.commitment,
+        ];
+        inputs.extend_from_slice(&self.nullifiers);
+        Ok(inputs)
+    }
+}
+
+// N件の行動をまとめて証明する証明者（Setup::generate_for_n_activitiesで生成した、同じNの回路の鍵を持つ）
+pub struct MultiActivityProver<const N: usize, E: Pairing = Bn254> {
+    proving_key: ProvingKey<E>,
+    freshness_window: Duration,
+}
+
+impl<const N: usize, E: Pairing> MultiActivityProver<N, E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 証明キーから証明者を作成（鮮度の期間はデフォルト値）
+    pub(crate) fn from_proving_key(proving_key: ProvingKey<E>) -> Self {
+        Self {
+            proving_key,
+            freshness_window: DEFAULT_FRESHNESS_WINDOW,
+        }
+    }
+
+    // 下限として証明する期間を変更する
+    pub fn with_freshness_window(mut self, freshness_window: Duration) -> Self {
+        self.freshness_window = freshness_window;
+        self
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<E> {
+        &self.proving_key
+    }
+
+    // 現在時刻から鮮度の期間だけ前を下限、現在時刻を上限として、N件のうちthreshold件以上が期間内であることを証明する
+    // （OsRngを使用）
+    #[cfg(feature = "std")]
+    pub fn prove_n_activities(
+        &self,
+        activities: &[ActivityData; N],
+        threshold: u64,
+    ) -> Result<(PublicMultiActivity<N, E::ScalarField>, Proof<E>), ZkError> {
+        self.prove_n_activities_with_rng(activities, threshold, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで証明する
+    #[cfg(feature = "std")]
+    pub fn prove_n_activities_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activities: &[ActivityData; N],
+        threshold: u64,
+        rng: &mut R,
+    ) -> Result<(PublicMultiActivity<N, E::ScalarField>, Proof<E>), ZkError> {
+        let now = Utc::now();
+        let cutoff = checked_sub(now, self.freshness_window)?;
+        self.prove_n_activities_in_window(activities, cutoff, now, threshold, rng)
+    }
+
+    // 下限と上限を指定して証明する（no_stdや、時刻を固定したい場合向け。どちらも秒に切り捨てる）
+    // 期間内の件数が閾値に足りない、または同じ行動が重複している場合は証明を作らずにUnsatisfied
+    pub fn prove_n_activities_in_window<R: RngCore + CryptoRng>(
+        &self,
+        activities: &[ActivityData; N],
+        cutoff: DateTime<Utc>,
+        upper: DateTime<Utc>,
+        threshold: u64,
+        rng: &mut R,
+    ) -> Result<(PublicMultiActivity<N, E::ScalarField>, Proof<E>), ZkError> {
+        let (cutoff, upper) = (cutoff.trunc_subsecs(0), upper.trunc_subsecs(0));
+        let circuit = MultiActivityCircuit::<N, E::ScalarField>::new(activities, cutoff, upper, threshold)?;
+        // 重複したハッシュでは合成自体が失敗するので、それも満たさないものとして扱う
+        let cs = ConstraintSystem::new_ref();
+        if circuit.clone().generate_constraints(cs.clone()).is_err() || !cs.is_satisfied()? {
+            return Err(ZkError::Unsatisfied);
+        }
+
+        let public = PublicMultiActivity {
+            cutoff,
+            upper,
+            threshold,
+            count: circuit.count,
+            commitment: circuit.commitment,
+            nullifiers: circuit.nullifiers,
+        };
+        let proof = Groth16::<E>::create_random_proof_with_reduction(circuit, &self.proving_key, rng)?;
+        Ok((public, proof))
+    }
+}
+
+// N件の行動の証明の検証者（同じNの回路の検証キーだけを持つので、別のNの証明は検証を通らない）
+pub struct MultiActivityVerifier<const N: usize, E: Pairing = Bn254> {
+    verifying_key: PreparedVerifyingKey<E>,
+    freshness_window: Duration,
+    clock_tolerance: Duration,
+}
+
+impl<const N: usize, E: Pairing> MultiActivityVerifier<N, E> {
+    // 検証キーから検証者を作成（鮮度の期間と時計の許容誤差はデフォルト値）
+    pub(crate) fn from_prepared_key(verifying_key: PreparedVerifyingKey<E>) -> Self {
+        Self {
+            verifying_key,
+            freshness_window: DEFAULT_FRESHNESS_WINDOW,
+            clock_tolerance: DEFAULT_CLOCK_TOLERANCE,
+        }
+    }
+
+    // 受理する下限の古さ（鮮度の期間）を変更する
+    pub fn with_freshness_window(mut self, freshness_window: Duration) -> Self {
+        self.freshness_window = freshness_window;
+        self
+    }
+
+    // 証明を生成してから検証するまでの時間や時計のずれとして許す幅を変更する
+    pub fn with_clock_tolerance(mut self, clock_tolerance: Duration) -> Self {
+        self.clock_tolerance = clock_tolerance;
+        self
+    }
+
+    pub fn verifying_key(&self) -> &PreparedVerifyingKey<E> {
+        &self.verifying_key
+    }
+
+    // 現在時刻で、期間内の行動がrequired件以上あることを検証する
+    #[cfg(feature = "std")]
+    pub fn verify_n_activities(
+        &self,
+        public: &PublicMultiActivity<N, E::ScalarField>,
+        proof: &Proof<E>,
+        required: u64,
+    ) -> Result<bool, ZkError> {
+        self.verify_n_activities_with_clock(public, proof, required, Utc::now())
+    }
+
+    // 検証者の時計を指定して検証する
+    // 証明の閾値がrequiredより小さければThresholdTooLow
+    // 下限が now - 鮮度の期間 - 許容誤差 より古ければStaleCutoff（単独の行動の検証と同じ判定）
+    // 上限が now + 許容誤差 より後ならFutureTimestamp（未来の行動を期間内として数えさせない）
+    pub fn verify_n_activities_with_clock(
+        &self,
+        public: &PublicMultiActivity<N, E::ScalarField>,
+        proof: &Proof<E>,
+        required: u64,
+        now: DateTime<Utc>,
+    ) -> Result<bool, ZkError> {
+        if public.threshold < required {
+            return Err(ZkError::ThresholdTooLow {
+                required,
+                got: public.threshold,
+            });
+        }
+        if public.cutoff < checked_sub(checked_sub(now, self.freshness_window)?, self.clock_tolerance)? {
+            return Err(ZkError::StaleCutoff);
+        }
+        check_not_future(public.upper, now, self.clock_tolerance)?;
+        Ok(Groth16::<E>::verify_proof(&self.verifying_key, proof, &public.public_inputs()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::{prepare_verifying_key, Groth16};
+    use ark_relations::r1cs::ConstraintSystem;
+    use chrono::Duration;
+
+    use crate::hash_activity;
+    use crate::test_utils::seeded_rng;
+
+    // 利用者user_secretの4件の行動（先頭のin_window件が期間内、残りは期間外）
+    fn user_activities(in_window: usize, now: DateTime<Utc>, user_secret: u8) -> [ActivityData; 4] {
+        std::array::from_fn(|i| {
+            let age = if i < in_window { Duration::days(i as i64) } else { Duration::days(40 + i as i64) };
+            ActivityData::new(now - age, hash_activity(&format!("aggregate_{}", i)), [user_secret; 32], [0u8; 32])
+        })
+    }
+
+    fn sample_activities(in_window: usize, now: DateTime<Utc>) -> [ActivityData; 4] {
+        user_activities(in_window, now, 1)
+    }
+
+    // 重複したハッシュでは逆元が存在せず合成自体が失敗するので、それも満たさないものとして扱う
+    fn is_satisfied(circuit: MultiActivityCircuit<4>) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).is_ok() && cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_threshold_depends_on_in_window_count() {
+        let now = Utc::now();
+        let cutoff = now - Duration::days(30);
+
+        // 期間内の件数が閾値以上のときだけ制約を満たす
+        for in_window in 0..=4 {
+            let circuit = MultiActivityCircuit::<4>::new(&sample_activities(in_window, now), cutoff, now, 2).unwrap();
+            assert_eq!(circuit.count, in_window as u64);
+            assert_eq!(is_satisfied(circuit), in_window >= 2, "in_window = {}", in_window);
+        }
+
+        // 閾値0は常に満たし、Nを超える閾値は決して満たさない
+        let activities = sample_activities(4, now);
+        assert!(is_satisfied(MultiActivityCircuit::new(&activities, cutoff, now, 0).unwrap()));
+        assert!(!is_satisfied(MultiActivityCircuit::new(&activities, cutoff, now, 5).unwrap()));
+
+        // 実際と異なる件数を公開することはできない
+        let mut circuit = MultiActivityCircuit::<4>::new(&sample_activities(2, now), cutoff, now, 2).unwrap();
+        circuit.count = 3;
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_future_activities_are_not_counted() {
+        let now = Utc::now();
+        let cutoff = now - Duration::days(30);
+
+        // 上限より後の時刻の行動は、下限より後でも期間内として数えない
+        let mut activities = sample_activities(2, now);
+        activities[2].timestamp = now + Duration::days(1);
+        activities[3].timestamp = DateTime::<Utc>::MAX_UTC;
+        let circuit = MultiActivityCircuit::<4>::new(&activities, cutoff, now, 2).unwrap();
+        assert_eq!(circuit.count, 2);
+        assert!(is_satisfied(circuit));
+        assert!(!is_satisfied(MultiActivityCircuit::<4>::new(&activities, cutoff, now, 3).unwrap()));
+
+        // 件数を水増しして公開することもできない
+        let mut circuit = MultiActivityCircuit::<4>::new(&activities, cutoff, now, 2).unwrap();
+        circuit.count = 4;
+        assert!(!is_satisfied(circuit));
+
+        // 上限ちょうどの行動は数える
+        activities[2].timestamp = now;
+        assert_eq!(MultiActivityCircuit::<4>::new(&activities, cutoff, now, 2).unwrap().count, 3);
+    }
+
+    #[test]
+    fn test_duplicate_activities_are_rejected() {
+        let now = Utc::now();
+        let mut activities = sample_activities(1, now);
+        activities[1].timestamp = now;
+
+        // 同じ行動を2件として数えることはできない
+        activities[1].activity_hash = activities[0].activity_hash;
+        let circuit = MultiActivityCircuit::<4>::new(&activities, now - Duration::days(30), now, 2).unwrap();
+        assert_eq!(circuit.count, 2);
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_activities_must_belong_to_one_user() {
+        let now = Utc::now();
+        let cutoff = now - Duration::days(30);
+
+        // 秘密値やソルトの異なる行動は1つのコミットメントにまとめられない
+        let mut activities = sample_activities(4, now);
+        activities[2].user_secret = [2u8; 32];
+        assert!(matches!(MultiActivityCircuit::<4>::new(&activities, cutoff, now, 2), Err(ZkError::DifferentUsers)));
+        let mut activities = sample_activities(4, now);
+        activities[3].salt = [1u8; 32];
+        assert!(matches!(MultiActivityCircuit::<4>::new(&activities, cutoff, now, 2), Err(ZkError::DifferentUsers)));
+        assert!(matches!(
+            MultiActivityCircuit::<4>::new(&user_activities(4, now, 0), cutoff, now, 2),
+            Err(ZkError::InvalidCommitment)
+        ));
+
+        // 別の利用者のコミットメントやnullifierを主張すると制約を満たさない
+        let other = MultiActivityCircuit::<4>::new(&user_activities(4, now, 2), cutoff, now, 2).unwrap();
+        let mut circuit = MultiActivityCircuit::<4>::new(&sample_activities(4, now), cutoff, now, 2).unwrap();
+        circuit.commitment = other.commitment;
+        assert!(!is_satisfied(circuit));
+        let mut circuit = MultiActivityCircuit::<4>::new(&sample_activities(4, now), cutoff, now, 2).unwrap();
+        circuit.nullifiers[0] = other.nullifiers[0];
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_aggregate_proof_binds_public_inputs() {
+        let rng = &mut seeded_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(MultiActivityCircuit::<4>::blank(), rng)
+            .unwrap();
+        let pvk = prepare_verifying_key(&pk.vk);
+
+        let now = Utc::now();
+        let cutoff = now - Duration::days(30);
+        let circuit = MultiActivityCircuit::<4>::new(&sample_activities(3, now), cutoff, now, 3).unwrap();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs.len(), MultiActivityCircuit::<4>::NUM_PUBLIC_INPUTS);
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, rng).unwrap();
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &public_inputs).unwrap());
+
+        // nullifierは単独の行動の証明と同じ値になる
+        let activity = &sample_activities(3, now)[1];
+        let single = crate::ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            cutoff,
+        )
+        .unwrap();
+        assert_eq!(public_inputs[NUM_AGGREGATE_PUBLIC_INPUTS + 1], single.nullifier);
+        assert_eq!(public_inputs[4], single.commitment);
+
+        // 同じ証明でより大きい閾値や件数、より新しい下限や古い上限を主張することはできない
+        let with = |index: usize, value: Fr| {
+            let mut inputs = public_inputs.clone();
+            inputs[index] = value;
+            inputs
+        };
+        let cutoff_secs = unix_seconds(cutoff).unwrap();
+        let upper_secs = unix_seconds(now).unwrap();
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &with(2, Fr::from(4u64))).unwrap());
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &with(3, Fr::from(4u64))).unwrap());
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &with(0, Fr::from(cutoff_secs + 86_400))).unwrap());
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &with(1, Fr::from(upper_secs - 86_400))).unwrap());
+
+        // 別の利用者のコミットメントに対しては検証に失敗する
+        let other = MultiActivityCircuit::<4>::new(&user_activities(3, now, 2), cutoff, now, 3).unwrap();
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other.public_inputs()).unwrap());
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &with(4, other.commitment)).unwrap());
+    }
+
+    #[test]
+    fn test_activity_proofs_check_the_threshold_and_cutoff() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = crate::Setup::<Bn254>::generate_for_n_activities::<4, _>(rng);
+        let now = Utc::now();
+
+        // 鮮度の期間内の下限で証明した件数は、検証者の時計で受理される
+        let cutoff = now - Duration::days(30);
+        let activities = sample_activities(3, now);
+        let (public, proof) = prover.prove_n_activities_in_window(&activities, cutoff, now, 3, rng).unwrap();
+        assert_eq!((public.count, public.threshold), (3, 3));
+        assert!(verifier.verify_n_activities_with_clock(&public, &proof, 3, now).unwrap());
+        assert!(verifier.verify_n_activities_with_clock(&public, &proof, 2, now).unwrap());
+
+        // 検証者が求める件数より小さい閾値の証明は、正しい証明でもThresholdTooLow
+        assert!(matches!(
+            verifier.verify_n_activities_with_clock(&public, &proof, 4, now),
+            Err(ZkError::ThresholdTooLow { required: 4, got: 3 })
+        ));
+        let none_in_window = sample_activities(0, now);
+        let (empty, empty_proof) = prover.prove_n_activities_in_window(&none_in_window, cutoff, now, 0, rng).unwrap();
+        assert_eq!((empty.count, empty.threshold), (0, 0));
+        assert!(matches!(
+            verifier.verify_n_activities_with_clock(&empty, &empty_proof, 3, now),
+            Err(ZkError::ThresholdTooLow { required: 3, got: 0 })
+        ));
+
+        // 検証者の鮮度の期間より古い下限はStaleCutoff
+        let short = MultiActivityVerifier::<4, Bn254>::from_prepared_key(verifier.verifying_key().clone())
+            .with_freshness_window(Duration::days(7));
+        assert!(matches!(
+            short.verify_n_activities_with_clock(&public, &proof, 3, now),
+            Err(ZkError::StaleCutoff)
+        ));
+
+        // 検証者の時計より許容誤差を超えて後の上限はFutureTimestamp
+        let later = now + Duration::hours(1);
+        let (future, future_proof) =
+            prover.prove_n_activities_in_window(&activities, later - Duration::days(30), later, 3, rng).unwrap();
+        assert!(matches!(
+            verifier.verify_n_activities_with_clock(&future, &future_proof, 3, now),
+            Err(ZkError::FutureTimestamp)
+        ));
+        assert!(verifier.verify_n_activities_with_clock(&future, &future_proof, 3, later).unwrap());
+
+        // 公開部分を書き換えると検証を通らない
+        let mut forged = public;
+        forged.count = 4;
+        assert!(!verifier.verify_n_activities_with_clock(&forged, &proof, 3, now).unwrap());
+
+        // 閾値に届かない行動や重複した行動では証明を作らない
+        let too_few = prover.prove_n_activities_in_window(&sample_activities(2, now), cutoff, now, 3, rng);
+        assert!(matches!(too_few, Err(ZkError::Unsatisfied)));
+        let mut duplicated = activities.clone();
+        duplicated[1].activity_hash = duplicated[0].activity_hash;
+        let duplicated = prover.prove_n_activities_in_window(&duplicated, cutoff, now, 2, rng);
+        assert!(matches!(duplicated, Err(ZkError::Unsatisfied)));
+    }
+}
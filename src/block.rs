@@ -0,0 +1,151 @@
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+
+use crate::activity::{HashField, TimestampField, TypedPublicInputs};
+use crate::circuit::NO_EXPIRY;
+
+// ブロック高で数える鮮度の期間のデフォルト値（12秒ごとのブロックで約30日）
+pub const DEFAULT_BLOCK_WINDOW: u64 = 216_000;
+
+// 壁時計の代わりに使うブロック高の時計（オンチェーンの検証者は時刻ではなくブロック高を信頼する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockClock {
+    // 証明・検証する時点のブロック高
+    pub current: u64,
+}
+
+impl BlockClock {
+    pub fn new(current: u64) -> Self {
+        Self { current }
+    }
+
+    // 鮮度の期間だけ遡った下限のブロック高（ジェネシスより前には遡らない）
+    pub fn cutoff(&self, window_blocks: u64) -> u64 {
+        self.current.saturating_sub(window_blocks)
+    }
+}
+
+// ブロック高で鮮度を示す証明の公開部分（タイムスタンプと下限の位置にブロック高が入る）
+// 回路はactivity_block >= cutoff_blockを課すので、cutoff_block = current - window_blocksなら
+// current - activity_block <= window_blocksが示される。回路の形は時刻の場合と同じなので鍵も同じものを使う
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicBlockActivity<F: PrimeField = Fr> {
+    pub activity_block: u64,
+    pub activity_hash: [u8; 32],
+    pub commitment: F,
+    pub cutoff_block: u64,
+    pub nullifier: F,
+    pub challenge: F,
+    pub user_id: F,
+}
+
+impl<F: PrimeField> PublicBlockActivity<F> {
+    // 回路の公開入力を名前付きで返す
+    pub fn public_inputs(&self) -> TypedPublicInputs<F> {
+        self.public_inputs_with(HashField::from_hash(&self.activity_hash))
+    }
+
+    // 行動ハッシュの位置に別の値（許可リストの根）を入れて公開入力を組み立てる
+    pub(crate) fn public_inputs_with(&self, activity_hash: HashField<F>) -> TypedPublicInputs<F> {
+        TypedPublicInputs {
+            timestamp: TimestampField::from_field(F::from(self.activity_block)),
+            activity_hash,
+            commitment: self.commitment,
+            cutoff: TimestampField::from_field(F::from(self.cutoff_block)),
+            nullifier: self.nullifier,
+            challenge: self.challenge,
+            user_id: self.user_id,
+            // ブロック高の証明には有効期限を付けない
+            expiry: F::from(NO_EXPIRY),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::error::ZkError;
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData};
+
+    #[test]
+    fn test_recent_block_activity_verification() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let clock = BlockClock::new(1_000_000);
+        let activity = ActivityData::new(Utc::now(), hash_activity("block"), test_secret(38), [0u8; 32]);
+
+        // 10ブロック前の行動は受理され、下限は現在のブロック高から期間だけ遡ったもの
+        let (public_activity, proof) = prover.prove_at_block(&activity, clock.current - 10, clock, rng).unwrap();
+        assert_eq!(public_activity.cutoff_block, clock.current - DEFAULT_BLOCK_WINDOW);
+        let nullifier = verifier.verify_at_block(&public_activity, &proof, clock).unwrap();
+        assert_eq!(nullifier, public_activity.nullifier);
+
+        // ブロック高を書き換えると証明が通らない
+        let tampered = PublicBlockActivity {
+            activity_block: public_activity.activity_block + 1,
+            ..public_activity.clone()
+        };
+        assert!(matches!(
+            verifier.verify_at_block(&tampered, &proof, clock),
+            Err(ZkError::VerificationFailed)
+        ));
+
+        // 期間を超えてブロックが進むと、同じ証明でも古い行動として拒否する
+        let later = BlockClock::new(clock.current + DEFAULT_BLOCK_WINDOW);
+        assert!(matches!(
+            verifier.verify_at_block(&public_activity, &proof, later),
+            Err(ZkError::StaleTimestamp)
+        ));
+    }
+
+    #[test]
+    fn test_block_window_boundary() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let (prover, verifier) = (prover.with_block_window(100), verifier.with_block_window(100));
+        let clock = BlockClock::new(5_000);
+        let activity = ActivityData::new(Utc::now(), hash_activity("block"), test_secret(39), [0u8; 32]);
+
+        // ちょうど期間の分だけ前のブロックは受理する
+        let (public_activity, proof) = prover.prove_at_block(&activity, clock.current - 100, clock, rng).unwrap();
+        assert!(verifier.verify_at_block(&public_activity, &proof, clock).is_ok());
+
+        // 1ブロックでも古ければ回路の制約を満たせず、証明自体が生成できない
+        assert!(matches!(
+            prover.prove_at_block(&activity, clock.current - 101, clock, rng),
+            Err(ZkError::Unsatisfied)
+        ));
+
+        // 1ブロック進んだ検証者からは、期間から外れた行動になる
+        let next = BlockClock::new(clock.current + 1);
+        assert!(matches!(
+            verifier.verify_at_block(&public_activity, &proof, next),
+            Err(ZkError::StaleTimestamp)
+        ));
+    }
+
+    #[test]
+    fn test_future_block_is_rejected() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let clock = BlockClock::new(2_000);
+        let activity = ActivityData::new(Utc::now(), hash_activity("block"), test_secret(40), [0u8; 32]);
+
+        // 証明者の時計より先のブロックでは証明しない
+        assert!(matches!(
+            prover.prove_at_block(&activity, clock.current + 1, clock, rng),
+            Err(ZkError::FutureTimestamp)
+        ));
+
+        // 検証者の時計が遅れていれば、まだ来ていないブロックの行動として拒否する
+        let (public_activity, proof) = prover.prove_at_block(&activity, clock.current, clock, rng).unwrap();
+        let behind = BlockClock::new(clock.current - 1);
+        assert!(matches!(
+            verifier.verify_at_block(&public_activity, &proof, behind),
+            Err(ZkError::FutureTimestamp)
+        ));
+    }
+}
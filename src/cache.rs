@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ark_ec::pairing::Pairing;
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+use crate::serialization::serialize_proof;
+
+// キーを計算するときに証明の前に付けるドメインタグ
+const CACHE_KEY_DOMAIN: &[u8] = b"zk_sample/verification_cache/v1";
+
+// 証明の暗号的な検証結果（ペアリングの結果だけ）を覚えておく、容量を決めたLRUのキャッシュ
+// 鮮度や下限は時刻によって変わるので覚えず、呼び出しのたびに検証者が判定し直す
+#[derive(Debug)]
+pub struct VerificationCache {
+    capacity: usize,
+    // キー → (検証結果, 最後に使った順番)
+    entries: HashMap<[u8; 32], (bool, u64)>,
+    // 最後に使った順番 → キー（先頭が最も長く使われていないもの）
+    order: BTreeMap<u64, [u8; 32]>,
+    tick: u64,
+}
+
+impl VerificationCache {
+    // capacityが0なら何も覚えない
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    // 覚えている結果を返し、最近使ったものとして扱う
+    pub fn get(&mut self, key: &[u8; 32]) -> Option<bool> {
+        let tick = self.next_tick();
+        let (valid, last_used) = self.entries.get_mut(key)?;
+        self.order.remove(last_used);
+        self.order.insert(tick, *key);
+        *last_used = tick;
+        Some(*valid)
+    }
+
+    // 結果を覚える（容量を超えたら最も長く使われていないものを捨てる）
+    pub fn insert(&mut self, key: [u8; 32], valid: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.next_tick();
+        if let Some((_, last_used)) = self.entries.insert(key, (valid, tick)) {
+            self.order.remove(&last_used);
+        } else if self.entries.len() > self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.insert(tick, key);
+    }
+
+    // すべての結果を捨てる（検証キーを入れ替えたときに使う）
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+// 証明と公開入力から計算するキャッシュのキー（SHA256(ドメインタグ || 圧縮形式の証明 || 公開入力)）
+pub(crate) fn cache_key<E: Pairing>(proof: &ark_groth16::Proof<E>, public_inputs: &[E::ScalarField]) -> [u8; 32] {
+    let mut bytes = serialize_proof(proof);
+    // Vecへの書き込みは失敗しない
+    public_inputs
+        .serialize_compressed(&mut bytes)
+        .expect("Serializing into a Vec cannot fail");
+    Sha256::new()
+        .chain_update(CACHE_KEY_DOMAIN)
+        .chain_update(bytes)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use chrono::{Duration, Utc};
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData, Setup};
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let mut cache = VerificationCache::new(2);
+        cache.insert([1u8; 32], true);
+        cache.insert([2u8; 32], false);
+
+        // 1を使ったので、3を入れると最も長く使われていない2が捨てられる
+        assert_eq!(cache.get(&[1u8; 32]), Some(true));
+        cache.insert([3u8; 32], true);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&[2u8; 32]), None);
+        assert_eq!(cache.get(&[1u8; 32]), Some(true));
+        assert_eq!(cache.get(&[3u8; 32]), Some(true));
+
+        let mut disabled = VerificationCache::new(0);
+        disabled.insert([1u8; 32], true);
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn test_cached_result_is_reused_until_rotation() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let (_, other_verifier) = Setup::generate_with_rng(rng);
+        let mut verifier = verifier.with_cache(8);
+        let activity = ActivityData::new(Utc::now(), hash_activity("cached"), test_secret(29), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let public_inputs = public_activity.public_inputs().unwrap().to_vec();
+
+        // 2回目は覚えた結果を使う（キャッシュにだけ偽の結果を入れて、ペアリングを計算していないことを確かめる）
+        assert!(verifier.verify_proof(&proof, &public_inputs).unwrap());
+        assert_eq!(verifier.cached_results(), 1);
+        let key = cache_key(&proof, &public_inputs);
+        verifier.cache.as_ref().unwrap().lock().unwrap().insert(key, false);
+        assert!(!verifier.verify_proof(&proof, &public_inputs).unwrap());
+
+        // 公開入力が1つでも違えば別のキーになる
+        let mut other_inputs = public_inputs.clone();
+        other_inputs[5] += Fr::from(1u64);
+        assert!(!verifier.verify_proof(&proof, &other_inputs).unwrap());
+        assert_eq!(verifier.cached_results(), 2);
+
+        // 検証キーを入れ替えるとキャッシュは空になり、新しい鍵で検証し直す
+        verifier.rotate_vk(other_verifier.verifying_key().clone()).unwrap();
+        assert_eq!(verifier.cached_results(), 0);
+        assert!(!verifier.verify_proof(&proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn test_freshness_is_rechecked_on_cache_hit() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let prover = prover.with_freshness_window(Duration::days(7));
+        let verifier = verifier.with_cache(8).with_freshness_window(Duration::days(7));
+        let now = Utc::now();
+        let activity = ActivityData::new(now - Duration::days(6), hash_activity("cached"), test_secret(29), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 今は新しいと判定され、証明の結果が覚えられる
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(verifier.cached_results(), 1);
+
+        // 2日後は、覚えた証明の結果を使っても鮮度は判定し直されて古いと分かる
+        let later = now + Duration::days(2);
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, later).unwrap();
+        assert!(report.proof_valid);
+        assert!(!report.timestamp_fresh);
+        assert_eq!(verifier.cached_results(), 1);
+    }
+}
@@ -0,0 +1,894 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::ops::Range;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::constraints::CryptographicSpongeVar;
+use ark_crypto_primitives::sponge::poseidon::constraints::PoseidonSpongeVar;
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge};
+use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, fields::FieldVar, uint64::UInt64};
+use ark_relations::ns;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, Namespace, SynthesisError, SynthesisMode,
+};
+use chrono::{DateTime, Utc};
+use once_cell::race::OnceBox;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::activity::{hash_to_field_be, unix_seconds};
+use crate::epoch::EpochWindow;
+use crate::error::ZkError;
+use crate::merkle::{merkle_root_var, Allowlist, AllowlistMembership};
+use crate::pedersen::{PedersenCommitment, PedersenField};
+use crate::preimage::activity_hash_var;
+
+// セットアップ用のダミーの回路で使う秘密値（回路の形を決めるためだけの値で、実際の証明には使わない）
+// すべて0の秘密値は拒否するので、それと区別できる一目で分かる値にしている（先頭の0は部分群の位数未満にするため）
+pub(crate) const SETUP_PLACEHOLDER_SECRET: [u8; 32] = *b"\0zk_sample:setup-placeholder:000";
+
+// 証明用の回路構造体（Fはペアリング曲線のスカラー体、デフォルトはBN254）
+#[derive(Clone)]
+pub struct ActivityCircuit<F: PrimeField = Fr> {
+    // 公開入力
+    pub timestamp: u64,
+    pub activity_hash: F,
+    pub commitment: F,
+    pub cutoff: u64,
+    pub nullifier: F,
+    // 検証者がセッションごとに発行するチャレンジ（0はチャレンジなし）
+    pub challenge: F,
+    // 証明を束縛する利用者のID（UserId::to_field。0は利用者を指定しない）
+    pub user_id: F,
+    // 証明自体の有効期限（Unix秒。NO_EXPIRYは期限なし）。timestamp <= expiry を課す
+    pub expiry: u64,
+
+    // 秘密入力
+    pub user_secret: F,
+    pub salt: F,
+
+    // 許可リストを使う場合、公開入力の行動ハッシュの位置には代わりに許可リストの根が入り、
+    // 行動ハッシュ自体は秘密入力になる（どの行動かを明かさずにリストに含まれることを示す）
+    pub allowlist: Option<AllowlistMembership<F>>,
+
+    // エポックを使う場合、公開入力のタイムスタンプの位置には代わりにエポックの番号が入り、
+    // 正確なタイムスタンプは秘密入力になる（そのエポックに含まれることだけを示す）
+    pub epoch: Option<EpochWindow>,
+
+    // 範囲を使う場合、公開入力のタイムスタンプの位置には代わりに範囲の終わりが入り（始まりは下限の位置）、
+    // 正確なタイムスタンプは秘密入力になる（cutoff <= timestamp <= range_end だけを示す。エポックとは併用しない）
+    pub range_end: Option<u64>,
+
+    // 原像を使う場合、行動ハッシュが秘密の原像のhash_activityであることも課す（回路の形は原像の長さで決まる）
+    pub preimage: Option<Vec<u8>>,
+
+    // ソルト付きの行動ハッシュを使う場合、公開入力の行動ハッシュの位置にはPoseidon(行動ハッシュ, ソルト)が入り、
+    // 行動ハッシュとソルトは秘密入力になる（許可リストと併用した場合は根が入るので、ソルトは使わない）
+    pub hash_salt: Option<F>,
+}
+
+impl<F: PedersenField> ActivityCircuit<F> {
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        activity_hash: [u8; 32],
+        user_secret: [u8; 32],
+        salt: [u8; 32],
+        cutoff: DateTime<Utc>,
+    ) -> Result<Self, ZkError> {
+        // タイムスタンプをu64に変換
+        let timestamp_u64 = unix_seconds(timestamp)?;
+
+        // すべて0の秘密値ではコミットメントが何も隠さないので拒否する
+        if user_secret == [0u8; 32] {
+            return Err(ZkError::InvalidCommitment);
+        }
+
+        // ハッシュ値と秘密値をField要素に変換（ビッグエンディアン。pを法として簡約される）
+        // 秘密値とソルトは、簡約した値が埋め込み曲線の部分群の位数未満でなければ拒否する
+        let activity_hash_fr = hash_to_field_be(&activity_hash);
+        let user_secret_fr = hash_to_field_be(&user_secret);
+        let salt_fr = hash_to_field_be(&salt);
+        PedersenCommitment::check_opening(user_secret_fr, salt_fr)?;
+
+        Ok(Self {
+            timestamp: timestamp_u64,
+            activity_hash: activity_hash_fr,
+            commitment: PedersenCommitment::commit(user_secret_fr, salt_fr),
+            cutoff: unix_seconds(cutoff)?,
+            nullifier: derive_nullifier(user_secret_fr, activity_hash_fr),
+            challenge: F::zero(),
+            user_id: F::zero(),
+            expiry: NO_EXPIRY,
+            user_secret: user_secret_fr,
+            salt: salt_fr,
+            allowlist: None,
+            epoch: None,
+            range_end: None,
+            preimage: None,
+            hash_salt: None,
+        })
+    }
+
+    // 回路に割り当てる公開入力（検証時に渡す値と同じ並び）
+    pub fn public_inputs(&self) -> Vec<F> {
+        self.public_input_layout().into_array().to_vec()
+    }
+
+    fn public_input_layout(&self) -> PublicInputLayout<F> {
+        // 時刻はnewでunix_secondsにより変換済みなので、timestamp_to_fieldと同じ値になる
+        PublicInputLayout {
+            timestamp: F::from(self.timestamp_slot()),
+            activity_hash: self.hash_slot(),
+            commitment: self.commitment,
+            cutoff: F::from(self.cutoff),
+            nullifier: self.nullifier,
+            challenge: self.challenge,
+            user_id: self.user_id,
+            expiry: F::from(self.expiry),
+        }
+    }
+
+    // 公開入力の先頭の値（タイムスタンプ、エポックの番号、または範囲の終わり）
+    fn timestamp_slot(&self) -> u64 {
+        match (self.epoch, self.range_end) {
+            (Some(window), _) => window.index,
+            (None, Some(end)) => end,
+            (None, None) => self.timestamp,
+        }
+    }
+
+    // 公開入力の2番目の値（行動ハッシュ、許可リストの根、またはソルト付きの行動ハッシュ）
+    fn hash_slot(&self) -> F {
+        match (&self.allowlist, self.hash_salt) {
+            (Some(membership), _) => membership.root,
+            (None, Some(hash_salt)) => poseidon_hash(&[self.activity_hash, hash_salt]),
+            (None, None) => self.activity_hash,
+        }
+    }
+
+    // セットアップ用のダミーの回路（回路の形は入力の値によらない）
+    pub(crate) fn placeholder() -> Self {
+        let epoch = DateTime::UNIX_EPOCH;
+        Self::new(epoch, [0u8; 32], SETUP_PLACEHOLDER_SECRET, [0u8; 32], epoch)
+            .expect("The Unix epoch is not before itself and the placeholder secret is nonzero")
+    }
+
+    // 許可リスト付きのセットアップ用のダミーの回路
+    pub(crate) fn placeholder_with_allowlist(allowlist: &Allowlist<F>) -> Self {
+        let mut circuit = Self::placeholder();
+        circuit.allowlist = Some(allowlist.blank_membership());
+        circuit
+    }
+
+    // エポック付きのセットアップ用のダミーの回路
+    pub(crate) fn placeholder_with_epoch(window: EpochWindow) -> Self {
+        let mut circuit = Self::placeholder();
+        circuit.epoch = Some(window);
+        circuit
+    }
+
+    // 範囲付きのセットアップ用のダミーの回路
+    pub(crate) fn placeholder_with_range() -> Self {
+        let mut circuit = Self::placeholder();
+        circuit.range_end = Some(0);
+        circuit
+    }
+
+    // 原像付きのセットアップ用のダミーの回路
+    pub(crate) fn placeholder_with_preimage_length(length: usize) -> Self {
+        let mut circuit = Self::placeholder();
+        circuit.preimage = Some(vec![0u8; length]);
+        circuit
+    }
+
+    // ソルト付きの行動ハッシュを使うセットアップ用のダミーの回路
+    pub(crate) fn placeholder_with_salted_hash() -> Self {
+        let mut circuit = Self::placeholder();
+        circuit.hash_salt = Some(F::zero());
+        circuit
+    }
+
+    // 鍵を生成せずに回路の大きさを数える（証明生成のコストの見積もり用）
+    pub fn metrics() -> CircuitMetrics {
+        let cs = ConstraintSystem::<F>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        Self::placeholder()
+            .generate_constraints(cs.clone())
+            .expect("The placeholder circuit always synthesizes");
+
+        CircuitMetrics {
+            constraints: cs.num_constraints(),
+            witness_variables: cs.num_witness_variables(),
+            // 定数1の変数を除いた数
+            public_inputs: cs.num_instance_variables() - 1,
+        }
+    }
+}
+
+// 回路のR1CSとしての大きさ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitMetrics {
+    pub constraints: usize,
+    pub witness_variables: usize,
+    pub public_inputs: usize,
+}
+
+// 公開入力の数
+pub const NUM_PUBLIC_INPUTS: usize = PublicInputLayout::<()>::FIELDS.len();
+
+// 証明に期限を付けない場合の有効期限（どのタイムスタンプも期限以前になる）
+pub const NO_EXPIRY: u64 = u64::MAX;
+
+// 回路のバージョン（保存した鍵のヘッダーに書き込む）。回路の制約を変えて古い鍵が使えなくなるたびに上げる
+pub const CIRCUIT_VERSION: u16 = 4;
+
+// 公開入力の並びの唯一の定義（回路の割り当てとPublicActivity::public_inputsの両方がこれを使う）
+// Tは値（体の元）か、回路内の変数。公開入力を追加するときはこの構造体とFIELDS・try_map・into_arrayに加えれば、
+// 回路の割り当て・公開入力の組み立て・検証時の数の確認がすべて追従する（どれかを書き忘れるとコンパイルエラー）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputLayout<T> {
+    // タイムスタンプ、エポックの番号、または範囲の終わり
+    pub timestamp: T,
+    // 行動ハッシュ、許可リストの根、またはソルト付きの行動ハッシュ
+    pub activity_hash: T,
+    pub commitment: T,
+    // 下限、または範囲の始まり
+    pub cutoff: T,
+    pub nullifier: T,
+    pub challenge: T,
+    pub user_id: T,
+    pub expiry: T,
+}
+
+impl<T> PublicInputLayout<T> {
+    // 公開入力の名前（回路に割り当てる順）
+    pub const FIELDS: &'static [&'static str] =
+        &["timestamp", "activity_hash", "commitment", "cutoff", "nullifier", "challenge", "user_id", "expiry"];
+
+    // 並びの順に1つずつ変換する（回路の変数の割り当てはこの順序で行われる）
+    pub fn try_map<U, E>(self, mut f: impl FnMut(T) -> Result<U, E>) -> Result<PublicInputLayout<U>, E> {
+        Ok(PublicInputLayout {
+            timestamp: f(self.timestamp)?,
+            activity_hash: f(self.activity_hash)?,
+            commitment: f(self.commitment)?,
+            cutoff: f(self.cutoff)?,
+            nullifier: f(self.nullifier)?,
+            challenge: f(self.challenge)?,
+            user_id: f(self.user_id)?,
+            expiry: f(self.expiry)?,
+        })
+    }
+
+    pub fn into_array(self) -> [T; NUM_PUBLIC_INPUTS] {
+        [
+            self.timestamp,
+            self.activity_hash,
+            self.commitment,
+            self.cutoff,
+            self.nullifier,
+            self.challenge,
+            self.user_id,
+            self.expiry,
+        ]
+    }
+}
+
+// zeroize機能では、手放すときに秘密入力（秘密値・ソルト・原像・ソルト付きの場合のソルト）を0で上書きする
+// 証明者は検査と証明で回路を複製するので、複製もそれぞれ手放すときに消える
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> Zeroize for ActivityCircuit<F> {
+    fn zeroize(&mut self) {
+        self.user_secret.zeroize();
+        self.salt.zeroize();
+        self.preimage.zeroize();
+        self.hash_salt.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> Drop for ActivityCircuit<F> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> ZeroizeOnDrop for ActivityCircuit<F> {}
+
+impl<F: PedersenField> ConstraintSynthesizer<F> for ActivityCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        self.synthesize(cs, &mut Vec::new())
+    }
+}
+
+// 診断用の、回路の制約のまとまりの名前（同じ名前のarkworksの名前空間で合成する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstraintLabel {
+    // チャレンジと利用者のIDを証明に束縛する制約
+    InputBinding,
+    // 許可リストの経路を辿って根に一致すること
+    AllowlistMembership,
+    // ソルト付きの行動ハッシュ、または原像から計算した行動ハッシュに一致すること
+    HashBinding,
+    // エポック・範囲の終わり・下限・有効期限とタイムスタンプの大小
+    TimestampRange,
+    // Pedersenコミットメントの開示
+    CommitmentOpen,
+    // nullifierの導出
+    Nullifier,
+}
+
+impl ConstraintLabel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConstraintLabel::InputBinding => "input_binding",
+            ConstraintLabel::AllowlistMembership => "allowlist_membership",
+            ConstraintLabel::HashBinding => "hash_binding",
+            ConstraintLabel::TimestampRange => "timestamp_range",
+            ConstraintLabel::CommitmentOpen => "commitment_open",
+            ConstraintLabel::Nullifier => "nullifier",
+        }
+    }
+}
+
+impl fmt::Display for ConstraintLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// まとまりの名前と、そのまとまりが追加した制約の番号の範囲
+type ConstraintGroup = (ConstraintLabel, Range<usize>);
+
+impl<F: PedersenField> ActivityCircuit<F> {
+    // 入力が満たさない制約のまとまりを、回路を合成した順に返す（すべて満たせば空）
+    // arkworksのwhich_is_unsatisfiedは最初の1つしか返さず、名前を得るにはConstraintLayerの設定が要るので、
+    // まとまりごとに記録した制約の番号の範囲と、行列と割り当てから評価した結果を突き合わせる。
+    // 名前空間の名前はラベルと同じなので、ConstraintLayerを設定すればarkworksのトレースにも同じ名前が現れる
+    pub fn unsatisfied_constraints(&self) -> Result<Vec<ConstraintLabel>, ZkError> {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let mut groups = Vec::new();
+        self.clone().synthesize(cs.clone(), &mut groups)?;
+        let unsatisfied = unsatisfied_indices(&cs)?;
+
+        let mut labels = Vec::new();
+        for (label, range) in groups {
+            if unsatisfied.iter().any(|index| range.contains(index)) && !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+        Ok(labels)
+    }
+
+    // 制約を合成し、まとまりごとに追加した制約の番号の範囲をgroupsに記録する
+    fn synthesize(self, cs: ConstraintSystemRef<F>, groups: &mut Vec<ConstraintGroup>) -> Result<(), SynthesisError> {
+        // 公開入力の割り当て（PublicInputLayoutの並びのまま先頭から割り当てる）
+        let PublicInputLayout {
+            timestamp: timestamp_slot,
+            activity_hash: hash_slot,
+            commitment,
+            cutoff,
+            nullifier,
+            challenge,
+            user_id,
+            expiry,
+        } = self.public_input_layout().try_map(|value| FpVar::new_input(cs.clone(), || Ok(value)))?;
+
+        // チャレンジと利用者のIDは他の値と関係しないが、公開入力である以上証明に束縛される
+        // 制約に一度も現れない変数を残さないよう、2乗を計算しておく
+        labeled(&cs, ns!(cs, "input_binding"), ConstraintLabel::InputBinding, groups, || {
+            let _ = challenge.square()?;
+            let _ = user_id.square()?;
+            Ok(())
+        })?;
+
+        // 秘密入力の割り当て
+        let user_secret = FpVar::new_witness(cs.clone(), || Ok(self.user_secret))?;
+        let salt = FpVar::new_witness(cs.clone(), || Ok(self.salt))?;
+
+        // 許可リストを使う場合は、秘密の行動ハッシュから経路を辿って公開された根に一致することを課す
+        // ソルト付きの場合は、秘密の行動ハッシュとソルトのPoseidonハッシュが公開された値に一致することを課す
+        let activity_hash = match (&self.allowlist, self.hash_salt) {
+            (Some(membership), _) => {
+                let namespace = ns!(cs, "allowlist_membership");
+                labeled(&cs, namespace, ConstraintLabel::AllowlistMembership, groups, || {
+                    let activity_hash = FpVar::new_witness(cs.clone(), || Ok(self.activity_hash))?;
+                    merkle_root_var(cs.clone(), activity_hash.clone(), &membership.path)?.enforce_equal(&hash_slot)?;
+                    Ok(activity_hash)
+                })?
+            }
+            (None, Some(hash_salt)) => labeled(&cs, ns!(cs, "hash_binding"), ConstraintLabel::HashBinding, groups, || {
+                let activity_hash = FpVar::new_witness(cs.clone(), || Ok(self.activity_hash))?;
+                let hash_salt = FpVar::new_witness(cs.clone(), || Ok(hash_salt))?;
+                poseidon_hash_var(cs.clone(), &[activity_hash.clone(), hash_salt])?.enforce_equal(&hash_slot)?;
+                Ok(activity_hash)
+            })?,
+            (None, None) => hash_slot,
+        };
+
+        // 原像を使う場合は、行動ハッシュが秘密の原像から計算した値に一致することを課す
+        if let Some(preimage) = &self.preimage {
+            labeled(&cs, ns!(cs, "hash_binding"), ConstraintLabel::HashBinding, groups, || {
+                activity_hash_var(cs.clone(), preimage)?.enforce_equal(&activity_hash)
+            })?;
+        }
+
+        labeled(&cs, ns!(cs, "timestamp_range"), ConstraintLabel::TimestampRange, groups, || {
+            // エポックを使う場合は、秘密のタイムスタンプが公開された番号のエポックに含まれることを課す
+            // start + index・length <= timestamp <= start + index・length + length - 1
+            // 範囲を使う場合は、秘密のタイムスタンプが公開された範囲の終わり以前であることを課す（始まりは下限の制約）
+            let timestamp = match (self.epoch, self.range_end) {
+                (Some(window), _) => {
+                    let timestamp = UInt64::new_witness(cs.clone(), || Ok(self.timestamp))?.to_fp()?;
+                    let first = &timestamp_slot * F::from(window.length) + FpVar::constant(F::from(window.start));
+                    let last = &first + FpVar::constant(F::from(window.length - 1));
+                    enforce_u64_geq(cs.clone(), &timestamp, &first, || {
+                        Ok(self.timestamp.wrapping_sub(window.first_second()))
+                    })?;
+                    enforce_u64_geq(cs.clone(), &last, &timestamp, || {
+                        Ok(window.last_second().wrapping_sub(self.timestamp))
+                    })?;
+                    timestamp
+                }
+                (None, Some(end)) => {
+                    let timestamp = UInt64::new_witness(cs.clone(), || Ok(self.timestamp))?.to_fp()?;
+                    enforce_u64_geq(cs.clone(), &timestamp_slot, &timestamp, || Ok(end.wrapping_sub(self.timestamp)))?;
+                    timestamp
+                }
+                (None, None) => timestamp_slot,
+            };
+
+            // timestamp >= cutoff を制約として課す（期間外の行動は証明できない）
+            enforce_u64_geq(cs.clone(), &timestamp, &cutoff, || {
+                Ok(self.timestamp.wrapping_sub(self.cutoff))
+            })?;
+
+            // timestamp <= expiry を制約として課す（期限より後の行動に対して期限付きの証明は作れない）
+            enforce_u64_geq(cs.clone(), &expiry, &timestamp, || Ok(self.expiry.wrapping_sub(self.timestamp)))
+        })?;
+
+        // commitment == Pedersen(user_secret, salt) を制約として課す（コミットメントの開示を知っていることの証明）
+        labeled(&cs, ns!(cs, "commitment_open"), ConstraintLabel::CommitmentOpen, groups, || {
+            let expected = PedersenCommitment::commit_var(cs.clone(), &user_secret, &salt)?;
+            commitment.enforce_equal(&expected)
+        })?;
+
+        // nullifier == Poseidon(user_secret, activity_hash) を制約として課す
+        labeled(&cs, ns!(cs, "nullifier"), ConstraintLabel::Nullifier, groups, || {
+            let expected = poseidon_hash_var(cs.clone(), &[user_secret, activity_hash])?;
+            nullifier.enforce_equal(&expected)
+        })
+    }
+}
+
+// 名前空間に入って制約のまとまりを合成し、追加された制約の番号の範囲を記録する
+fn labeled<F: PrimeField, T>(
+    cs: &ConstraintSystemRef<F>,
+    namespace: Namespace<F>,
+    label: ConstraintLabel,
+    groups: &mut Vec<ConstraintGroup>,
+    synthesize: impl FnOnce() -> Result<T, SynthesisError>,
+) -> Result<T, SynthesisError> {
+    let start = cs.num_constraints();
+    let value = synthesize()?;
+    drop(namespace);
+    groups.push((label, start..cs.num_constraints()));
+    Ok(value)
+}
+
+// 満たされない制約の番号をすべて、小さい順に返す（割り当てを持たないセットアップ用の合成ではエラー）
+// 行列の列は公開入力の変数、続いて秘密入力の変数の順なので、連結した割り当ての添字と一致する
+pub(crate) fn unsatisfied_indices<F: PrimeField>(cs: &ConstraintSystemRef<F>) -> Result<Vec<usize>, ZkError> {
+    cs.finalize();
+    let matrices = cs.to_matrices().ok_or(SynthesisError::AssignmentMissing)?;
+    let mut assignment = {
+        let cs = cs.borrow().ok_or(SynthesisError::AssignmentMissing)?;
+        [cs.instance_assignment.as_slice(), cs.witness_assignment.as_slice()].concat()
+    };
+    let eval = |row: &[(F, usize)]| row.iter().map(|(coeff, index)| *coeff * assignment[*index]).sum::<F>();
+    let indices = (0..matrices.num_constraints)
+        .filter(|&i| eval(&matrices.a[i]) * eval(&matrices.b[i]) != eval(&matrices.c[i]))
+        .collect();
+    wipe_assignment(&mut assignment);
+    Ok(indices)
+}
+
+// 秘密入力を含む割り当ての複製を0で上書きする（zeroize機能が無効なら何もしない）
+#[cfg_attr(not(feature = "zeroize"), allow(unused_variables))]
+pub(crate) fn wipe_assignment<F: PrimeField>(assignment: &mut [F]) {
+    #[cfg(feature = "zeroize")]
+    assignment.iter_mut().for_each(Zeroize::zeroize);
+}
+
+// 回路内でPoseidonハッシュを計算する
+pub(crate) fn poseidon_hash_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    inputs: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::new(cs, poseidon_config());
+    sponge.absorb(&inputs.to_vec())?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+// value >= lower を強制する（差分が64ビットに収まることを範囲チェックで示す）
+pub(crate) fn enforce_u64_geq<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    value: &FpVar<F>,
+    lower: &FpVar<F>,
+    diff: impl FnOnce() -> Result<u64, SynthesisError>,
+) -> Result<(), SynthesisError> {
+    let diff = UInt64::new_witness(cs, diff)?;
+    diff.to_fp()?.enforce_equal(&(value - lower))
+}
+
+// 回路内外で共通のPoseidonパラメータ（rate=2, alpha=5）。体ごとに一度だけ生成して使い回す
+pub fn poseidon_config<F: PrimeField>() -> &'static PoseidonConfig<F> {
+    cached_per_type(|| {
+        let (ark, mds) = find_poseidon_ark_and_mds::<F>(F::MODULUS_BIT_SIZE as u64, 2, 8, 57, 0);
+        PoseidonConfig::new(8, 57, 5, mds, ark, 2, 1)
+    })
+}
+
+// 型ごとに一度だけ値を生成して保持する（ジェネリックな関数ではstaticを型ごとに持てないため）
+// no_stdでも使えるよう、ロックではなく一度だけ書き込める枠（OnceBox）を連結リストにつないで持つ。
+// 複数のスレッドが同時に初期化すると両方が値を生成するが、先に枠に入った方だけを残すので、どのスレッドにも同じ値が返る
+pub(crate) fn cached_per_type<T: Any + Send + Sync>(init: impl FnOnce() -> T) -> &'static T {
+    struct Entry {
+        type_id: TypeId,
+        value: Box<dyn Any + Send + Sync>,
+        next: OnceBox<Entry>,
+    }
+    static HEAD: OnceBox<Entry> = OnceBox::new();
+
+    let find = |mut slot: &'static OnceBox<Entry>| {
+        while let Some(entry) = slot.get() {
+            if entry.type_id == TypeId::of::<T>() {
+                return Ok(entry.value.downcast_ref::<T>().expect("Entries are keyed by their type"));
+            }
+            slot = &entry.next;
+        }
+        // 末尾の空いた枠
+        Err(slot)
+    };
+
+    let mut slot = match find(&HEAD) {
+        Ok(value) => return value,
+        Err(slot) => slot,
+    };
+    let mut entry = Box::new(Entry {
+        type_id: TypeId::of::<T>(),
+        value: Box::new(init()),
+        next: OnceBox::new(),
+    });
+    // 他のスレッドが先に枠を埋めていたら、埋まった枠から探し直す（同じ型ならその値を使い、自分の値は捨てる）
+    loop {
+        match slot.set(entry) {
+            Ok(()) => return find(slot).unwrap_or_else(|_| unreachable!("The slot was just filled")),
+            Err(rejected) => match find(slot) {
+                Ok(value) => return value,
+                Err(empty) => (slot, entry) = (empty, rejected),
+            },
+        }
+    }
+}
+
+// 回路外でPoseidonハッシュを計算する（poseidon_hash_varと同じ計算）
+pub(crate) fn poseidon_hash<F: PrimeField + Absorb>(inputs: &[F]) -> F {
+    let mut sponge = PoseidonSponge::<F>::new(poseidon_config());
+    sponge.absorb(&inputs.to_vec());
+    sponge.squeeze_field_elements(1)[0]
+}
+
+// 回路外でnullifierを計算する（同じユーザーと行動なら常に同じ値になる）
+pub fn derive_nullifier<F: PrimeField + Absorb>(user_secret: F, activity_hash: F) -> F {
+    poseidon_hash(&[user_secret, activity_hash])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup};
+    use crate::{hash_activity, timestamp_to_field, ActivityData};
+
+    #[test]
+    fn test_satisfied_circuit_proof_verifies() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("valid_activity"),
+            user_secret: [2u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let circuit = ActivityCircuit::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            public_activity.cutoff,
+        )
+        .unwrap();
+
+        assert!(verifier.verify_proof(&proof, &circuit.public_inputs()).unwrap());
+    }
+
+    #[test]
+    fn test_public_inputs_match_circuit_allocation() {
+        let activity = ActivityData::new(Utc::now(), hash_activity("allocation"), [2u8; 32], [3u8; 32]);
+        let cutoff = activity.timestamp - Duration::days(30);
+        let circuit = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            cutoff,
+        )
+        .unwrap();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.clone().generate_constraints(cs.clone()).unwrap();
+        let instance = cs.borrow().unwrap().instance_assignment.clone();
+
+        // 先頭は定数1の変数で、その後に公開入力が同じ順序で並ぶ
+        assert_eq!(instance.len(), NUM_PUBLIC_INPUTS + 1);
+        assert_eq!(instance[1..], circuit.public_inputs());
+        assert_eq!(instance[1..], activity.public::<Fr>(cutoff).public_inputs().unwrap().to_vec());
+    }
+
+    #[test]
+    fn test_timestamp_to_field_matches_circuit_allocation() {
+        // Unix epoch、2038年問題の境界、32ビットを超える遠い未来、現在時刻
+        let dates = [
+            DateTime::UNIX_EPOCH,
+            DateTime::from_timestamp(i32::MAX as i64 + 1, 0).unwrap(),
+            DateTime::from_timestamp(1 << 40, 0).unwrap(),
+            Utc::now(),
+        ];
+        for date in dates {
+            let circuit =
+                ActivityCircuit::<Fr>::new(date, hash_activity("conversion"), [2u8; 32], [3u8; 32], date).unwrap();
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            let instance = cs.borrow().unwrap().instance_assignment.clone();
+
+            // 回路に割り当てたタイムスタンプと下限が、変換関数の値と一致する
+            let expected = timestamp_to_field::<Fr>(date).unwrap();
+            assert_eq!(instance[1], expected, "{}", date);
+            assert_eq!(instance[4], expected, "{}", date);
+        }
+
+        // 1970年より前の時刻はどちらの側でもエラー
+        let before_epoch = DateTime::from_timestamp(-1, 0).unwrap();
+        assert!(matches!(timestamp_to_field::<Fr>(before_epoch), Err(ZkError::PreEpochTimestamp)));
+    }
+
+    #[test]
+    fn test_tampered_witness_fails() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("valid_activity"),
+            user_secret: [2u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // 秘密値だけを書き換えるとコミットメントの制約を満たさない
+        let mut tampered = ActivityCircuit::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            activity.timestamp - Duration::days(30),
+        )
+        .unwrap();
+        tampered.user_secret = Fr::from_be_bytes_mod_order(&[3u8; 32]);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        tampered.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // 正規の証明も、別のコミットメントに対しては検証に失敗する
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let public_inputs = vec![
+            timestamp_to_field(activity.timestamp).unwrap(),
+            tampered.activity_hash,
+            PedersenCommitment::commit(tampered.user_secret, tampered.salt),
+            timestamp_to_field(public_activity.cutoff).unwrap(),
+            derive_nullifier(tampered.user_secret, tampered.activity_hash),
+            public_activity.challenge,
+            public_activity.user_id,
+            Fr::from(public_activity.expiry),
+        ];
+
+        assert!(!verifier.verify_proof(&proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn test_commitment_is_bound_to_user_secret() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("bound_commitment"), [4u8; 32], [5u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 公開入力の3番目は秘密値とソルトから導いたPedersenコミットメント
+        let user_secret = Fr::from_be_bytes_mod_order(&activity.user_secret);
+        let salt = Fr::from_be_bytes_mod_order(&activity.salt);
+        let mut public_inputs = public_activity.public_inputs().unwrap().to_vec();
+        assert_eq!(public_inputs[2], PedersenCommitment::commit(user_secret, salt));
+
+        // 秘密値と関係のない値をコミットメントとして主張しても、回路の制約を満たさない
+        let mut garbage = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            public_activity.cutoff,
+        )
+        .unwrap();
+        garbage.commitment = Fr::from(12345u64);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        garbage.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // 別のソルトから導いたコミットメントでは、正規の証明も検証に失敗する
+        public_inputs[2] = PedersenCommitment::commit(user_secret, salt + Fr::from(1u64));
+        assert!(!verifier.verify_proof(&proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn test_zero_commitment_is_rejected() {
+        let now = Utc::now();
+
+        // すべて0の秘密値は、ビルダーでも回路の作成でも拒否される
+        assert!(matches!(
+            ActivityData::builder().activity("walk").commitment([0u8; 32]).build(),
+            Err(ZkError::InvalidCommitment)
+        ));
+        assert!(matches!(
+            ActivityCircuit::<Fr>::new(now, hash_activity("walk"), [0u8; 32], [1u8; 32], now - Duration::days(1)),
+            Err(ZkError::InvalidCommitment)
+        ));
+
+        // ActivityData::newで作った場合も、証明の生成時に拒否される
+        let rng = &mut seeded_rng();
+        let (prover, _) = shared_test_setup();
+        let activity = ActivityData::new(now, hash_activity("walk"), [0u8; 32], [0u8; 32]);
+        assert!(matches!(
+            prover.prove_activity_with_rng(&activity, rng),
+            Err(ZkError::InvalidCommitment)
+        ));
+
+        // セットアップ用のダミーの値は0ではなく、それと分かるラベル付きの値
+        assert_ne!(SETUP_PLACEHOLDER_SECRET, [0u8; 32]);
+        assert!(SETUP_PLACEHOLDER_SECRET[1..].starts_with(b"zk_sample:setup-placeholder"));
+    }
+
+    #[test]
+    fn test_pre_epoch_timestamp_is_rejected() {
+        let before_epoch = DateTime::from_timestamp(-86_400, 0).unwrap();
+        assert_eq!(before_epoch.format("%Y").to_string(), "1969");
+
+        // u64へのキャストで巨大な値に化けず、エラーになる
+        assert!(matches!(
+            ActivityCircuit::<Fr>::new(before_epoch, [0u8; 32], [0u8; 32], [0u8; 32], before_epoch),
+            Err(ZkError::PreEpochTimestamp)
+        ));
+
+        let activity = ActivityData::new(before_epoch, hash_activity("before_epoch"), [2u8; 32], [0u8; 32]);
+        let public_activity = activity.public::<Fr>(before_epoch);
+        assert!(matches!(public_activity.public_inputs(), Err(ZkError::PreEpochTimestamp)));
+    }
+
+    #[test]
+    fn test_layout_matches_allocated_public_inputs() {
+        // 名前の数と、回路が実際に割り当てる公開変数の数が一致する
+        assert_eq!(PublicInputLayout::<Fr>::FIELDS.len(), NUM_PUBLIC_INPUTS);
+        assert_eq!(ActivityCircuit::<Fr>::metrics().public_inputs, NUM_PUBLIC_INPUTS);
+
+        // 名前の位置に、その名前の値が割り当てられる
+        let activity = ActivityData::new(Utc::now(), hash_activity("layout"), [2u8; 32], [3u8; 32]);
+        let cutoff = activity.timestamp - Duration::days(30);
+        let public_activity = activity.public::<Fr>(cutoff);
+        let circuit = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            cutoff,
+        )
+        .unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        let instance = cs.borrow().unwrap().instance_assignment.clone();
+        let slot = |name: &str| instance[1 + PublicInputLayout::<Fr>::FIELDS.iter().position(|f| *f == name).unwrap()];
+        assert_eq!(slot("timestamp"), timestamp_to_field::<Fr>(activity.timestamp).unwrap());
+        assert_eq!(slot("cutoff"), timestamp_to_field::<Fr>(cutoff).unwrap());
+        assert_eq!(slot("commitment"), public_activity.commitment);
+        assert_eq!(slot("nullifier"), public_activity.nullifier);
+    }
+
+    #[test]
+    fn test_metrics_are_nonzero_and_stable() {
+        let metrics = ActivityCircuit::<Fr>::metrics();
+        assert!(metrics.constraints > 0);
+        assert!(metrics.witness_variables > 0);
+        assert_eq!(metrics.public_inputs, NUM_PUBLIC_INPUTS);
+
+        // 回路の形は入力の値によらないので、何度数えても、実際の入力で合成しても同じになる
+        assert_eq!(ActivityCircuit::<Fr>::metrics(), metrics);
+        let activity = ActivityData::new(Utc::now(), hash_activity("metrics"), [4u8; 32], [5u8; 32]);
+        let circuit = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            activity.timestamp - Duration::days(30),
+        )
+        .unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert_eq!(cs.num_constraints(), metrics.constraints);
+        assert_eq!(cs.num_witness_variables(), metrics.witness_variables);
+    }
+
+    #[test]
+    fn test_unsatisfied_constraints_report_commitment_open() {
+        let now = Utc::now();
+        let mut circuit =
+            ActivityCircuit::<Fr>::new(now, hash_activity("diagnose"), [4u8; 32], [5u8; 32], now - Duration::days(30))
+                .unwrap();
+        assert!(circuit.unsatisfied_constraints().unwrap().is_empty());
+
+        // ソルトはコミットメントにしか使われないので、書き換えるとコミットメントの開示だけが満たされない
+        circuit.salt += Fr::from(1u64);
+        let labels = circuit.unsatisfied_constraints().unwrap();
+        assert_eq!(labels, vec![ConstraintLabel::CommitmentOpen]);
+        assert_eq!(labels[0].to_string(), "commitment_open");
+    }
+
+    #[test]
+    fn test_cached_per_type_initializes_once_across_threads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Barrier;
+
+        struct First(usize);
+        struct Second(usize);
+        static INITIALIZED: AtomicUsize = AtomicUsize::new(0);
+
+        // 同時に初期化しても、すべてのスレッドが同じ値を受け取る（捨てられた値は使われない）
+        let barrier = Barrier::new(8);
+        let values: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        let value = cached_per_type(|| First(INITIALIZED.fetch_add(1, Ordering::SeqCst)));
+                        value as *const First as usize
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert!(values.iter().all(|&value| value == values[0]));
+        let first = cached_per_type(|| First(usize::MAX));
+        assert_eq!(first as *const First as usize, values[0]);
+
+        // 型が違えば別の値になる
+        let second = cached_per_type(|| Second(first.0 + 1));
+        assert_eq!(second.0, first.0 + 1);
+        assert_eq!(cached_per_type(|| Second(0)).0, second.0);
+    }
+}
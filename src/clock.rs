@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+
+// 検証者が「今」とみなす時刻の取得元（信頼できる外部の時刻源を使う場合や、テストで時刻を固定する場合に差し替える）
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+// OSの時計（検証者のデフォルト）
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// 常に同じ時刻を返す時計（期間の境界ちょうどの時刻を、待たずに再現するテスト用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, SubsecRound};
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData, ZkError, DEFAULT_CLOCK_TOLERANCE, DEFAULT_FRESHNESS_WINDOW};
+
+    #[test]
+    fn test_fixed_clock_freshness_window_boundary() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        // 下限を行動の時刻そのものにして、鮮度と下限の境界を同じ時刻に揃える
+        let timestamp = Utc::now().trunc_subsecs(0);
+        let activity = ActivityData::new(timestamp, hash_activity("fixed_clock"), test_secret(45), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_with_cutoff(&activity, timestamp, rng).unwrap();
+        let verify_at = |now| {
+            let verifier = shared_test_setup().1.with_clock(FixedClock(now));
+            verifier.verify_activity(&public_activity, &proof).unwrap()
+        };
+
+        // 鮮度の期間と許容誤差をちょうど使い切った時点までは受理し、1秒後には古いとして拒否する
+        let last_accepted = timestamp + DEFAULT_FRESHNESS_WINDOW + DEFAULT_CLOCK_TOLERANCE;
+        let report = verify_at(last_accepted);
+        assert!(report.is_valid());
+        assert_eq!(report.checked_at, last_accepted);
+        let report = verify_at(last_accepted + Duration::seconds(1));
+        assert!(report.proof_valid);
+        assert!(!report.timestamp_fresh);
+        assert!(matches!(report.into_result(), Err(ZkError::StaleTimestamp)));
+
+        // 検証者の時計が許容誤差ちょうど遅れている時点までは受理し、それより前は未来の行動として拒否する
+        let first_accepted = timestamp - DEFAULT_CLOCK_TOLERANCE;
+        assert!(verify_at(first_accepted).is_valid());
+        let report = verify_at(first_accepted - Duration::seconds(1));
+        assert!(!report.timestamp_not_future);
+        assert!(matches!(report.into_result(), Err(ZkError::FutureTimestamp)));
+
+        // デフォルトの時計（OSの時計）では、証明した直後の証明を受理する
+        assert!(verifier.verify_activity(&public_activity, &proof).unwrap().is_valid());
+    }
+}
@@ -0,0 +1,61 @@
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_groth16::{PreparedVerifyingKey, Proof};
+
+// Groth16の検証式 e(A, B) = e(α, β)·e(Σ x_i·IC_i, γ)·e(C, δ) だけを確かめる（時刻もファイルも乱数も使わない）
+// オンチェーンなど制約の多い環境向けで、鮮度や下限の判定は上位のActivityVerifierが受け持つ
+// 公開入力の数が検証キーと合わなければfalse
+pub fn core_verify<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> bool {
+    let ic = &pvk.vk.gamma_abc_g1;
+    if public_inputs.len() + 1 != ic.len() {
+        return false;
+    }
+    let prepared_inputs = public_inputs
+        .iter()
+        .zip(&ic[1..])
+        .fold(ic[0].into_group(), |acc, (input, base)| acc + *base * input);
+
+    // γとδは前処理で符号を反転してあるので、3組のミラーループの積がe(α, β)になれば成立する
+    let miller_loop = E::multi_miller_loop(
+        [proof.a, prepared_inputs.into_affine(), proof.c],
+        [proof.b.into(), pvk.gamma_g2_neg_pc.clone(), pvk.delta_g2_neg_pc.clone()],
+    );
+    E::final_exponentiation(miller_loop).is_some_and(|output| output.0 == pvk.alpha_g1_beta_g2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData};
+
+    #[test]
+    fn test_core_verify_agrees_with_verify_activity() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("core_verify"), test_secret(49), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let pvk = &verifier.verifying_key;
+
+        // 正しい証明では、どちらも暗号の部分を受理する
+        let public_inputs = public_activity.public_inputs().unwrap().to_vec();
+        assert!(verifier.verify_activity(&public_activity, &proof).unwrap().proof_valid);
+        assert!(core_verify(pvk, &proof, &public_inputs));
+
+        // 公開入力を1つ書き換えると、どちらも拒否する
+        let mut tampered = public_activity.clone();
+        tampered.nullifier += Fr::from(1u64);
+        assert!(!verifier.verify_activity(&tampered, &proof).unwrap().proof_valid);
+        assert!(!core_verify(pvk, &proof, &tampered.public_inputs().unwrap().to_vec()));
+
+        // 公開入力の数が合わなければ、パニックせずにfalse
+        assert!(!core_verify(pvk, &proof, &public_inputs[1..]));
+    }
+}
@@ -0,0 +1,200 @@
+use alloc::vec::Vec;
+
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use ark_groth16::VerifyingKey;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate};
+use sha2::{Digest, Sha256};
+
+use crate::error::ZkError;
+use crate::serialization::{deserialize_proof_with_mode, proof_serialization_mode, serialize_proof_with_mode};
+
+// 封筒の形式のバージョン（フィールドの並びを変えたら上げる）
+pub const ENVELOPE_VERSION: u16 = 1;
+
+// 回路のIDを計算するときに検証キーの前に付けるドメインタグ
+const CIRCUIT_ID_DOMAIN: &[u8] = b"zk_sample/circuit_id/v1";
+
+// 検証キーから回路のIDを計算する（SHA256(ドメインタグ || 圧縮形式の検証キー)）
+// 同じ回路でもセットアップをやり直すと鍵が変わるので、IDは回路の形ではなく検証キーごとに決まる
+pub fn circuit_id<E: Pairing>(vk: &VerifyingKey<E>) -> [u8; 32] {
+    let mut bytes = Vec::with_capacity(vk.compressed_size());
+    // Vecへの書き込みは失敗しない
+    vk.serialize_compressed(&mut bytes)
+        .expect("Serializing into a Vec cannot fail");
+    Sha256::new()
+        .chain_update(CIRCUIT_ID_DOMAIN)
+        .chain_update(bytes)
+        .finalize()
+        .into()
+}
+
+// 証明と、検証に使う公開入力、どの検証キーで検証するかをまとめた1つのバイト列
+// 検証者は公開部分から公開入力を組み立て直さずに済み、証明者と検証者で組み立て方がずれる心配がない
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofEnvelope<E: Pairing = Bn254> {
+    pub version: u16,
+    pub public_inputs: Vec<E::ScalarField>,
+    pub proof: ark_groth16::Proof<E>,
+    pub circuit_id: [u8; 32],
+}
+
+impl<E: Pairing> ProofEnvelope<E> {
+    pub fn new(circuit_id: [u8; 32], public_inputs: Vec<E::ScalarField>, proof: ark_groth16::Proof<E>) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            public_inputs,
+            proof,
+            circuit_id,
+        }
+    }
+
+    // バージョン（2バイト）、回路のID（32バイト）、長さ付きの公開入力、圧縮形式の証明の順に並べる
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with_mode(Compress::Yes)
+    }
+
+    // 証明の点の形式を指定して並べる（公開入力は体の元なので形式によらない）
+    // 形式は証明の部分の長さで決まるので、from_bytesはどちらの形式もそのまま読める
+    pub fn to_bytes_with_mode(&self, compress: Compress) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // Vecへの書き込みは失敗しない
+        self.version
+            .serialize_compressed(&mut bytes)
+            .and_then(|()| self.circuit_id.serialize_compressed(&mut bytes))
+            .and_then(|()| self.public_inputs.serialize_compressed(&mut bytes))
+            .expect("Serializing into a Vec cannot fail");
+        bytes.extend_from_slice(&serialize_proof_with_mode(&self.proof, compress));
+        bytes
+    }
+
+    // バイト列から封筒を復元する（公開入力が体の元として正規であること、証明の点が曲線上にあることも検証する）
+    // 証明は最後に置くので、残りの長さから形式を判別でき、deserialize_proofで余分なバイトと正規でない表現も拒否できる
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        let mut reader = bytes;
+        let version = u16::deserialize_compressed(&mut reader)?;
+        if version != ENVELOPE_VERSION {
+            return Err(ZkError::EnvelopeVersionMismatch {
+                expected: ENVELOPE_VERSION,
+                found: version,
+            });
+        }
+        let circuit_id = <[u8; 32]>::deserialize_compressed(&mut reader)?;
+
+        // 長さを書き換えた封筒で巨大な領域を確保しないよう、残りのバイト数に収まらない長さは先に拒否する
+        let len = u64::deserialize_compressed(&mut reader)?;
+        let field_size = E::ScalarField::default().compressed_size();
+        if len > (reader.len() / field_size) as u64 {
+            return Err(SerializationError::InvalidData.into());
+        }
+        let public_inputs = (0..len)
+            .map(|_| E::ScalarField::deserialize_with_mode(&mut reader, Compress::Yes, Validate::Yes))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // どちらの長さでもなければ、圧縮形式として読んだときのエラーを返す
+        let compress = proof_serialization_mode::<E>(reader).unwrap_or(Compress::Yes);
+        Ok(Self {
+            version,
+            public_inputs,
+            proof: deserialize_proof_with_mode(reader, compress)?,
+            circuit_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData, Setup};
+
+    #[test]
+    fn test_envelope_round_trip_verifies() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("envelope"), test_secret(27), [0u8; 32]);
+        assert_eq!(prover.circuit_id(), verifier.circuit_id());
+
+        // 1つのバイト列から、公開入力を組み立て直さずに検証できる
+        let envelope = prover.prove_envelope_with_rng(&activity, rng).unwrap();
+        let bytes = envelope.to_bytes();
+        let restored = ProofEnvelope::<Bn254>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored, envelope);
+        assert!(verifier.verify_envelope(&restored).unwrap());
+
+        // 途中で切れたもの、余分なバイトが付いたもの、知らないバージョンは読めない
+        assert!(ProofEnvelope::<Bn254>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(matches!(ProofEnvelope::<Bn254>::from_bytes(&extended), Err(ZkError::TrailingBytes(1))));
+        let mut future = bytes;
+        future[0] = 2;
+        assert!(matches!(
+            ProofEnvelope::<Bn254>::from_bytes(&future),
+            Err(ZkError::EnvelopeVersionMismatch { expected: 1, found: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_uncompressed_envelope_carries_its_mode() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("envelope"), test_secret(47), [0u8; 32]);
+        let envelope = prover.prove_envelope_with_rng(&activity, rng).unwrap();
+
+        // 圧縮しない形式の封筒は証明の分だけ大きく、形式を指定せずに読み戻せる
+        let compressed = envelope.to_bytes();
+        let uncompressed = envelope.to_bytes_with_mode(Compress::No);
+        assert_eq!(uncompressed.len() - compressed.len(), 128);
+        let restored = ProofEnvelope::<Bn254>::from_bytes(&uncompressed).unwrap();
+        assert_eq!(restored, envelope);
+        assert!(verifier.verify_envelope(&restored).unwrap());
+        assert!(ProofEnvelope::<Bn254>::from_bytes(&uncompressed[..uncompressed.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_envelope_for_other_circuit_is_rejected() {
+        let rng = &mut seeded_rng();
+        let (prover, _) = Setup::generate_with_rng(rng);
+        let (_, other_verifier) = Setup::generate_with_rng(rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("envelope"), test_secret(27), [0u8; 32]);
+
+        // 別のセットアップの検証キーでは、証明を検証する前にIDの不一致として拒否する
+        let envelope = prover.prove_envelope_with_rng(&activity, rng).unwrap();
+        assert_ne!(envelope.circuit_id, other_verifier.circuit_id());
+        assert!(matches!(other_verifier.verify_envelope(&envelope), Err(ZkError::CircuitMismatch)));
+    }
+
+    #[test]
+    fn test_envelope_uses_the_verifiers_allowlist_and_clock() {
+        let rng = &mut seeded_rng();
+        let allowed = [hash_activity("walk"), hash_activity("run"), hash_activity("swim")];
+        let (prover, verifier) = Setup::<Bn254>::generate_with_allowlist(&allowed, rng);
+        let now = Utc::now();
+        let activity = ActivityData::new(now, hash_activity("run"), test_secret(48), [0u8; 32]);
+        let envelope = prover.prove_envelope_with_rng(&activity, rng).unwrap();
+        assert!(verifier.verify_envelope_with_clock(&envelope, now).unwrap());
+
+        // 証明者が自分で作った許可リストの根を封筒に入れても、検証者の根で検証するので受理されない
+        let own = [hash_activity("dance"), hash_activity("sing"), hash_activity("sleep")];
+        let self_rooted = prover.with_allowlist(&own);
+        let activity = ActivityData::new(now, hash_activity("dance"), test_secret(48), [0u8; 32]);
+        let envelope = self_rooted.prove_envelope_with_rng(&activity, rng).unwrap();
+        assert!(!verifier.verify_envelope_with_clock(&envelope, now).unwrap());
+    }
+
+    #[test]
+    fn test_stale_envelope_is_rejected() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now();
+        let activity = ActivityData::new(now, hash_activity("envelope"), test_secret(49), [0u8; 32]);
+        let envelope = prover.prove_envelope_with_rng(&activity, rng).unwrap();
+
+        // 証明としては正しくても、検証者の時計で古い行動や下限は受理しない
+        assert!(verifier.verify_envelope_with_clock(&envelope, now).unwrap());
+        assert!(!verifier.verify_envelope_with_clock(&envelope, now + chrono::Duration::days(60)).unwrap());
+    }
+}
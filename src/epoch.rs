@@ -0,0 +1,217 @@
+use ark_ff::{BigInteger, PrimeField};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::activity::{unix_seconds, ActivityData};
+use crate::error::ZkError;
+
+// 正確な時刻の代わりに公開する時間の区切り（start から length ごとに 0, 1, 2, ... と番号を振る）
+// 区切りは回路の定数になるので、同じ設定で生成した鍵でしか検証できない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochConfig {
+    pub start: DateTime<Utc>,
+    pub length: Duration,
+}
+
+impl EpochConfig {
+    // 行動が属するエポックの番号（検証者に公開されるのはこの値だけ）
+    pub fn public_epoch(&self, data: &ActivityData) -> Result<u64, ZkError> {
+        Ok(self.window(data.timestamp)?.index)
+    }
+
+    // エポックの開始時刻
+    pub fn epoch_start(&self, index: u64) -> Result<DateTime<Utc>, ZkError> {
+        let (start, length) = self.seconds()?;
+        index
+            .checked_mul(length)
+            .and_then(|offset| offset.checked_add(start))
+            .and_then(|seconds| DateTime::from_timestamp(i64::try_from(seconds).ok()?, 0))
+            .ok_or(ZkError::InvalidEpoch)
+    }
+
+    // 時刻が属するエポック（開始より前の時刻はどのエポックにも属さない）
+    pub(crate) fn window(&self, timestamp: DateTime<Utc>) -> Result<EpochWindow, ZkError> {
+        let (start, length) = self.seconds()?;
+        let offset = unix_seconds(timestamp)?
+            .checked_sub(start)
+            .ok_or(ZkError::InvalidEpoch)?;
+        Ok(EpochWindow {
+            index: offset / length,
+            start,
+            length,
+        })
+    }
+
+    // セットアップ用のエポック（回路の形は開始時刻と長さだけで決まる）
+    pub(crate) fn blank_window(&self) -> Result<EpochWindow, ZkError> {
+        let (start, length) = self.seconds()?;
+        Ok(EpochWindow { index: 0, start, length })
+    }
+
+    // 開始時刻と長さをUnix秒で返す（長さは1秒以上）
+    fn seconds(&self) -> Result<(u64, u64), ZkError> {
+        let length = u64::try_from(self.length.num_seconds())
+            .ok()
+            .filter(|length| *length > 0)
+            .ok_or(ZkError::InvalidEpoch)?;
+        Ok((unix_seconds(self.start)?, length))
+    }
+}
+
+// 検証に通った証明のエポックの番号（証明に束縛された公開入力から読み取ったもの）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EpochIndex(pub u64);
+
+impl EpochIndex {
+    // 公開入力のエポックの番号を読む（64ビットに収まらない値は回路の範囲チェックを通らないのでNone）
+    pub(crate) fn from_field<F: PrimeField>(value: F) -> Option<Self> {
+        let value = value.into_bigint();
+        (value.num_bits() <= 64).then(|| Self(value.as_ref()[0]))
+    }
+}
+
+// 回路に渡すエポック（番号は公開入力、開始時刻と長さは回路の定数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochWindow {
+    pub index: u64,
+    pub start: u64,
+    pub length: u64,
+}
+
+impl EpochWindow {
+    // エポックに含まれる最初と最後の時刻（Unix秒）
+    pub(crate) fn first_second(&self) -> u64 {
+        self.start + self.index * self.length
+    }
+
+    pub(crate) fn last_second(&self) -> u64 {
+        self.first_second() + self.length - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fr};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+    use crate::circuit::ActivityCircuit;
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityVerifier, Setup};
+
+    // 1日ごとのエポック
+    fn daily() -> EpochConfig {
+        EpochConfig {
+            start: DateTime::UNIX_EPOCH,
+            length: Duration::days(1),
+        }
+    }
+
+    #[test]
+    fn test_same_epoch_activities_are_unlinkable() {
+        let rng = &mut seeded_rng();
+        let config = daily();
+        let (prover, verifier) = Setup::<Bn254>::generate_with_epoch(config, rng).unwrap();
+
+        // 今日のエポック内の異なる時刻の2つの行動
+        let now = Utc::now();
+        let today = config.epoch_start(config.window(now).unwrap().index).unwrap();
+        let first = ActivityData::new(today, hash_activity("walk"), test_secret(31), [1u8; 32]);
+        let second = ActivityData::new(today + (now - today) / 2, hash_activity("walk"), test_secret(32), [2u8; 32]);
+        assert_eq!(config.public_epoch(&first).unwrap(), config.public_epoch(&second).unwrap());
+
+        let (public_first, proof_first) = prover.prove_activity_with_rng(&first, rng).unwrap();
+        let (public_second, proof_second) = prover.prove_activity_with_rng(&second, rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_first, &proof_first));
+        assert!(verifier.verify_activity_bool(&public_second, &proof_second));
+
+        // 公開されるのはエポックの開始時刻だけで、コミットメントとナリファイアからは結び付けられない
+        assert_eq!(public_first.timestamp, today);
+        assert_eq!(public_second.timestamp, today);
+        assert_ne!(public_first.commitment, public_second.commitment);
+        assert_ne!(public_first.nullifier, public_second.nullifier);
+
+        // エポックを設定していない検証者は、開始時刻をそのまま公開入力にするので受理しない
+        let plain_verifier = ActivityVerifier::from_prepared_key(verifier.verifying_key.clone());
+        assert!(!plain_verifier.verify_activity(&public_first, &proof_first).unwrap().proof_valid);
+    }
+
+    #[test]
+    fn test_verified_report_carries_epoch_index() {
+        let rng = &mut seeded_rng();
+        let config = daily();
+        let (prover, verifier) = Setup::<Bn254>::generate_with_epoch(config, rng).unwrap();
+
+        // 検証に通った報告には、今日のエポックの番号が入る
+        let now = Utc::now();
+        let activity = ActivityData::new(now, hash_activity("ride"), test_secret(34), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap();
+        let expected = (now - DateTime::UNIX_EPOCH).num_days() as u64;
+        assert_eq!(report.epoch, Some(EpochIndex(expected)));
+        assert_eq!(report.epoch, Some(EpochIndex(config.public_epoch(&activity).unwrap())));
+
+        // 公開部分の時刻を前日にずらすと、公開入力のエポックが変わり証明が通らないので番号も返さない
+        let mut forged = public_activity.clone();
+        forged.timestamp = public_activity.timestamp - Duration::days(1);
+        let report = verifier.verify_activity_with_clock(&forged, &proof, now).unwrap();
+        assert!(!report.proof_valid);
+        assert_eq!(report.epoch, None);
+
+        // エポックを使わない検証者の報告には番号がない
+        let (plain_prover, plain_verifier) = shared_test_setup();
+        let (public_activity, proof) = plain_prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let report = plain_verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.epoch, None);
+    }
+
+    #[test]
+    fn test_timestamp_outside_claimed_epoch_is_unsatisfied() {
+        let config = daily();
+        let now = Utc::now();
+        let activity = ActivityData::new(now, hash_activity("run"), test_secret(33), [0u8; 32]);
+        let window = config.window(now).unwrap();
+
+        let is_satisfied = |window: EpochWindow| {
+            let mut circuit = ActivityCircuit::<Fr>::new(
+                activity.timestamp,
+                activity.activity_hash,
+                activity.user_secret,
+                activity.salt,
+                now - Duration::days(30),
+            )
+            .unwrap();
+            circuit.epoch = Some(window);
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+
+        // 正しいエポックでだけ制約を満たし、前後のエポックを主張することはできない
+        assert!(is_satisfied(window));
+        assert!(!is_satisfied(EpochWindow { index: window.index - 1, ..window }));
+        assert!(!is_satisfied(EpochWindow { index: window.index + 1, ..window }));
+    }
+
+    #[test]
+    fn test_invalid_epoch_configuration() {
+        let activity = ActivityData::new(DateTime::UNIX_EPOCH, hash_activity("swim"), [0u8; 32], [0u8; 32]);
+
+        // 開始より前の時刻と、長さが1秒未満の設定はエラー
+        let later_start = EpochConfig {
+            start: DateTime::UNIX_EPOCH + Duration::days(1),
+            ..daily()
+        };
+        assert!(matches!(later_start.public_epoch(&activity), Err(ZkError::InvalidEpoch)));
+        let empty = EpochConfig {
+            length: Duration::zero(),
+            ..daily()
+        };
+        assert!(matches!(empty.public_epoch(&activity), Err(ZkError::InvalidEpoch)));
+        assert!(matches!(
+            Setup::<Bn254>::generate_with_epoch(empty, &mut seeded_rng()),
+            Err(ZkError::InvalidEpoch)
+        ));
+        assert_eq!(daily().public_epoch(&activity).unwrap(), 0);
+    }
+}
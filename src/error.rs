@@ -0,0 +1,172 @@
+use core::fmt;
+
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+
+// 証明の生成・検証に関するエラー
+#[derive(Debug)]
+pub enum ZkError {
+    // 回路の合成や証明システム内部のエラー
+    Synthesis(SynthesisError),
+    // 証明のシリアライズ・デシリアライズのエラー
+    Serialization(SerializationError),
+    // 証明の後ろに余分なバイトが付いている
+    TrailingBytes(usize),
+    // 入力が回路の制約を満たさない
+    Unsatisfied,
+    // check_satisfiedで見つけた、最初に満たされない制約の番号（回路を合成した順）
+    UnsatisfiedConstraint(usize),
+    // Unix epochより前の時刻はu64の公開入力に変換できない
+    PreEpochTimestamp,
+    // 行動のタイムスタンプが古すぎる
+    StaleTimestamp,
+    // 証明に束縛された下限が検証者の時計から見て古すぎる
+    StaleCutoff,
+    // 証明に束縛された有効期限を検証者の時計で過ぎている（または行動より前の期限で証明しようとした）
+    ExpiredProof,
+    // 公開入力の数が検証キーと一致しない
+    PublicInputArity { expected: usize, got: usize },
+    // 証明が公開入力に対して正しくない
+    VerificationFailed,
+    // ActivityDataのビルダーで必須の値が設定されていない
+    MissingField(&'static str),
+    // ユーザーの秘密値（コミットメントの元）がすべて0で、コミットメントが何も隠さない
+    InvalidCommitment,
+    // 行動のタイムスタンプが現在時刻より許容誤差を超えて未来
+    FutureTimestamp,
+    // 16進文字列として読めない
+    InvalidHex,
+    // 16進文字列が32バイトではない（値は実際のバイト数）
+    InvalidHashLength(usize),
+    // 行動ハッシュが許可リストに含まれていない
+    NotAllowlisted,
+    // 原像の長さが鍵を生成したときの長さと一致しない
+    PreimageLength { expected: usize, got: usize },
+    // エポックの長さが1秒未満、または時刻がエポックの開始より前
+    InvalidEpoch,
+    // 保存した鍵が別のバージョンの回路で生成されている
+    KeyVersionMismatch { expected: u16, found: u16 },
+    // 範囲の始まりが終わりより後、またはエポックを設定した証明者で範囲を証明しようとした
+    InvalidRange,
+    // 履歴の葉の番号が範囲外（値は番号と葉の数）
+    HistoryIndex { index: usize, len: usize },
+    // snarkjs形式の証明のJSONとして読めない（値は問題のあったキーや理由）
+    InvalidProofJson(&'static str),
+    // 証明の生成が期限までに終わらなかった
+    Timeout,
+    // 行動のソルトの有無が、鍵を生成したときの回路（ソルト付きかどうか）と一致しない
+    SaltedHashMismatch,
+    // 証明の封筒が別の形式のバージョンで作られている
+    EnvelopeVersionMismatch { expected: u16, found: u16 },
+    // 証明の封筒の回路のIDが検証キーと一致しない
+    CircuitMismatch,
+    // 時刻に期間を足し引きした結果が、DateTimeで表せる範囲を超えた
+    TimeArithmetic,
+    // エポックを設定した証明者・検証者で、ブロック高で証明・検証しようとした
+    BlockWithEpoch,
+    // 証明の短い文字列表現がbase64url（パディングなし）として読めない
+    InvalidBase64,
+    // 集約する証明の数が2以上の2のべき乗でない、SRSの大きさを超える、または公開入力の数と合わない（値は証明の数）
+    AggregationSize(usize),
+    // 公開部分の形式のバージョンが、検証者が受け付ける最も古いバージョンより古い
+    InputVersionRejected { minimum: u16, found: u16 },
+    // 同じ利用者であることを証明しようとした2つの行動の秘密値が異なる（集約の証明では、秘密値かソルトが異なる）
+    DifferentUsers,
+    // 32バイトの値が体の位数以上で、体の元にすると簡約される
+    NonCanonicalField,
+    // 検証者のビルダーで、組み合わせられない設定や範囲外の値が指定された（値は理由）
+    InvalidVerifierConfig(&'static str),
+    // 秘密値かソルトが埋め込み曲線の部分群の位数以上（コミットメントは位数を法とした値しか区別できない）
+    SecretOutOfRange,
+    // 範囲の証明・検証の有無が、鍵を生成したときの回路（範囲付きかどうか）と一致しない
+    RangeMismatch,
+    // 証明が示す件数の閾値が、検証者の求める件数より小さい
+    ThresholdTooLow { required: u64, got: u64 },
+}
+
+impl fmt::Display for ZkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZkError::Synthesis(e) => write!(f, "Constraint system error: {}", e),
+            ZkError::Serialization(e) => write!(f, "Serialization error: {}", e),
+            ZkError::TrailingBytes(n) => write!(f, "Unexpected {} trailing bytes after proof", n),
+            ZkError::Unsatisfied => write!(f, "Inputs do not satisfy the circuit constraints"),
+            ZkError::UnsatisfiedConstraint(index) => {
+                write!(f, "Inputs do not satisfy circuit constraint #{}", index)
+            }
+            ZkError::PreEpochTimestamp => write!(f, "Timestamp is before the Unix epoch"),
+            ZkError::StaleTimestamp => write!(f, "Activity timestamp is outside the freshness window"),
+            ZkError::StaleCutoff => write!(f, "Proven cutoff is older than the verifier tolerates"),
+            ZkError::ExpiredProof => write!(f, "Proof has passed its expiry"),
+            ZkError::PublicInputArity { expected, got } => {
+                write!(f, "Expected {} public inputs, got {}", expected, got)
+            }
+            ZkError::VerificationFailed => write!(f, "Proof verification failed"),
+            ZkError::MissingField(name) => write!(f, "Activity data is missing the {} field", name),
+            ZkError::InvalidCommitment => write!(f, "User secret must not be all zeros"),
+            ZkError::FutureTimestamp => write!(f, "Activity timestamp is in the future"),
+            ZkError::InvalidHex => write!(f, "Value is not valid hex"),
+            ZkError::InvalidHashLength(n) => write!(f, "Expected 32 bytes of hex, got {}", n),
+            ZkError::NotAllowlisted => write!(f, "Activity hash is not in the allowlist"),
+            ZkError::PreimageLength { expected, got } => {
+                write!(f, "Expected a {}-byte activity preimage, got {} bytes", expected, got)
+            }
+            ZkError::InvalidEpoch => write!(f, "Timestamp is not covered by the epoch configuration"),
+            ZkError::KeyVersionMismatch { expected, found } => {
+                write!(f, "Key was generated for circuit version {}, expected {}", found, expected)
+            }
+            ZkError::InvalidRange => write!(f, "Time range is empty or combined with an epoch configuration"),
+            ZkError::HistoryIndex { index, len } => {
+                write!(f, "History leaf {} is out of range for {} leaves", index, len)
+            }
+            ZkError::InvalidProofJson(what) => write!(f, "Invalid snarkjs proof JSON: {}", what),
+            ZkError::Timeout => write!(f, "Proof generation did not finish before the deadline"),
+            ZkError::SaltedHashMismatch => {
+                write!(f, "Activity salt must be set exactly when the key was generated for salted hashes")
+            }
+            ZkError::EnvelopeVersionMismatch { expected, found } => {
+                write!(f, "Proof envelope has format version {}, expected {}", found, expected)
+            }
+            ZkError::CircuitMismatch => write!(f, "Proof envelope was made for a different verifying key"),
+            ZkError::TimeArithmetic => write!(f, "Time arithmetic overflowed the supported date range"),
+            ZkError::BlockWithEpoch => write!(f, "Block heights cannot be combined with an epoch configuration"),
+            ZkError::InvalidBase64 => write!(f, "Value is not valid unpadded base64url"),
+            ZkError::AggregationSize(n) => write!(f, "Cannot aggregate {} proofs with these public inputs and SRS", n),
+            ZkError::InputVersionRejected { minimum, found } => {
+                write!(f, "Public inputs of version {} are older than the minimum accepted version {}", found, minimum)
+            }
+            ZkError::DifferentUsers => write!(f, "Activities belong to different users"),
+            ZkError::NonCanonicalField => write!(f, "Value is not below the field modulus and would be reduced"),
+            ZkError::InvalidVerifierConfig(reason) => write!(f, "Invalid verifier configuration: {}", reason),
+            ZkError::SecretOutOfRange => write!(f, "Secret or salt is not below the commitment group order"),
+            ZkError::RangeMismatch => {
+                write!(f, "Range proofs must be used exactly when the key was generated for ranges")
+            }
+            ZkError::ThresholdTooLow { required, got } => {
+                write!(f, "Proof shows at least {} activities but {} are required", got, required)
+            }
+        }
+    }
+}
+
+impl ark_std::error::Error for ZkError {
+    fn source(&self) -> Option<&(dyn ark_std::error::Error + 'static)> {
+        match self {
+            ZkError::Synthesis(e) => Some(e),
+            ZkError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SynthesisError> for ZkError {
+    fn from(e: SynthesisError) -> Self {
+        ZkError::Synthesis(e)
+    }
+}
+
+impl From<SerializationError> for ZkError {
+    fn from(e: SerializationError) -> Self {
+        ZkError::Serialization(e)
+    }
+}
@@ -0,0 +1,82 @@
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+// 行動ハッシュに使うハッシュ関数（hash_activity_withで選ぶ）
+// 回路内で原像を確かめるガジェットがあるのはSha256Hasherだけなので、原像付きの証明ではSha256Hasherを使うこと
+pub trait ActivityHasher {
+    fn hash(input: &[u8]) -> [u8; 32];
+}
+
+// SHA-256（デフォルト。hash_activityはこれを使う）
+// 例: "abc" -> ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl ActivityHasher for Sha256Hasher {
+    fn hash(input: &[u8]) -> [u8; 32] {
+        Sha256::digest(input).into()
+    }
+}
+
+// Keccak-256（EVMのkeccak256と同じ。NISTのSHA3-256とはパディングが異なり、値も違う）
+// 例: "abc" -> 4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keccak256Hasher;
+
+impl ActivityHasher for Keccak256Hasher {
+    fn hash(input: &[u8]) -> [u8; 32] {
+        Keccak256::digest(input).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::PrimeField;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use crate::preimage::activity_hash_var;
+    use crate::{hash_activity, hash_activity_with, hex_to_hash};
+
+    #[test]
+    fn test_hashers_match_documented_digests() {
+        let expected = [
+            (
+                Sha256Hasher::hash(b"abc"),
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                Sha256Hasher::hash(b""),
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                Keccak256Hasher::hash(b"abc"),
+                "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+            ),
+            (
+                Keccak256Hasher::hash(b""),
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            ),
+        ];
+        for (digest, hex) in expected {
+            assert_eq!(digest, hex_to_hash(hex).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_hashers_share_the_activity_domain() {
+        // hash_activityはSHA-256版と同じで、回路内のガジェットとも一致する
+        let sha256 = hash_activity_with::<Sha256Hasher>("walk");
+        assert_eq!(sha256, hash_activity("walk"));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = activity_hash_var(cs, b"walk").unwrap().value().unwrap();
+        assert_eq!(value, Fr::from_be_bytes_mod_order(&sha256));
+
+        // Keccak-256版もドメインのタグを付けてからハッシュする
+        let keccak = hash_activity_with::<Keccak256Hasher>("walk");
+        assert_eq!(keccak, Keccak256Hasher::hash(b"zk_sample:activity:v1walk"));
+        assert_ne!(keccak, sha256);
+    }
+}
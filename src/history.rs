@@ -0,0 +1,449 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ark_bn254::{Bn254, Fr};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof};
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, uint64::UInt64};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::activity::{check_not_future, checked_sub, hash_to_field_be, unix_seconds, ActivityData};
+use crate::circuit::{enforce_u64_geq, poseidon_hash, poseidon_hash_var};
+use crate::error::ZkError;
+use crate::merkle::{build_tree_from_leaves, merkle_root_and_index_var, MerklePath};
+use crate::pedersen::{PedersenCommitment, PedersenField};
+use crate::verifier::{DEFAULT_CLOCK_TOLERANCE, DEFAULT_FRESHNESS_WINDOW};
+
+// 公開入力の数（根と期間の始まり・終わり、利用者のコミットメント）
+pub const NUM_HISTORY_PUBLIC_INPUTS: usize = 4;
+
+// 履歴の葉（Poseidon(秘密値, 行動ハッシュ, Unix秒)）
+// 時刻も葉に含めるので木の根が時刻ごと束縛し、秘密値を含めるので秘密値を知る利用者しか葉を作れない
+pub fn history_leaf<F: PrimeField + Absorb>(user_secret: &[u8; 32], activity_hash: &[u8; 32], timestamp: u64) -> F {
+    poseidon_hash(&[hash_to_field_be(user_secret), hash_to_field_be(activity_hash), F::from(timestamp)])
+}
+
+// ユーザーが積み上げていく、時刻付きの行動のMerkle木（根だけを検証者に渡す）
+#[derive(Debug, Clone)]
+pub struct ActivityHistory<F: PrimeField = Fr> {
+    entries: Vec<([u8; 32], u64)>,
+    root: F,
+    paths: Vec<MerklePath<F>>,
+    user_secret: F,
+    salt: F,
+}
+
+impl<F: PedersenField> ActivityHistory<F> {
+    // 行動はすべて同じ秘密値とソルトを持つ必要がある（異なればDifferentUsers、行動がなければMissingField）
+    // すべて0の秘密値ではInvalidCommitment、秘密値かソルトが部分群の位数以上ならSecretOutOfRange
+    pub fn new(activities: &[ActivityData]) -> Result<Self, ZkError> {
+        let first = activities.first().ok_or(ZkError::MissingField("activities"))?;
+        if activities.iter().any(|activity| activity.user_secret != first.user_secret || activity.salt != first.salt) {
+            return Err(ZkError::DifferentUsers);
+        }
+        if first.user_secret == [0u8; 32] {
+            return Err(ZkError::InvalidCommitment);
+        }
+        let user_secret = hash_to_field_be(&first.user_secret);
+        let salt = hash_to_field_be(&first.salt);
+        PedersenCommitment::check_opening(user_secret, salt)?;
+
+        let entries = activities
+            .iter()
+            .map(|activity| Ok((activity.activity_hash, unix_seconds(activity.timestamp)?)))
+            .collect::<Result<Vec<_>, ZkError>>()?;
+        let leaves = entries
+            .iter()
+            .map(|(hash, timestamp)| history_leaf(&first.user_secret, hash, *timestamp))
+            .collect();
+        let (root, paths) = build_tree_from_leaves(leaves);
+        Ok(Self {
+            entries,
+            root,
+            paths,
+            user_secret,
+            salt,
+        })
+    }
+
+    pub fn root(&self) -> F {
+        self.root
+    }
+
+    // 履歴の持ち主のコミットメント（Pedersen(秘密値, ソルト)。ActivityCircuitの公開コミットメントと同じ値）
+    pub fn commitment(&self) -> F {
+        PedersenCommitment::commit(self.user_secret, self.salt)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // 木の深さ（回路の形はこの深さとKで決まる）
+    pub fn depth(&self) -> usize {
+        self.entries.len().max(1).next_power_of_two().trailing_zeros() as usize
+    }
+
+    // 期間内（start <= timestamp <= end）の葉の番号
+    pub fn indices_in_window(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<usize>, ZkError> {
+        let (start, end) = (unix_seconds(start)?, unix_seconds(end)?);
+        Ok((0..self.entries.len())
+            .filter(|index| (start..=end).contains(&self.entries[*index].1))
+            .collect())
+    }
+}
+
+// 履歴の木に、期間内の時刻を持つ互いに異なる葉が少なくともK件あることを、木の中身を明かさずに証明する回路
+// Kは回路の形なので、検証キーごとに件数が決まる（件数を変えるには別の鍵を生成する）
+// 葉は公開コミットメントを開く秘密値から作ったものに限るので、別の利用者の履歴の木は使えない
+#[derive(Clone)]
+pub struct HistoryCountCircuit<const K: usize, F: PedersenField = Fr> {
+    // 公開入力
+    pub root: F,
+    pub start: u64,
+    pub end: u64,
+    pub commitment: F,
+
+    // 秘密入力
+    pub user_secret: F,
+    pub salt: F,
+    pub activity_hashes: [F; K],
+    pub timestamps: [u64; K],
+    pub paths: [MerklePath<F>; K],
+}
+
+impl<const K: usize, F: PedersenField> HistoryCountCircuit<K, F> {
+    // 履歴のうち、indicesで選んだK件の葉を使う
+    pub fn new(
+        history: &ActivityHistory<F>,
+        indices: [usize; K],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Self, ZkError> {
+        if let Some(index) = indices.iter().find(|index| **index >= history.len()) {
+            return Err(ZkError::HistoryIndex {
+                index: *index,
+                len: history.len(),
+            });
+        }
+
+        Ok(Self {
+            root: history.root,
+            start: unix_seconds(start)?,
+            end: unix_seconds(end)?,
+            commitment: history.commitment(),
+            user_secret: history.user_secret,
+            salt: history.salt,
+            activity_hashes: indices.map(|index| hash_to_field_be(&history.entries[index].0)),
+            timestamps: indices.map(|index| history.entries[index].1),
+            paths: indices.map(|index| history.paths[index].clone()),
+        })
+    }
+
+    // セットアップ用の空の回路（回路の形はKと木の深さだけで決まる）
+    pub fn blank(depth: usize) -> Self {
+        Self {
+            root: F::zero(),
+            start: 0,
+            end: 0,
+            commitment: F::zero(),
+            user_secret: F::zero(),
+            salt: F::zero(),
+            activity_hashes: [F::zero(); K],
+            timestamps: [0; K],
+            paths: core::array::from_fn(|_| MerklePath {
+                index: 0,
+                siblings: vec![F::zero(); depth],
+            }),
+        }
+    }
+
+    // 回路に割り当てる公開入力（検証者は根と期間を自分で決め、コミットメントを利用者のものと照らし合わせる）
+    pub fn public_inputs(&self) -> Vec<F> {
+        vec![self.root, F::from(self.start), F::from(self.end), self.commitment]
+    }
+}
+
+impl<const K: usize, F: PedersenField> ConstraintSynthesizer<F> for HistoryCountCircuit<K, F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // 公開入力の割り当て（public_inputsと同じ並び）
+        let root = FpVar::new_input(cs.clone(), || Ok(self.root))?;
+        let start = FpVar::new_input(cs.clone(), || Ok(F::from(self.start)))?;
+        let end = FpVar::new_input(cs.clone(), || Ok(F::from(self.end)))?;
+        let commitment = FpVar::new_input(cs.clone(), || Ok(self.commitment))?;
+
+        // commitment == Pedersen(user_secret, salt) を課す（葉に使う秘密値を利用者に結び付ける）
+        let user_secret = FpVar::new_witness(cs.clone(), || Ok(self.user_secret))?;
+        let salt = FpVar::new_witness(cs.clone(), || Ok(self.salt))?;
+        commitment.enforce_equal(&PedersenCommitment::commit_var(cs.clone(), &user_secret, &salt)?)?;
+
+        let mut indices = Vec::with_capacity(K);
+        for ((hash, timestamp), path) in self.activity_hashes.iter().zip(self.timestamps).zip(&self.paths) {
+            // 秘密入力の割り当て（時刻はUInt64として割り当て、64ビットに収まることも課す）
+            let hash = FpVar::new_witness(cs.clone(), || Ok(*hash))?;
+            let timestamp_var = UInt64::new_witness(cs.clone(), || Ok(timestamp))?.to_fp()?;
+
+            // 葉を計算し、経路を辿って公開された根に辿り着くことを課す
+            let leaf = poseidon_hash_var(cs.clone(), &[user_secret.clone(), hash, timestamp_var.clone()])?;
+            let (node, index) = merkle_root_and_index_var(cs.clone(), leaf, path)?;
+            node.enforce_equal(&root)?;
+
+            // start <= timestamp <= end を制約として課す
+            enforce_u64_geq(cs.clone(), &timestamp_var, &start, || Ok(timestamp.wrapping_sub(self.start)))?;
+            enforce_u64_geq(cs.clone(), &end, &timestamp_var, || Ok(self.end.wrapping_sub(timestamp)))?;
+            indices.push(index);
+        }
+
+        // 同じ葉をK回使えないよう、葉の番号が互いに異なることを課す
+        for (i, index) in indices.iter().enumerate() {
+            for other in &indices[i + 1..] {
+                index.enforce_not_equal(other)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 履歴の件数の証明で検証者に渡す公開部分
+// 期間の始まりと終わりは証明者が選んだ時刻で、検証者は自分の時計から見て受理できるかを判定する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicHistoryCount<F: PrimeField = Fr> {
+    pub root: F,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub commitment: F,
+}
+
+impl<F: PrimeField> PublicHistoryCount<F> {
+    // HistoryCountCircuit::public_inputsと同じ並びの公開入力
+    pub fn public_inputs(&self) -> Result<Vec<F>, ZkError> {
+        Ok(vec![self.root, F::from(unix_seconds(self.start)?), F::from(unix_seconds(self.end)?), self.commitment])
+    }
+}
+
+// 履歴の木に期間内の行動がK件以上あることを検証する検証者（Setup::generate_for_history_countで生成した鍵を持つ）
+pub struct HistoryCountVerifier<const K: usize, E: Pairing = Bn254> {
+    verifying_key: PreparedVerifyingKey<E>,
+    freshness_window: Duration,
+    clock_tolerance: Duration,
+}
+
+impl<const K: usize, E: Pairing> HistoryCountVerifier<K, E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 前処理済みの検証キーから検証者を作成（鮮度の期間と許容誤差はデフォルト値）
+    pub(crate) fn from_prepared_key(verifying_key: PreparedVerifyingKey<E>) -> Self {
+        Self {
+            verifying_key,
+            freshness_window: DEFAULT_FRESHNESS_WINDOW,
+            clock_tolerance: DEFAULT_CLOCK_TOLERANCE,
+        }
+    }
+
+    // 受理する期間の始まりの古さ（鮮度の期間）を変更する
+    pub fn with_freshness_window(mut self, freshness_window: Duration) -> Self {
+        self.freshness_window = freshness_window;
+        self
+    }
+
+    // 証明を生成してから検証するまでの時間や時計のずれとして許す幅を変更する
+    pub fn with_clock_tolerance(mut self, clock_tolerance: Duration) -> Self {
+        self.clock_tolerance = clock_tolerance;
+        self
+    }
+
+    pub fn verifying_key(&self) -> &PreparedVerifyingKey<E> {
+        &self.verifying_key
+    }
+
+    // 現在時刻で、期間内の行動がK件以上あることを検証する
+    #[cfg(feature = "std")]
+    pub fn verify_history_count(
+        &self,
+        public: &PublicHistoryCount<E::ScalarField>,
+        proof: &Proof<E>,
+    ) -> Result<bool, ZkError> {
+        self.verify_history_count_with_clock(public, proof, Utc::now())
+    }
+
+    // 検証者の時計を指定して検証する
+    // 期間の始まりが now - 鮮度の期間 - 許容誤差 より古ければStaleCutoff（古い行動を数えさせない）
+    // 期間の終わりが now + 許容誤差 より後ならFutureTimestamp（未来の行動を数えさせない）
+    pub fn verify_history_count_with_clock(
+        &self,
+        public: &PublicHistoryCount<E::ScalarField>,
+        proof: &Proof<E>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, ZkError> {
+        if public.start < checked_sub(checked_sub(now, self.freshness_window)?, self.clock_tolerance)? {
+            return Err(ZkError::StaleCutoff);
+        }
+        check_not_future(public.end, now, self.clock_tolerance)?;
+        Ok(Groth16::<E>::verify_proof(&self.verifying_key, proof, &public.public_inputs()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::{prepare_verifying_key, Groth16};
+    use ark_relations::r1cs::ConstraintSystem;
+    use chrono::Duration;
+
+    use crate::hash_activity;
+    use crate::test_utils::seeded_rng;
+
+    // 利用者user_secretの8件の行動（偶数番目は期間内、奇数番目は40日以上前）
+    fn user_activities(now: DateTime<Utc>, user_secret: u8) -> Vec<ActivityData> {
+        (0..8)
+            .map(|i| {
+                let age = if i % 2 == 0 { Duration::days(i) } else { Duration::days(40 + i) };
+                ActivityData::new(now - age, hash_activity(&format!("history_{}", i)), [user_secret; 32], [0u8; 32])
+            })
+            .collect()
+    }
+
+    fn sample_history(now: DateTime<Utc>) -> ActivityHistory {
+        ActivityHistory::new(&user_activities(now, 1)).unwrap()
+    }
+
+    // 同じ番号の葉では逆元が存在せず合成自体が失敗するので、それも満たさないものとして扱う
+    fn is_satisfied(circuit: HistoryCountCircuit<3>) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).is_ok() && cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_three_distinct_in_window_leaves_prove() {
+        let rng = &mut seeded_rng();
+        let now = Utc::now();
+        let (start, end) = (now - Duration::days(30), now);
+        let history = sample_history(now);
+        assert_eq!(history.depth(), 3);
+        assert_eq!(history.indices_in_window(start, end).unwrap(), [0, 2, 4, 6]);
+
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(HistoryCountCircuit::<3>::blank(3), rng)
+            .unwrap();
+        let pvk = prepare_verifying_key(&pk.vk);
+
+        // 検証者は根と期間だけを受け取る
+        let circuit = HistoryCountCircuit::<3>::new(&history, [0, 4, 6], start, end).unwrap();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs.len(), NUM_HISTORY_PUBLIC_INPUTS);
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, rng).unwrap();
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &public_inputs).unwrap());
+
+        // 同じ証明を別の根やより狭い期間に使い回すことはできない
+        let other_root = sample_history(now - Duration::days(1)).root();
+        let mut wrong_root = public_inputs.clone();
+        wrong_root[0] = other_root;
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &wrong_root).unwrap());
+        let mut narrower = public_inputs.clone();
+        narrower[1] += Fr::from(86_400u64);
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &narrower).unwrap());
+
+        // 別の利用者のコミットメントに対しては検証に失敗する
+        let mut other_user = public_inputs;
+        other_user[3] = ActivityHistory::<Fr>::new(&user_activities(now, 2)).unwrap().commitment();
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other_user).unwrap());
+    }
+
+    #[test]
+    fn test_verifier_checks_the_window_against_its_clock() {
+        let rng = &mut seeded_rng();
+        let (pk, verifier) = crate::Setup::<Bn254>::generate_for_history_count::<3, _>(3, rng);
+        let now = Utc::now();
+        let history = sample_history(now);
+        let prove = |start: DateTime<Utc>, end: DateTime<Utc>, rng: &mut _| {
+            let circuit = HistoryCountCircuit::<3>::new(&history, [0, 2, 4], start, end).unwrap();
+            let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, rng).unwrap();
+            let public = PublicHistoryCount {
+                root: history.root(),
+                start,
+                end,
+                commitment: history.commitment(),
+            };
+            (public, proof)
+        };
+
+        // 鮮度の期間内の期間で証明した件数は、検証者の時計で受理される
+        let (public, proof) = prove(now - Duration::days(30), now, rng);
+        assert!(verifier.verify_history_count_with_clock(&public, &proof, now).unwrap());
+
+        // 期間を広げて古い行動まで数えた証明はStaleCutoff
+        let (stale, stale_proof) = prove(now - Duration::days(60), now, rng);
+        assert!(matches!(
+            verifier.verify_history_count_with_clock(&stale, &stale_proof, now),
+            Err(ZkError::StaleCutoff)
+        ));
+
+        // 検証者の時計より許容誤差を超えて後の終わりはFutureTimestamp
+        let later = now + Duration::hours(1);
+        let (future, future_proof) = prove(later - Duration::days(30), later, rng);
+        assert!(matches!(
+            verifier.verify_history_count_with_clock(&future, &future_proof, now),
+            Err(ZkError::FutureTimestamp)
+        ));
+
+        // 公開部分を書き換えると検証を通らない
+        let mut forged = public;
+        forged.start += Duration::days(1);
+        assert!(!verifier.verify_history_count_with_clock(&forged, &proof, now).unwrap());
+    }
+
+    #[test]
+    fn test_history_is_bound_to_one_user() {
+        let now = Utc::now();
+        let (start, end) = (now - Duration::days(30), now);
+
+        // 秘密値やソルトの異なる行動は1つの履歴にまとめられない
+        let mut activities = user_activities(now, 1);
+        activities[5].user_secret = [2u8; 32];
+        assert!(matches!(ActivityHistory::<Fr>::new(&activities), Err(ZkError::DifferentUsers)));
+        let mut activities = user_activities(now, 1);
+        activities[5].salt = [1u8; 32];
+        assert!(matches!(ActivityHistory::<Fr>::new(&activities), Err(ZkError::DifferentUsers)));
+        assert!(matches!(ActivityHistory::<Fr>::new(&[]), Err(ZkError::MissingField("activities"))));
+
+        // 葉は秘密値ごとに異なり、同じ行動でも別の利用者の木は別の根になる
+        let history = sample_history(now);
+        let other = ActivityHistory::<Fr>::new(&user_activities(now, 2)).unwrap();
+        assert_ne!(history.root(), other.root());
+        assert_ne!(history.commitment(), other.commitment());
+
+        // 他人の木の根を、自分の秘密値とコミットメントで使うことはできない
+        let mut circuit = HistoryCountCircuit::<3>::new(&other, [0, 2, 4], start, end).unwrap();
+        assert!(is_satisfied(circuit.clone()));
+        circuit.commitment = history.commitment();
+        circuit.user_secret = history.user_secret;
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_reused_or_out_of_window_leaves_fail() {
+        let now = Utc::now();
+        let (start, end) = (now - Duration::days(30), now);
+        let history = sample_history(now);
+        assert!(is_satisfied(HistoryCountCircuit::new(&history, [6, 2, 0], start, end).unwrap()));
+
+        // 同じ葉を2回数えることはできない
+        assert!(!is_satisfied(HistoryCountCircuit::new(&history, [0, 0, 2], start, end).unwrap()));
+        assert!(!is_satisfied(HistoryCountCircuit::new(&history, [4, 2, 4], start, end).unwrap()));
+
+        // 期間外の葉や、木にない番号は使えない
+        assert!(!is_satisfied(HistoryCountCircuit::new(&history, [0, 1, 2], start, end).unwrap()));
+        assert!(matches!(
+            HistoryCountCircuit::<3>::new(&history, [0, 2, 8], start, end),
+            Err(ZkError::HistoryIndex { index: 8, len: 8 })
+        ));
+    }
+}
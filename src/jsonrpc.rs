@@ -0,0 +1,330 @@
+use std::fmt;
+
+use chrono::Utc;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::activity::{hash_activity, hash_to_hex, hex_to_hash, ActivityData, PublicActivity};
+use crate::circuit::NO_EXPIRY;
+use crate::error::ZkError;
+use crate::prover::Bn254Prover;
+use crate::serialization::{proof_from_compact_string, proof_to_compact_string};
+use crate::verifier::{Bn254Verifier, VerificationReport};
+use crate::wire::{datetime_from_unix, field_to_hex, hex_to_field};
+
+// JSON-RPC 2.0で定められたエラーコード
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+// ZkErrorのコードはサーバーが自由に使える範囲（-32000から-32099）に、バリアントごとに1つずつ割り当てる
+const ZK_ERROR_BASE: i64 = -32000;
+
+// ソルトを省略した場合の値（32バイトの0）
+const ZERO_SALT: [u8; 32] = [0u8; 32];
+
+// JSON-RPCの要求（idのない通知はNoneで、処理はするが応答を返さない。"id": null はSome(Null)として応答する）
+// zk_proveの引数には秘密値が入るので、Debugではメソッド名とidだけを出す
+#[derive(Clone, Deserialize)]
+pub struct JsonRpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default, deserialize_with = "present")]
+    pub id: Option<Value>,
+}
+
+impl fmt::Debug for JsonRpcRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonRpcRequest")
+            .field("method", &self.method)
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+// キーがあればnullでもSomeにする（serdeの既定ではnullもNoneになり、通知と区別できない）
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+// JSON-RPCの応答（resultとerrorのどちらか一方だけが入る）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+    pub id: Value,
+}
+
+impl JsonRpcResponse {
+    fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    fn failure(id: Value, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            result: None,
+            error: Some(error),
+            id,
+        }
+    }
+}
+
+// JSON-RPCのエラー（ZkErrorから変換したものはdataにバリアント名が入る）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<ZkError> for JsonRpcError {
+    fn from(e: ZkError) -> Self {
+        let (offset, name) = zk_error_code(&e);
+        Self {
+            code: ZK_ERROR_BASE - offset,
+            message: e.to_string(),
+            data: Some(Value::String(name.into())),
+        }
+    }
+}
+
+// ZkErrorのバリアントごとの番号と名前（番号は宣言順。ワイルドカードを使わないので、バリアントを足すと書き忘れに気づく）
+fn zk_error_code(e: &ZkError) -> (i64, &'static str) {
+    match e {
+        ZkError::Synthesis(_) => (0, "Synthesis"),
+        ZkError::Serialization(_) => (1, "Serialization"),
+        ZkError::TrailingBytes(_) => (2, "TrailingBytes"),
+        ZkError::Unsatisfied => (3, "Unsatisfied"),
+        ZkError::UnsatisfiedConstraint(_) => (4, "UnsatisfiedConstraint"),
+        ZkError::PreEpochTimestamp => (5, "PreEpochTimestamp"),
+        ZkError::StaleTimestamp => (6, "StaleTimestamp"),
+        ZkError::StaleCutoff => (7, "StaleCutoff"),
+        ZkError::ExpiredProof => (8, "ExpiredProof"),
+        ZkError::PublicInputArity { .. } => (9, "PublicInputArity"),
+        ZkError::VerificationFailed => (10, "VerificationFailed"),
+        ZkError::MissingField(_) => (11, "MissingField"),
+        ZkError::InvalidCommitment => (12, "InvalidCommitment"),
+        ZkError::FutureTimestamp => (13, "FutureTimestamp"),
+        ZkError::InvalidHex => (14, "InvalidHex"),
+        ZkError::InvalidHashLength(_) => (15, "InvalidHashLength"),
+        ZkError::NotAllowlisted => (16, "NotAllowlisted"),
+        ZkError::PreimageLength { .. } => (17, "PreimageLength"),
+        ZkError::InvalidEpoch => (18, "InvalidEpoch"),
+        ZkError::KeyVersionMismatch { .. } => (19, "KeyVersionMismatch"),
+        ZkError::InvalidRange => (20, "InvalidRange"),
+        ZkError::HistoryIndex { .. } => (21, "HistoryIndex"),
+        ZkError::InvalidProofJson(_) => (22, "InvalidProofJson"),
+        ZkError::Timeout => (23, "Timeout"),
+        ZkError::SaltedHashMismatch => (24, "SaltedHashMismatch"),
+        ZkError::EnvelopeVersionMismatch { .. } => (25, "EnvelopeVersionMismatch"),
+        ZkError::CircuitMismatch => (26, "CircuitMismatch"),
+        ZkError::TimeArithmetic => (27, "TimeArithmetic"),
+        ZkError::BlockWithEpoch => (28, "BlockWithEpoch"),
+        ZkError::InvalidBase64 => (29, "InvalidBase64"),
+        ZkError::AggregationSize(_) => (30, "AggregationSize"),
+        ZkError::InputVersionRejected { .. } => (31, "InputVersionRejected"),
+        ZkError::DifferentUsers => (32, "DifferentUsers"),
+        ZkError::NonCanonicalField => (33, "NonCanonicalField"),
+        ZkError::InvalidVerifierConfig(_) => (34, "InvalidVerifierConfig"),
+        ZkError::SecretOutOfRange => (35, "SecretOutOfRange"),
+        ZkError::RangeMismatch => (36, "RangeMismatch"),
+        ZkError::ThresholdTooLow { .. } => (37, "ThresholdTooLow"),
+    }
+}
+
+// zk_proveの引数（秘密値とソルトは32バイトの16進文字列）
+// 秘密値をログに出さないよう、Debugは実装しない
+#[derive(Deserialize)]
+pub struct ProveParams {
+    pub activity: String,
+    pub user_secret: String,
+    #[serde(default)]
+    pub salt: Option<String>,
+}
+
+// zk_proveの結果（証明はproof_to_compact_stringの形式）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProveResult {
+    pub proof: String,
+    pub public: PublicInputsJson,
+}
+
+// zk_verifyの引数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyParams {
+    pub proof: String,
+    pub public: PublicInputsJson,
+}
+
+// zk_verifyの結果（証明が正しくない・古い場合はvalid = falseで、reasonに理由が入る）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// PublicActivityのJSON表現（時刻はUnix秒、行動ハッシュと体の元は32バイトのビッグエンディアンの16進文字列）
+// gRPCのPublicInputsと同じく、有効期限は省略すると期限なし
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicInputsJson {
+    pub timestamp: i64,
+    pub activity_hash: String,
+    pub commitment: String,
+    pub cutoff: i64,
+    pub nullifier: String,
+    pub challenge: String,
+    pub user_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<u64>,
+}
+
+impl From<&PublicActivity> for PublicInputsJson {
+    fn from(activity: &PublicActivity) -> Self {
+        Self {
+            timestamp: activity.timestamp.timestamp(),
+            activity_hash: hash_to_hex(&activity.activity_hash),
+            commitment: field_to_hex(&activity.commitment),
+            cutoff: activity.cutoff.timestamp(),
+            nullifier: field_to_hex(&activity.nullifier),
+            challenge: field_to_hex(&activity.challenge),
+            user_id: field_to_hex(&activity.user_id),
+            expiry: (activity.expiry != NO_EXPIRY).then_some(activity.expiry),
+        }
+    }
+}
+
+impl TryFrom<&PublicInputsJson> for PublicActivity {
+    type Error = ZkError;
+
+    fn try_from(inputs: &PublicInputsJson) -> Result<Self, ZkError> {
+        Ok(Self {
+            timestamp: datetime_from_unix(inputs.timestamp)?,
+            activity_hash: hex_to_hash(&inputs.activity_hash)?,
+            commitment: hex_to_field(&inputs.commitment)?,
+            cutoff: datetime_from_unix(inputs.cutoff)?,
+            nullifier: hex_to_field(&inputs.nullifier)?,
+            challenge: hex_to_field(&inputs.challenge)?,
+            user_id: hex_to_field(&inputs.user_id)?,
+            expiry: inputs.expiry.unwrap_or(NO_EXPIRY),
+        })
+    }
+}
+
+// zk_proveとzk_verifyを提供するJSON-RPCのハンドラー（トランスポートは呼び出し側が用意する）
+// 証明キーを応答やログに出さないよう、Debugは実装しない
+pub struct JsonRpcService {
+    prover: Bn254Prover,
+    verifier: Bn254Verifier,
+}
+
+impl JsonRpcService {
+    pub fn new(prover: Bn254Prover, verifier: Bn254Verifier) -> Self {
+        Self { prover, verifier }
+    }
+
+    // 要求のJSON文字列を処理し、応答のJSON文字列を返す（JSONとして読めなければPARSE_ERROR）
+    // 通知には応答しないのでNoneを返す（トランスポートは何も送り返さない）
+    pub fn handle(&self, request: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(request) {
+            Ok(value) => self.handle_value(value)?,
+            Err(e) => JsonRpcResponse::failure(Value::Null, JsonRpcError::new(PARSE_ERROR, e.to_string())),
+        };
+        Some(serde_json::to_string(&response).expect("A JSON-RPC response always serializes"))
+    }
+
+    // 読み込み済みのJSONを要求として処理する（要求の形をしていなければ、通知かどうか分からないのでidをnullとしてINVALID_REQUEST）
+    pub fn handle_value(&self, request: Value) -> Option<JsonRpcResponse> {
+        match serde_json::from_value::<JsonRpcRequest>(request) {
+            Ok(request) => self.handle_request(request),
+            Err(e) => Some(JsonRpcResponse::failure(Value::Null, JsonRpcError::new(INVALID_REQUEST, e.to_string()))),
+        }
+    }
+
+    // 要求を処理する（JSON-RPC 2.0に従い、idのない通知にはエラーでも応答しない）
+    pub fn handle_request(&self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let result = if request.jsonrpc != "2.0" {
+            Err(JsonRpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 requests are supported"))
+        } else {
+            match request.method.as_str() {
+                "zk_prove" => params(request.params).and_then(|params| self.prove(params)).and_then(to_value),
+                "zk_verify" => params(request.params).and_then(|params| self.verify(params)).and_then(to_value),
+                method => Err(JsonRpcError::new(METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+            }
+        };
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => JsonRpcResponse::success(id, result),
+            Err(error) => JsonRpcResponse::failure(id, error),
+        })
+    }
+
+    // 現在時刻の行動として証明する
+    fn prove(&self, params: ProveParams) -> Result<ProveResult, JsonRpcError> {
+        let salt = params.salt.as_deref().map(hex_to_hash).transpose()?.unwrap_or(ZERO_SALT);
+        let activity_data =
+            ActivityData::new(Utc::now(), hash_activity(&params.activity), hex_to_hash(&params.user_secret)?, salt);
+        let (public_activity, proof) = self.prover.prove_activity(&activity_data)?;
+        Ok(ProveResult {
+            proof: proof_to_compact_string(&proof),
+            public: PublicInputsJson::from(&public_activity),
+        })
+    }
+
+    // 証明が正しくない・古い・期限切れの場合はvalid = false、引数の形式が不正ならエラー
+    fn verify(&self, params: VerifyParams) -> Result<VerifyResult, JsonRpcError> {
+        let proof = proof_from_compact_string(&params.proof)?;
+        let activity = PublicActivity::try_from(&params.public)?;
+        let result = self.verifier.verify_activity(&activity, &proof).and_then(VerificationReport::into_result);
+        match result {
+            Ok(_) => Ok(VerifyResult {
+                valid: true,
+                reason: None,
+            }),
+            Err(
+                e @ (ZkError::VerificationFailed
+                | ZkError::StaleTimestamp
+                | ZkError::FutureTimestamp
+                | ZkError::StaleCutoff
+                | ZkError::ExpiredProof),
+            ) => Ok(VerifyResult {
+                valid: false,
+                reason: Some(e.to_string()),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// 名前付きの引数として読む（読めなければINVALID_PARAMS）
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, JsonRpcError> {
+    serde_json::from_value(params).map_err(|e| JsonRpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: Serialize>(result: T) -> Result<Value, JsonRpcError> {
+    serde_json::to_value(result).map_err(|e| JsonRpcError::new(INTERNAL_ERROR, e.to_string()))
+}
@@ -0,0 +1,101 @@
+// stdを無効にすると、回路・証明・検証・シリアライズだけのno_std + alloc構成になる
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod activity;
+mod aggregate;
+mod block;
+#[cfg(feature = "std")]
+mod cache;
+mod circuit;
+mod clock;
+mod core_verify;
+mod envelope;
+mod epoch;
+mod error;
+mod hasher;
+mod history;
+#[cfg(feature = "jsonrpc")]
+pub mod jsonrpc;
+mod link;
+mod merkle;
+#[cfg(feature = "std")]
+mod nullifier;
+mod pedersen;
+mod preimage;
+mod prover;
+mod r1cs;
+#[cfg(feature = "std")]
+mod resources;
+mod sequence;
+mod serialization;
+#[cfg(feature = "server")]
+pub mod server;
+mod setup;
+mod snarkjs;
+mod snarkpack;
+mod verifier;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod wire;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use activity::{
+    check_not_future, field_to_bytes_be, field_to_bytes_le, hash_activities, hash_activity, hash_activity_bytes,
+    hash_activity_bytes_with, hash_activity_with, hash_to_field_be, hash_to_field_be_checked, hash_to_field_le,
+    hash_to_hex, hex_to_hash, is_canonical_be, salted_activity_hash, timestamp_to_field, ActivityData,
+    ActivityDataBuilder, HashField, PublicActivity, PublicInputVersion, TimestampField, TypedPublicInputs, UserId,
+    ACTIVITIES_HASH_DOMAIN, ACTIVITY_HASH_DOMAIN,
+};
+#[cfg(feature = "std")]
+pub use activity::hash_activity_reader;
+#[cfg(feature = "parallel")]
+pub use activity::hash_activities_parallel;
+pub use aggregate::{
+    MultiActivityCircuit, MultiActivityProver, MultiActivityVerifier, PublicMultiActivity, NUM_AGGREGATE_PUBLIC_INPUTS,
+};
+pub use block::{BlockClock, PublicBlockActivity, DEFAULT_BLOCK_WINDOW};
+#[cfg(feature = "std")]
+pub use cache::VerificationCache;
+pub use circuit::{
+    derive_nullifier, poseidon_config, ActivityCircuit, CircuitMetrics, ConstraintLabel, PublicInputLayout,
+    CIRCUIT_VERSION, NO_EXPIRY, NUM_PUBLIC_INPUTS,
+};
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use clock::{Clock, FixedClock};
+pub use core_verify::core_verify;
+pub use envelope::{circuit_id, ProofEnvelope, ENVELOPE_VERSION};
+pub use epoch::{EpochConfig, EpochIndex, EpochWindow};
+pub use error::ZkError;
+pub use hasher::{ActivityHasher, Keccak256Hasher, Sha256Hasher};
+pub use history::{
+    history_leaf, ActivityHistory, HistoryCountCircuit, HistoryCountVerifier, PublicHistoryCount,
+    NUM_HISTORY_PUBLIC_INPUTS,
+};
+pub use link::{link_tag, prove_same_user, SameUserCircuit, UserLink, NUM_LINK_PUBLIC_INPUTS};
+pub use merkle::{
+    build_tree, merkle_leaf, Allowlist, AllowlistMembership, MerkleActivityCircuit, MerklePath, MerkleRoot, MerkleTree,
+};
+#[cfg(feature = "std")]
+pub use nullifier::NullifierSet;
+pub use pedersen::{PedersenCommitment, PedersenField};
+pub use prover::{ActivityProver, Bls12Prover, Bn254Prover};
+#[cfg(feature = "std")]
+pub use resources::ResourceEstimate;
+pub use sequence::{SequenceCircuit, NUM_SEQUENCE_PUBLIC_INPUTS};
+pub use serialization::{
+    compact_proof_len, deserialize_proof, deserialize_proof_with_mode, proof_from_compact_string,
+    proof_serialization_mode, proof_to_compact_string, serialize_proof, serialize_proof_with_mode,
+};
+pub use setup::{key_info, keys_equal, KeyInfo, KeyKind, Setup, SetupError};
+pub use snarkjs::{proof_from_snarkjs_json, proof_to_snarkjs_json};
+pub use snarkpack::{AggregateProof, AggregationSrs, AggregationVerifierKey};
+pub use verifier::{
+    num_public_inputs, verify_with_vk, ActivityVerifier, ActivityVerifierBuilder, Bls12Verifier, Bn254Verifier,
+    VerificationContext, VerificationReport, DEFAULT_CLOCK_TOLERANCE, DEFAULT_FRESHNESS_WINDOW,
+};
+pub use wire::{datetime_from_unix, field_to_hex, hex_to_field};
@@ -0,0 +1,192 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ark_bn254::Fr;
+use ark_ec::pairing::Pairing;
+use ark_groth16::{Groth16, ProvingKey};
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, fields::FieldVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use rand::{CryptoRng, RngCore};
+
+use crate::activity::{hash_to_field_be, ActivityData};
+use crate::circuit::{poseidon_hash, poseidon_hash_var};
+use crate::error::ZkError;
+use crate::pedersen::{PedersenCommitment, PedersenField};
+
+// リンクタグを計算するときに秘密値の前に置くドメインタグ（ヌリファイアとは別の値になるようにする）
+const LINK_TAG_DOMAIN: &[u8] = b"zk_sample/link_tag/v1";
+
+// 公開入力の数（2つのコミットメントとリンクタグ）
+pub const NUM_LINK_PUBLIC_INPUTS: usize = 3;
+
+// 利用者ごとに決まるリンクタグ（Poseidon(ドメインタグ, 秘密値)）
+// 同じ利用者のリンクの証明はすべて同じタグになるので、タグを見た検証者同士はリンクの証明どうしを結び付けられる
+pub fn link_tag<F: PedersenField>(user_secret: &[u8; 32]) -> F {
+    poseidon_hash(&[F::from_be_bytes_mod_order(LINK_TAG_DOMAIN), hash_to_field_be(user_secret)])
+}
+
+// 2つの行動の証明の公開コミットメントが、同じ利用者の秘密値から作られたことを示すときの公開部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserLink<F: PedersenField = Fr> {
+    pub commitments: [F; 2],
+    pub link_tag: F,
+}
+
+impl<F: PedersenField> UserLink<F> {
+    // 回路に割り当てる公開入力（コミットメントa, b, リンクタグの順）
+    pub fn public_inputs(&self) -> Vec<F> {
+        vec![self.commitments[0], self.commitments[1], self.link_tag]
+    }
+}
+
+// 2つのコミットメントが1つの秘密値とそれぞれのソルトから作られ、リンクタグも同じ秘密値から作られたことを示す回路
+// 秘密値そのものは明かさず、検証者は各行動の証明のコミットメントと照らし合わせて同じ利用者だと分かる
+#[derive(Clone)]
+pub struct SameUserCircuit<F: PedersenField = Fr> {
+    // 公開入力
+    pub link: UserLink<F>,
+
+    // 秘密入力
+    pub user_secret: F,
+    pub salts: [F; 2],
+}
+
+impl<F: PedersenField> SameUserCircuit<F> {
+    // 秘密値が異なる2つの行動ではDifferentUsers、すべて0の秘密値ではInvalidCommitment、
+    // 秘密値かソルトが部分群の位数以上ならSecretOutOfRange
+    pub fn new(a: &ActivityData, b: &ActivityData) -> Result<Self, ZkError> {
+        if a.user_secret != b.user_secret {
+            return Err(ZkError::DifferentUsers);
+        }
+        if a.user_secret == [0u8; 32] {
+            return Err(ZkError::InvalidCommitment);
+        }
+
+        let user_secret = hash_to_field_be(&a.user_secret);
+        let salts = [hash_to_field_be(&a.salt), hash_to_field_be(&b.salt)];
+        for salt in salts {
+            PedersenCommitment::check_opening(user_secret, salt)?;
+        }
+        Ok(Self {
+            link: UserLink {
+                commitments: salts.map(|salt| PedersenCommitment::commit(user_secret, salt)),
+                link_tag: link_tag(&a.user_secret),
+            },
+            user_secret,
+            salts,
+        })
+    }
+
+    // セットアップ用の空の回路（回路の形は値によらない）
+    pub fn blank() -> Self {
+        Self {
+            link: UserLink {
+                commitments: [F::zero(); 2],
+                link_tag: F::zero(),
+            },
+            user_secret: F::zero(),
+            salts: [F::zero(); 2],
+        }
+    }
+}
+
+impl<F: PedersenField> ConstraintSynthesizer<F> for SameUserCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // 公開入力の割り当て（UserLink::public_inputsと同じ並び）
+        let commitments = self
+            .link
+            .commitments
+            .iter()
+            .map(|commitment| FpVar::new_input(cs.clone(), || Ok(*commitment)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let link_tag = FpVar::new_input(cs.clone(), || Ok(self.link.link_tag))?;
+
+        // 秘密入力の割り当て（秘密値は1つだけで、両方のコミットメントに使う）
+        let user_secret = FpVar::new_witness(cs.clone(), || Ok(self.user_secret))?;
+
+        // commitment_i == Pedersen(user_secret, salt_i) を課す
+        for (commitment, salt) in commitments.iter().zip(self.salts) {
+            let salt = FpVar::new_witness(cs.clone(), || Ok(salt))?;
+            commitment.enforce_equal(&PedersenCommitment::commit_var(cs.clone(), &user_secret, &salt)?)?;
+        }
+
+        // link_tag == Poseidon(ドメインタグ, user_secret) を課す
+        let domain = FpVar::constant(F::from_be_bytes_mod_order(LINK_TAG_DOMAIN));
+        link_tag.enforce_equal(&poseidon_hash_var(cs, &[domain, user_secret])?)
+    }
+}
+
+// 2つの行動が同じ利用者のものであることを、SameUserCircuit::blankから生成した証明キーで証明する
+pub fn prove_same_user<E: Pairing, R: RngCore + CryptoRng>(
+    proving_key: &ProvingKey<E>,
+    a: &ActivityData,
+    b: &ActivityData,
+    rng: &mut R,
+) -> Result<(UserLink<E::ScalarField>, ark_groth16::Proof<E>), ZkError>
+where
+    E::ScalarField: PedersenField,
+{
+    let circuit = SameUserCircuit::new(a, b)?;
+    let link = circuit.link;
+    let proof = Groth16::<E>::create_random_proof_with_reduction(circuit, proving_key, rng)?;
+    Ok((link, proof))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::prepare_verifying_key;
+    use ark_relations::r1cs::ConstraintSystem;
+    use chrono::Utc;
+
+    use crate::hash_activity;
+    use crate::test_utils::seeded_rng;
+
+    fn activity(name: &str, user_secret: u8, salt: u8) -> ActivityData {
+        ActivityData::new(Utc::now(), hash_activity(name), [user_secret; 32], [salt; 32])
+    }
+
+    #[test]
+    fn test_same_user_pairs_share_a_link_tag() {
+        let rng = &mut seeded_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(SameUserCircuit::<Fr>::blank(), rng)
+            .unwrap();
+        let pvk = prepare_verifying_key(&pk.vk);
+
+        // 別のソルトで作った2つのコミットメントを、同じ利用者のものとして結び付けられる
+        let (purchase, visit) = (activity("purchase", 50, 1), activity("visit", 50, 2));
+        let (link, proof) = prove_same_user(&pk, &purchase, &visit, rng).unwrap();
+        assert_eq!(link.commitments[0], purchase.public::<Fr>(Utc::now()).commitment);
+        assert_eq!(link.commitments[1], visit.public::<Fr>(Utc::now()).commitment);
+        assert_ne!(link.commitments[0], link.commitments[1]);
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &link.public_inputs()).unwrap());
+
+        // 同じ利用者の別の組でも同じタグになる
+        let (other_link, _) = prove_same_user(&pk, &visit, &activity("review", 50, 3), rng).unwrap();
+        assert_eq!(other_link.link_tag, link.link_tag);
+
+        // 別の利用者のコミットメントやタグに差し替えた公開入力では検証を通らない
+        let stranger = activity("purchase", 51, 1);
+        let mut forged = link;
+        forged.commitments[1] = stranger.public::<Fr>(Utc::now()).commitment;
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &forged.public_inputs()).unwrap());
+        let mut forged = link;
+        forged.link_tag = link_tag(&stranger.user_secret);
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &forged.public_inputs()).unwrap());
+    }
+
+    #[test]
+    fn test_different_users_do_not_share_a_link_tag() {
+        let (alice, bob) = (activity("purchase", 52, 1), activity("visit", 53, 1));
+        assert_ne!(link_tag::<Fr>(&alice.user_secret), link_tag::<Fr>(&bob.user_secret));
+        assert!(matches!(SameUserCircuit::<Fr>::new(&alice, &bob), Err(ZkError::DifferentUsers)));
+
+        // 秘密値が違うのにコミットメントを並べた回路は、制約を満たさない
+        let mut circuit = SameUserCircuit::<Fr>::new(&alice, &alice).unwrap();
+        circuit.link.commitments[1] = bob.public::<Fr>(Utc::now()).commitment;
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
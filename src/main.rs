@@ -1,185 +1,218 @@
-use ark_ff::Field;
-use ark_groth16::{
-    generate_random_parameters, create_random_proof, prepare_verifying_key, verify_proof,
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+use zk_sample::{
+    datetime_from_unix, deserialize_proof, field_to_hex, hash_activity, hash_to_hex, hex_to_field, hex_to_hash,
+    keys_equal, serialize_proof, ActivityData, Bn254Prover, Bn254Verifier, PublicActivity, Setup, VerificationReport,
+    ZkError, NO_EXPIRY,
 };
-use ark_bn254::{Bn254, Fr};
-use ark_std::{test_rng, UniformRand};
-use chrono::{DateTime, Duration, Utc};
-use sha2::{Sha256, Digest};
-
-// 行動データの構造体
-#[derive(Debug)]
-pub struct ActivityData {
-    timestamp: DateTime<Utc>,
-    activity_hash: [u8; 32],
-    user_commitment: [u8; 32],
-}
 
-// 証明用の回路構造体
-#[derive(Clone)]
-pub struct ActivityCircuit {
-    // 公開入力
-    pub timestamp: u64,
-    pub activity_hash: Fr,
-    
-    // 秘密入力
-    pub user_commitment: Fr,
-}
+// 鍵ディレクトリ内のファイル名
+const PROVER_KEY_FILE: &str = "prover.key";
+const VERIFIER_KEY_FILE: &str = "verifier.key";
 
-impl ActivityCircuit {
-    pub fn new(
-        timestamp: DateTime<Utc>,
-        activity_hash: [u8; 32],
-        user_commitment: [u8; 32],
-    ) -> Self {
-        // タイムスタンプをu64に変換
-        let timestamp_u64 = timestamp.timestamp() as u64;
-        
-        // ハッシュ値とコミットメントをField要素に変換
-        let activity_hash_fr = Fr::from_be_bytes_mod_order(&activity_hash);
-        let user_commitment_fr = Fr::from_be_bytes_mod_order(&user_commitment);
-        
-        Self {
-            timestamp: timestamp_u64,
-            activity_hash: activity_hash_fr,
-            user_commitment: user_commitment_fr,
-        }
-    }
+// ソルトを指定しない場合の値（32バイトの0）
+const ZERO_SALT: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// 終了コード（0は検証成功）
+const EXIT_INVALID: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+#[derive(Parser)]
+#[command(version, about = "Prove and verify recent activities with Groth16")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-// 検証システムの実装
-pub struct ActivityVerifier {
-    proving_key: ark_groth16::ProvingKey<Bn254>,
-    verifying_key: ark_groth16::PreparedVerifyingKey<Bn254>,
+#[derive(Subcommand)]
+enum Command {
+    // トラステッドセットアップを実行し、鍵を保存する
+    #[command(about = "Run the trusted setup and write the proving and verifying keys")]
+    Setup {
+        #[arg(long)]
+        out_dir: PathBuf,
+        #[arg(long, help = "Derive the keys from a 32-byte hex seed so that audit can regenerate them")]
+        seed: Option<String>,
+    },
+    // 行動の証明を生成し、検証に必要な公開入力を出力する
+    #[command(about = "Prove an activity and print the public inputs needed by verify")]
+    Prove {
+        #[arg(long)]
+        activity: String,
+        #[arg(long, help = "User secret as 32 bytes of hex")]
+        commitment: String,
+        #[arg(long, help = "Salt for the public commitment as 32 bytes of hex", default_value = ZERO_SALT)]
+        salt: String,
+        #[arg(long)]
+        keys: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    // シードからセットアップをやり直し、公開されている鍵と同じものになるかを確かめる
+    #[command(about = "Regenerate the setup from a seed and compare it with published keys")]
+    Audit {
+        #[arg(long, help = "Setup seed as 32 bytes of hex")]
+        seed: String,
+        #[arg(long)]
+        keys: PathBuf,
+    },
+    // 証明を検証する
+    #[command(about = "Verify a proof against its public inputs")]
+    Verify {
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long, help = "Activity time as unix seconds")]
+        timestamp: i64,
+        #[arg(long)]
+        activity_hash: String,
+        #[arg(long)]
+        public_commitment: String,
+        #[arg(long, help = "Lower bound proven in the circuit as unix seconds")]
+        cutoff: i64,
+        #[arg(long)]
+        nullifier: String,
+        #[arg(long)]
+        keys: PathBuf,
+    },
 }
 
-impl ActivityVerifier {
-    // 新しい検証システムの初期化
-    pub fn new() -> Self {
-        let rng = &mut test_rng();
-        
-        // ダミーの回路でパラメータを生成
-        let circuit = ActivityCircuit::new(
-            Utc::now(),
-            [0u8; 32],
-            [0u8; 32],
-        );
-        
-        // 証明キーと検証キーの生成
-        let params = generate_random_parameters::<Bn254, _, _>(circuit, rng).unwrap();
-        let verifying_key = prepare_verifying_key(&params.vk);
-        
-        Self {
-            proving_key: params,
-            verifying_key,
-        }
-    }
-    
-    // 証明の生成
-    pub fn generate_proof(&self, activity_data: &ActivityData) -> Result<ark_groth16::Proof<Bn254>, &'static str> {
-        let rng = &mut test_rng();
-        
-        // 回路の作成
-        let circuit = ActivityCircuit::new(
-            activity_data.timestamp,
-            activity_data.activity_hash,
-            activity_data.user_commitment,
-        );
-        
-        // 証明の生成
-        create_random_proof(circuit, &self.proving_key, rng)
-            .map_err(|_| "Failed to generate proof")
-    }
-    
-    // 証明の検証
-    pub fn verify_proof(
-        &self,
-        proof: &ark_groth16::Proof<Bn254>,
-        public_inputs: &[Fr],
-    ) -> bool {
-        verify_proof(
-            &self.verifying_key,
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Setup { out_dir, seed } => run_setup(&out_dir, seed.as_deref()).map(|_| true),
+        Command::Prove {
+            activity,
+            commitment,
+            salt,
+            keys,
+            out,
+        } => run_prove(&activity, &commitment, &salt, &keys, &out).map(|_| true),
+        Command::Audit { seed, keys } => run_audit(&seed, &keys),
+        Command::Verify {
             proof,
-            public_inputs,
-        )
-        .unwrap_or(false)
-    }
-    
-    // 行動の検証（メインの検証ロジック）
-    pub fn verify_activity(&self, activity_data: &ActivityData) -> bool {
-        // 1ヶ月前の日時を計算
-        let one_month_ago = Utc::now() - Duration::days(30);
-        
-        // タイムスタンプの検証
-        if activity_data.timestamp < one_month_ago {
-            return false;
+            timestamp,
+            activity_hash,
+            public_commitment,
+            cutoff,
+            nullifier,
+            keys,
+        } => parse_public_activity(timestamp, &activity_hash, &public_commitment, cutoff, &nullifier)
+            .and_then(|activity| run_verify(&proof, &activity, &keys)),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_INVALID),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_ERROR)
         }
-        
-        // 証明の生成
-        let proof = match self.generate_proof(activity_data) {
-            Ok(p) => p,
-            Err(_) => return false,
-        };
-        
-        // 公開入力の準備
-        let public_inputs = vec![
-            Fr::from(activity_data.timestamp.timestamp() as u64),
-            Fr::from_be_bytes_mod_order(&activity_data.activity_hash),
-        ];
-        
-        // 証明の検証
-        self.verify_proof(&proof, &public_inputs)
     }
 }
 
-// ユーティリティ関数
-pub fn hash_activity(activity: &str) -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(activity.as_bytes());
-    hasher.finalize().into()
+// シードを指定したセットアップはシードを知る誰もが証明を偽造できるので、監査できる公開の検証用に限る
+fn run_setup(out_dir: &Path, seed: Option<&str>) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let (prover, verifier) = match seed {
+        Some(seed) => Setup::setup_from_seed(hex_to_hash(seed)?),
+        None => Setup::generate(),
+    };
+    Setup::save_keys(
+        &prover,
+        &verifier,
+        out_dir.join(PROVER_KEY_FILE),
+        out_dir.join(VERIFIER_KEY_FILE),
+    )?;
+    Ok(())
 }
 
-// 使用例
-fn main() {
-    // 検証システムの初期化
-    let verifier = ActivityVerifier::new();
-    
-    // テスト用の行動データの作成
-    let activity_data = ActivityData {
-        timestamp: Utc::now(),
-        activity_hash: hash_activity("some_activity"),
-        user_commitment: [0u8; 32], // 実際の実装ではユーザー固有の値を使用
+fn run_prove(activity: &str, commitment: &str, salt: &str, keys: &Path, out: &Path) -> Result<(), Box<dyn Error>> {
+    let prover = Bn254Prover::load(keys.join(PROVER_KEY_FILE))?;
+    let activity_data = ActivityData::new(
+        Utc::now(),
+        hash_activity(activity),
+        hex_to_hash(commitment)?,
+        hex_to_hash(salt)?,
+    );
+
+    let (public_activity, proof) = prover.prove_activity(&activity_data)?;
+    fs::write(out, serialize_proof(&proof))?;
+
+    // verifyサブコマンドの引数名と同じ形式で公開入力を出力する
+    println!("timestamp: {}", public_activity.timestamp.timestamp());
+    println!("activity-hash: {}", hash_to_hex(&public_activity.activity_hash));
+    println!("public-commitment: {}", field_to_hex(&public_activity.commitment));
+    println!("cutoff: {}", public_activity.cutoff.timestamp());
+    println!("nullifier: {}", field_to_hex(&public_activity.nullifier));
+    Ok(())
+}
+
+// 鍵が1つでも異なればOk(false)。証明鍵は公開されないことが多いので、ファイルがあるときだけ比べる
+fn run_audit(seed: &str, keys: &Path) -> Result<bool, Box<dyn Error>> {
+    let (prover, verifier) = Setup::setup_from_seed(hex_to_hash(seed)?);
+
+    let published = Bn254Verifier::load(keys.join(VERIFIER_KEY_FILE))?;
+    let verifier_matches = keys_equal(verifier.verifying_key(), published.verifying_key());
+    println!("verifier.key: {}", if verifier_matches { "matches" } else { "differs" });
+
+    let prover_path = keys.join(PROVER_KEY_FILE);
+    let prover_matches = if prover_path.exists() {
+        let matches = keys_equal(prover.proving_key(), Bn254Prover::load(prover_path)?.proving_key());
+        println!("prover.key: {}", if matches { "matches" } else { "differs" });
+        matches
+    } else {
+        true
     };
-    
-    // 検証の実行
-    let is_valid = verifier.verify_activity(&activity_data);
-    println!("検証結果: {}", is_valid);
+    Ok(verifier_matches && prover_matches)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    #[test]
-    fn test_recent_activity_verification() {
-        let verifier = ActivityVerifier::new();
-        
-        // 有効な行動データのテスト
-        let valid_activity = ActivityData {
-            timestamp: Utc::now(),
-            activity_hash: hash_activity("valid_activity"),
-            user_commitment: [1u8; 32],
-        };
-        
-        assert!(verifier.verify_activity(&valid_activity));
-        
-        // 1ヶ月以上前の行動データのテスト
-        let old_activity = ActivityData {
-            timestamp: Utc::now() - Duration::days(31),
-            activity_hash: hash_activity("old_activity"),
-            user_commitment: [1u8; 32],
-        };
-        
-        assert!(!verifier.verify_activity(&old_activity));
+// 証明が正しくない場合はOk(false)、入力やファイルの問題はErrとして区別する
+fn run_verify(proof: &Path, activity: &PublicActivity, keys: &Path) -> Result<bool, Box<dyn Error>> {
+    let verifier = Bn254Verifier::load(keys.join(VERIFIER_KEY_FILE))?;
+    let proof = deserialize_proof(&fs::read(proof)?)?;
+
+    match verifier.verify_activity(activity, &proof).and_then(VerificationReport::into_result) {
+        Ok(_) => {
+            println!("valid");
+            Ok(true)
+        }
+        Err(
+            e @ (ZkError::VerificationFailed
+            | ZkError::StaleTimestamp
+            | ZkError::FutureTimestamp
+            | ZkError::StaleCutoff
+            | ZkError::ExpiredProof),
+        ) => {
+            println!("invalid: {}", e);
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
     }
-}
\ No newline at end of file
+}
+
+// コマンドライン引数から検証者に渡す公開部分を組み立てる
+fn parse_public_activity(
+    timestamp: i64,
+    activity_hash: &str,
+    commitment: &str,
+    cutoff: i64,
+    nullifier: &str,
+) -> Result<PublicActivity, Box<dyn Error>> {
+    Ok(PublicActivity {
+        timestamp: datetime_from_unix(timestamp)?,
+        activity_hash: hex_to_hash(activity_hash)?,
+        commitment: hex_to_field(commitment)?,
+        cutoff: datetime_from_unix(cutoff)?,
+        nullifier: hex_to_field(nullifier)?,
+        // CLIの証明はチャレンジと利用者のID、有効期限なしで生成する
+        challenge: Fr::zero(),
+        user_id: Fr::zero(),
+        expiry: NO_EXPIRY,
+    })
+}
@@ -0,0 +1,432 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::{PrimeField, Zero};
+use ark_r1cs_std::{
+    alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, fields::FieldVar, select::CondSelectGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::activity::ActivityData;
+use crate::circuit::{poseidon_hash, poseidon_hash_var};
+
+// Merkle木の根（検証者が受け取る唯一の公開入力）
+pub type MerkleRoot = Fr;
+
+// 葉から根までの経路（siblingsは葉に近い順）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerklePath<F: PrimeField = Fr> {
+    pub index: usize,
+    pub siblings: Vec<F>,
+}
+
+impl<F: PrimeField + Absorb> MerklePath<F> {
+    // 経路から根を計算する（回路内の制約と同じ計算）
+    pub fn compute_root(&self, leaf: F) -> F {
+        self.siblings.iter().enumerate().fold(leaf, |node, (level, sibling)| {
+            if (self.index >> level) & 1 == 1 {
+                poseidon_hash(&[*sibling, node])
+            } else {
+                poseidon_hash(&[node, *sibling])
+            }
+        })
+    }
+}
+
+// 行動データの一覧からMerkle木を構築し、根と各葉の経路を返す（埋め方はbuild_tree_from_leavesを参照）
+pub fn build_tree(activities: &[ActivityData]) -> (MerkleRoot, Vec<MerklePath>) {
+    let hashes: Vec<[u8; 32]> = activities.iter().map(|activity| activity.activity_hash).collect();
+    build_tree_from_hashes(&hashes)
+}
+
+// 行動ハッシュの一覧からMerkle木を構築する
+// 葉はSHA256の行動ハッシュ、内部ノードはPoseidon(左, 右)で計算し、足りない葉は0で埋める
+fn build_tree_from_hashes<F: PrimeField + Absorb>(hashes: &[[u8; 32]]) -> (F, Vec<MerklePath<F>>) {
+    build_tree_from_leaves(hashes.iter().map(|hash| F::from_be_bytes_mod_order(hash)).collect())
+}
+
+// 葉の値の一覧からMerkle木を構築する。外部の検証者が同じ根を再現できるよう、埋め方は次のとおりに固定する
+// - 葉の数nを2のべき乗（n = 0のときは1）に切り上げ、足りない分は右端に値0の葉を並べる
+// - 内部ノードはPoseidon(左, 右)。n = 0の根は0、n = 1の根はその葉の値そのもの（経路の兄弟ノードは0個）
+// 値0の葉は埋め草なので、回路は0の葉の所属を証明させない（merkle_root_varを参照）
+pub(crate) fn build_tree_from_leaves<F: PrimeField + Absorb>(leaves: Vec<F>) -> (F, Vec<MerklePath<F>>) {
+    let len = leaves.len();
+    let mut level = leaves;
+    level.resize(len.max(1).next_power_of_two(), F::zero());
+
+    let mut paths: Vec<MerklePath<F>> = (0..len)
+        .map(|index| MerklePath {
+            index,
+            siblings: Vec::new(),
+        })
+        .collect();
+
+    while level.len() > 1 {
+        for path in paths.iter_mut() {
+            let position = path.index >> path.siblings.len();
+            path.siblings.push(level[position ^ 1]);
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| poseidon_hash(&[pair[0], pair[1]]))
+            .collect();
+    }
+
+    (level[0], paths)
+}
+
+// 内部ノードを段ごとに保持するMerkle木（経路は必要になったときに1本ずつ組み立てる）
+// 保持するのは約2n個のノードだけで、build_treeのように全ての葉の経路（n log n個）を作らない
+#[derive(Debug, Clone)]
+pub struct MerkleTree<F: PrimeField = Fr> {
+    // levels[0]が0で埋めた葉、最後の段が根だけの段
+    levels: Vec<Vec<F>>,
+    len: usize,
+}
+
+impl MerkleTree {
+    // build_treeと同じ木を、行動データの一覧から構築する
+    pub fn new(activities: &[ActivityData]) -> Self {
+        Self::from_leaves(activities.iter().map(|activity| merkle_leaf(&activity.activity_hash)).collect())
+    }
+}
+
+impl<F: PrimeField + Absorb> MerkleTree<F> {
+    // 葉の値の一覧から木を構築する（build_treeと同じく、足りない葉は0で埋める）
+    pub fn from_leaves(leaves: Vec<F>) -> Self {
+        let len = leaves.len();
+        let mut level = leaves;
+        level.resize(len.max(1).next_power_of_two(), F::zero());
+
+        let mut levels = vec![level];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| poseidon_hash(&[pair[0], pair[1]]))
+                .collect();
+            levels.push(next);
+        }
+        Self { levels, len }
+    }
+
+    pub fn root(&self) -> F {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    // 埋める前の葉の数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 木の深さ（経路の兄弟ノードの数）
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    // leaf_index番目の葉の経路を保持しているノードから組み立てる（範囲外ならNone）
+    pub fn path(&self, leaf_index: usize) -> Option<MerklePath<F>> {
+        if leaf_index >= self.len {
+            return None;
+        }
+        let siblings = self.levels[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(level, nodes)| nodes[(leaf_index >> level) ^ 1])
+            .collect();
+        Some(MerklePath {
+            index: leaf_index,
+            siblings,
+        })
+    }
+
+    // 全ての葉の経路を順に1本ずつ返す（まとめて持たないので、一度に必要なメモリは経路1本分）
+    pub fn paths(&self) -> impl Iterator<Item = MerklePath<F>> + '_ {
+        (0..self.len).filter_map(|index| self.path(index))
+    }
+}
+
+// 行動ハッシュをMerkle木の葉に変換する
+pub fn merkle_leaf(activity_hash: &[u8; 32]) -> Fr {
+    Fr::from_be_bytes_mod_order(activity_hash)
+}
+
+// 回路内で葉から経路を辿って根を計算する（各段で経路のビットに応じて左右を入れ替える）
+// 葉は行動ハッシュそのもので証明者が自由に選べるため、埋め草の0を葉として使えないように課す
+pub(crate) fn merkle_root_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    leaf: FpVar<F>,
+    path: &MerklePath<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    leaf.enforce_not_equal(&FpVar::zero())?;
+    Ok(merkle_root_and_index_var(cs, leaf, path)?.0)
+}
+
+// merkle_root_varと同じ計算で、経路のビットから葉の番号も組み立てて返す
+// 深さが決まっていれば番号と葉の位置は1対1なので、番号が異なれば別の葉であることが分かる
+pub(crate) fn merkle_root_and_index_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    leaf: FpVar<F>,
+    path: &MerklePath<F>,
+) -> Result<(FpVar<F>, FpVar<F>), SynthesisError> {
+    let mut node = leaf;
+    let mut index = FpVar::zero();
+    let mut weight = F::ONE;
+    for (level, sibling) in path.siblings.iter().enumerate() {
+        let is_right = Boolean::new_witness(cs.clone(), || Ok((path.index >> level) & 1 == 1))?;
+        let sibling = FpVar::new_witness(cs.clone(), || Ok(*sibling))?;
+
+        let left = FpVar::conditionally_select(&is_right, &sibling, &node)?;
+        let right = FpVar::conditionally_select(&is_right, &node, &sibling)?;
+        node = poseidon_hash_var(cs.clone(), &[left, right])?;
+
+        // 係数を掛けて足すだけなので制約は増えない
+        index += FpVar::from(is_right) * weight;
+        weight.double_in_place();
+    }
+    Ok((node, index))
+}
+
+// 許可リストに含まれることを回路内で示すための値（根は公開入力、経路は秘密入力）
+#[derive(Debug, Clone)]
+pub struct AllowlistMembership<F: PrimeField = Fr> {
+    pub root: F,
+    pub path: MerklePath<F>,
+}
+
+// 受け付ける行動ハッシュの許可リスト（Merkle木の根だけを公開入力にするので、リストの長さによらず回路の入力は同じ）
+#[derive(Debug, Clone)]
+pub struct Allowlist<F: PrimeField = Fr> {
+    hashes: Vec<[u8; 32]>,
+    root: F,
+    paths: Vec<MerklePath<F>>,
+}
+
+impl<F: PrimeField + Absorb> Allowlist<F> {
+    pub fn new(hashes: &[[u8; 32]]) -> Self {
+        let (root, paths) = build_tree_from_hashes(hashes);
+        Self {
+            hashes: hashes.to_vec(),
+            root,
+            paths,
+        }
+    }
+
+    pub fn root(&self) -> F {
+        self.root
+    }
+
+    // 木の深さ（回路の形はこの深さで決まる）
+    pub fn depth(&self) -> usize {
+        self.hashes.len().max(1).next_power_of_two().trailing_zeros() as usize
+    }
+
+    // 許可された行動ハッシュの経路（リストになければNone）
+    pub fn path(&self, activity_hash: &[u8; 32]) -> Option<&MerklePath<F>> {
+        let index = self.hashes.iter().position(|hash| hash == activity_hash)?;
+        Some(&self.paths[index])
+    }
+
+    // 回路に渡す、行動ハッシュがリストに含まれることの証拠
+    pub fn membership(&self, activity_hash: &[u8; 32]) -> Option<AllowlistMembership<F>> {
+        Some(AllowlistMembership {
+            root: self.root,
+            path: self.path(activity_hash)?.clone(),
+        })
+    }
+
+    // セットアップ用の空の証拠（回路の形は木の深さだけで決まる）
+    pub(crate) fn blank_membership(&self) -> AllowlistMembership<F> {
+        AllowlistMembership {
+            root: F::zero(),
+            path: MerklePath {
+                index: 0,
+                siblings: vec![F::zero(); self.depth()],
+            },
+        }
+    }
+}
+
+// ある行動がMerkle木に含まれていることを、どの行動かを明かさずに証明する回路
+#[derive(Clone)]
+pub struct MerkleActivityCircuit {
+    // 公開入力
+    pub root: MerkleRoot,
+
+    // 秘密入力
+    pub activity_hash: Fr,
+    pub path: MerklePath,
+}
+
+impl MerkleActivityCircuit {
+    pub fn new(activity_hash: [u8; 32], path: MerklePath, root: MerkleRoot) -> Self {
+        Self {
+            root,
+            activity_hash: merkle_leaf(&activity_hash),
+            path,
+        }
+    }
+
+    // セットアップ用の空の回路（回路の形は木の深さだけで決まる）
+    pub fn blank(depth: usize) -> Self {
+        Self {
+            root: Fr::zero(),
+            activity_hash: Fr::zero(),
+            path: MerklePath {
+                index: 0,
+                siblings: vec![Fr::zero(); depth],
+            },
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MerkleActivityCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // 公開入力の割り当て
+        let root = FpVar::new_input(cs.clone(), || Ok(self.root))?;
+
+        // 秘密入力の割り当て
+        let leaf = FpVar::new_witness(cs.clone(), || Ok(self.activity_hash))?;
+
+        // 経路を辿って辿り着いた値が公開された根と一致することを制約として課す
+        let node = merkle_root_var(cs, leaf, &self.path)?;
+        node.enforce_equal(&root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::{prepare_verifying_key, Groth16};
+    use ark_relations::r1cs::ConstraintSystem;
+    use chrono::Utc;
+
+    use crate::hash_activity;
+    use crate::test_utils::seeded_rng;
+
+    fn sample_activities() -> Vec<ActivityData> {
+        ["walk", "run", "swim"]
+            .iter()
+            .map(|name| ActivityData::new(Utc::now(), hash_activity(name), [1u8; 32], [0u8; 32]))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_merkle_path_verifies() {
+        let rng = &mut seeded_rng();
+        let activities = sample_activities();
+        let (root, paths) = build_tree(&activities);
+
+        let depth = paths[0].siblings.len();
+        let pk =
+            Groth16::<Bn254>::generate_random_parameters_with_reduction(MerkleActivityCircuit::blank(depth), rng)
+                .unwrap();
+        let pvk = prepare_verifying_key(&pk.vk);
+
+        // どの葉の経路からも同じ根が得られる
+        for (activity, path) in activities.iter().zip(&paths) {
+            assert_eq!(path.compute_root(merkle_leaf(&activity.activity_hash)), root);
+        }
+
+        // 検証者は根だけを公開入力として受け取る
+        let circuit = MerkleActivityCircuit::new(activities[1].activity_hash, paths[1].clone(), root);
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, rng).unwrap();
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &[root]).unwrap());
+    }
+
+    #[test]
+    fn test_on_demand_paths_match_eager_tree() {
+        let activities: Vec<ActivityData> = (0..5)
+            .map(|i| ActivityData::new(Utc::now(), hash_activity(&format!("tree_{}", i)), [1u8; 32], [0u8; 32]))
+            .collect();
+        let (root, paths) = build_tree(&activities);
+        let tree = MerkleTree::new(&activities);
+        assert_eq!(tree.root(), root);
+        assert_eq!((tree.len(), tree.depth()), (5, 3));
+
+        // 1本ずつ組み立てた経路は、まとめて作った経路と同じで、同じ根に辿り着く
+        for index in [0, 1, 3, 4] {
+            let path = tree.path(index).unwrap();
+            assert_eq!(path, paths[index]);
+            assert_eq!(path.compute_root(merkle_leaf(&activities[index].activity_hash)), root);
+        }
+        assert!(tree.paths().eq(paths.into_iter()));
+
+        // 0で埋めた葉や範囲外の番号には経路を返さない
+        assert!(tree.path(5).is_none());
+        let single = MerkleTree::new(&activities[..1]);
+        assert_eq!(single.path(0).unwrap().siblings.len(), 0);
+        assert_eq!(single.root(), merkle_leaf(&activities[0].activity_hash));
+    }
+
+    #[test]
+    fn test_small_and_padded_trees() {
+        let leaves: Vec<Fr> = (1..=4u64).map(Fr::from).collect();
+
+        // 空の木の根は埋め草の葉（0）で、経路は1本もない
+        let (root, paths) = build_tree_from_leaves::<Fr>(Vec::new());
+        assert_eq!(root, Fr::zero());
+        assert!(paths.is_empty());
+        let empty = MerkleTree::<Fr>::from_leaves(Vec::new());
+        assert_eq!((empty.root(), empty.depth()), (Fr::zero(), 0));
+        assert!(empty.is_empty() && empty.path(0).is_none());
+
+        // 葉が1つなら根はその葉で、兄弟ノードのない経路になる
+        let (root, paths) = build_tree_from_leaves(leaves[..1].to_vec());
+        assert_eq!(root, leaves[0]);
+        assert_eq!(paths, vec![MerklePath { index: 0, siblings: vec![] }]);
+
+        // 3つなら右端に0の葉を1つ足した4つの木と同じ根になり、4つならそのまま
+        let (root, paths) = build_tree_from_leaves(leaves[..3].to_vec());
+        let padded = [leaves[0], leaves[1], leaves[2], Fr::zero()];
+        let expected = poseidon_hash(&[poseidon_hash(&padded[..2]), poseidon_hash(&padded[2..])]);
+        assert_eq!(root, expected);
+        assert_eq!(paths[2].siblings[0], Fr::zero());
+        let (full_root, full_paths) = build_tree_from_leaves(leaves.clone());
+        assert_ne!(full_root, root);
+        for (leaf, path) in leaves.iter().zip(&full_paths) {
+            assert_eq!(path.compute_root(*leaf), full_root);
+        }
+
+        // 埋め草と隣り合う葉も所属を証明でき、埋め草自身の所属は証明できない
+        let hashes = [hash_activity("walk"), hash_activity("run"), hash_activity("swim")];
+        let activities: Vec<ActivityData> =
+            hashes.iter().map(|hash| ActivityData::new(Utc::now(), *hash, [1u8; 32], [0u8; 32])).collect();
+        let (root, paths) = build_tree(&activities);
+        let is_satisfied = |activity_hash, path| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            // 0の葉は逆元がないので、割り当ての段階で失敗する
+            let circuit = MerkleActivityCircuit::new(activity_hash, path, root);
+            circuit.generate_constraints(cs.clone()).is_ok() && cs.is_satisfied().unwrap()
+        };
+        assert!(is_satisfied(hashes[2], paths[2].clone()));
+        let padding_path = MerklePath {
+            index: 3,
+            siblings: vec![merkle_leaf(&hashes[2]), paths[2].siblings[1]],
+        };
+        assert_eq!(padding_path.compute_root(Fr::zero()), root);
+        assert!(!is_satisfied([0u8; 32], padding_path));
+    }
+
+    #[test]
+    fn test_wrong_sibling_fails() {
+        let activities = sample_activities();
+        let (root, paths) = build_tree(&activities);
+
+        // 兄弟ノードを1つ書き換えると根に辿り着けない
+        let mut path = paths[0].clone();
+        path.siblings[1] += Fr::from(1u64);
+        assert_ne!(path.compute_root(merkle_leaf(&activities[0].activity_hash)), root);
+
+        let circuit = MerkleActivityCircuit::new(activities[0].activity_hash, path, root);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
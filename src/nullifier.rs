@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+
+// 使用済みnullifierの集合（同じ行動の二重計上を防ぐ）
+#[derive(Debug, Default)]
+pub struct NullifierSet<F: PrimeField = Fr> {
+    seen: HashSet<F>,
+}
+
+impl<F: PrimeField> NullifierSet<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 未使用なら登録してtrue、使用済みならfalseを返す
+    pub fn insert_if_new(&mut self, nullifier: F) -> bool {
+        self.seen.insert(nullifier)
+    }
+
+    pub fn contains(&self, nullifier: &F) -> bool {
+        self.seen.contains(nullifier)
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData, VerificationReport, ZkError};
+
+    #[test]
+    fn test_nullifier_rejects_duplicate_activity() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("counted_once"),
+            user_secret: test_secret(12),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // 同じ行動の証明は毎回同じnullifierになる
+        let (public_1, proof_1) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let (public_2, proof_2) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let nullifier_1 = verifier.verify_activity(&public_1, &proof_1).unwrap().nullifier.unwrap();
+        let nullifier_2 = verifier.verify_activity(&public_2, &proof_2).unwrap().nullifier.unwrap();
+        assert_eq!(nullifier_1, nullifier_2);
+
+        // 2回目の提出は集合によって拒否される
+        let mut nullifiers = NullifierSet::new();
+        assert!(nullifiers.insert_if_new(nullifier_1));
+        assert!(!nullifiers.insert_if_new(nullifier_2));
+        assert_eq!(nullifiers.len(), 1);
+
+        // 別のユーザーの同じ行動は別のnullifierになる
+        let other_user = ActivityData {
+            timestamp: activity.timestamp,
+            activity_hash: activity.activity_hash,
+            user_secret: test_secret(13),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_other, proof_other) = prover.prove_activity_with_rng(&other_user, rng).unwrap();
+        let nullifier_other = verifier.verify_activity(&public_other, &proof_other).unwrap().nullifier.unwrap();
+        assert_ne!(nullifier_1, nullifier_other);
+        assert!(nullifiers.insert_if_new(nullifier_other));
+    }
+
+    #[test]
+    fn test_forged_nullifier_fails_verification() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("forged_nullifier"),
+            user_secret: test_secret(12),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (mut public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // nullifierを差し替えて二重計上を逃れることはできない
+        public_activity.nullifier += Fr::from(1u64);
+        assert!(matches!(
+            verifier.verify_activity(&public_activity, &proof).and_then(VerificationReport::into_result),
+            Err(ZkError::VerificationFailed)
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::twisted_edwards::{Projective, TECurveConfig};
+use ark_ec::{AdditiveGroup, CurveConfig, CurveGroup, PrimeGroup};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_r1cs_std::{
+    boolean::Boolean, convert::ToBitsGadget, fields::fp::FpVar, groups::curves::twisted_edwards::AffineVar,
+    groups::CurveVar,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::circuit::{cached_per_type, poseidon_hash, poseidon_hash_var};
+use crate::error::ZkError;
+
+// Pedersenコミットメントを計算できる体（その体上で定義された埋め込み曲線を持つ）
+pub trait PedersenField: PrimeField + Absorb {
+    type Config: TECurveConfig<BaseField = Self>;
+}
+
+// BN254のスカラー体にはBaby Jubjub（ed-on-bn254）
+impl PedersenField for ark_bn254::Fr {
+    type Config = ark_ed_on_bn254::EdwardsConfig;
+}
+
+// BLS12-381のスカラー体にはJubjub
+impl PedersenField for ark_bls12_381::Fr {
+    type Config = ark_ed_on_bls12_381::EdwardsConfig;
+}
+
+// commit(value, randomness) = value・G + randomness・H を埋め込み曲線上で計算するコミットメント
+// 点は (x, y) をPoseidonで1つの体の元にまとめて公開する（xだけだと符号違いの点と区別できないため）
+pub struct PedersenCommitment<F: PedersenField = Fr>(PhantomData<F>);
+
+// 生成元の組と、回路内の固定基底スカラー倍に使う 2^i 倍の表
+struct Generators<F: PedersenField> {
+    g: Projective<F::Config>,
+    h: Projective<F::Config>,
+    g_powers: Vec<Projective<F::Config>>,
+    h_powers: Vec<Projective<F::Config>>,
+}
+
+impl<F: PedersenField> PedersenCommitment<F> {
+    // 固定の生成元 G, H（公開されたシードから導出するので、互いの離散対数は誰も知らない）
+    fn generators() -> &'static Generators<F> {
+        cached_per_type(|| {
+            let mut rng = ChaCha20Rng::from_seed(*b"zk_sample pedersen generators v1");
+            let g: Projective<F::Config> = ark_std::UniformRand::rand(&mut rng);
+            let h: Projective<F::Config> = ark_std::UniformRand::rand(&mut rng);
+            Generators {
+                g,
+                h,
+                g_powers: powers_of_two(g, F::MODULUS_BIT_SIZE as usize),
+                h_powers: powers_of_two(h, F::MODULUS_BIT_SIZE as usize),
+            }
+        })
+    }
+
+    // 値が埋め込み曲線の部分群の位数ℓ未満か（ℓ < p なので、ℓ以上の値は値+ℓと同じコミットメントになってしまう）
+    // コミットメントを開く値（秘密値とソルト）はこの範囲に限り、回路内でも同じ範囲を課す
+    pub fn in_range(value: F) -> bool {
+        value.into_bigint() <= Self::max_scalar().into_bigint()
+    }
+
+    // 秘密値とソルトの組がコミットメントを開く値として使えるか（どちらかがℓ以上ならSecretOutOfRange）
+    pub(crate) fn check_opening(value: F, randomness: F) -> Result<(), ZkError> {
+        if !Self::in_range(value) || !Self::in_range(randomness) {
+            return Err(ZkError::SecretOutOfRange);
+        }
+        Ok(())
+    }
+
+    // 部分群の位数より1小さい値（ℓ - 1）を体の元にしたもの
+    fn max_scalar() -> F {
+        let max = -<<F::Config as CurveConfig>::ScalarField as Field>::ONE;
+        F::from_le_bytes_mod_order(&max.into_bigint().to_bytes_le())
+    }
+
+    // 回路外でコミットメントを計算する
+    pub fn commit(value: F, randomness: F) -> F {
+        let generators = Self::generators();
+        let point = (generators.g.mul_bigint(value.into_bigint()) + generators.h.mul_bigint(randomness.into_bigint()))
+            .into_affine();
+        poseidon_hash(&[point.x, point.y])
+    }
+
+    // 回路内でコミットメントを計算する（commitと同じ計算）
+    // 値と乱数がℓ未満であることも課す（課さないと、同じコミットメントを値+ℓでも開けてしまい、
+    // 値から導くnullifierなどを利用者ごとに複数作れる）
+    pub(crate) fn commit_var(
+        cs: ConstraintSystemRef<F>,
+        value: &FpVar<F>,
+        randomness: &FpVar<F>,
+    ) -> Result<FpVar<F>, SynthesisError> {
+        let generators = Self::generators();
+
+        // 生成元は固定なので、2^i 倍の表を使ってビットごとに足し込む
+        let max = Self::max_scalar().into_bigint();
+        let value_bits = value.to_bits_le()?;
+        let randomness_bits = randomness.to_bits_le()?;
+        Boolean::enforce_smaller_or_equal_than_le(&value_bits, max)?;
+        Boolean::enforce_smaller_or_equal_than_le(&randomness_bits, max)?;
+
+        let mut point = AffineVar::<F::Config, FpVar<F>>::zero();
+        point.precomputed_base_scalar_mul_le(value_bits.iter().zip(&generators.g_powers))?;
+        point.precomputed_base_scalar_mul_le(randomness_bits.iter().zip(&generators.h_powers))?;
+        poseidon_hash_var(cs, &[point.x, point.y])
+    }
+}
+
+// base, 2・base, 4・base, ... を count 個並べる
+fn powers_of_two<P: TECurveConfig>(base: Projective<P>, count: usize) -> Vec<Projective<P>> {
+    core::iter::successors(Some(base), |power| Some(power.double()))
+        .take(count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use chrono::Utc;
+
+    use crate::{hash_activity, ActivityCircuit};
+
+    #[test]
+    fn test_native_and_circuit_commitments_agree() {
+        let value = Fr::from_be_bytes_mod_order(&[5u8; 32]);
+        let randomness = Fr::from_be_bytes_mod_order(&[6u8; 32]);
+
+        // 回路内の計算と回路外の計算が同じ値になる
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value_var = FpVar::new_witness(cs.clone(), || Ok(value)).unwrap();
+        let randomness_var = FpVar::new_witness(cs.clone(), || Ok(randomness)).unwrap();
+        let commitment_var = PedersenCommitment::commit_var(cs.clone(), &value_var, &randomness_var).unwrap();
+        assert_eq!(commitment_var.value().unwrap(), PedersenCommitment::commit(value, randomness));
+        assert!(cs.is_satisfied().unwrap());
+
+        // 乱数が違えば同じ値でも別のコミットメントになる（値を隠す）
+        assert_ne!(
+            PedersenCommitment::commit(value, randomness),
+            PedersenCommitment::commit(value, randomness + Fr::from(1u64))
+        );
+        // 値を取り替えても同じコミットメントにはならない
+        assert_ne!(
+            PedersenCommitment::commit(value, randomness),
+            PedersenCommitment::commit(value + Fr::from(1u64), randomness)
+        );
+    }
+
+    #[test]
+    fn test_activity_circuit_requires_commitment_opening() {
+        let now = Utc::now();
+        let circuit =
+            ActivityCircuit::<Fr>::new(now, hash_activity("pedersen_opening"), [5u8; 32], [6u8; 32], now).unwrap();
+        assert_eq!(circuit.commitment, PedersenCommitment::commit(circuit.user_secret, circuit.salt));
+
+        // 正しい開示（値と乱数）なら制約を満たす
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // 乱数だけを書き換えると公開されたコミットメントと一致しない
+        let mut wrong_randomness = circuit;
+        wrong_randomness.salt += Fr::from(1u64);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        wrong_randomness.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+}
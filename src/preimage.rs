@@ -0,0 +1,110 @@
+use ark_crypto_primitives::crh::sha256::constraints::Sha256Gadget;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{convert::ToBitsGadget, fields::fp::FpVar, fields::FieldVar, uint8::UInt8};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+
+use crate::activity::ACTIVITY_HASH_DOMAIN;
+
+// 回路内で hash_activity（Sha256Hasher）と同じ計算（SHA256(ACTIVITY_HASH_DOMAIN || preimage) を体の元に変換）をする
+// 原像は秘密入力で、長さがそのまま回路の形になるので、同じ長さで生成した鍵でしか証明できない
+// SHA256はビット演算を1ビットずつ制約にするので重く、64バイトのブロックごとに約3.4万制約増える。
+// タグの21バイトとパディングを含めて1ブロックに収まる34バイトまでの原像で回路全体は約4.1万制約
+// （原像なしの4,659制約の約9倍）、35バイトからは2ブロックで約7.5万制約になる
+pub(crate) fn activity_hash_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    preimage: &[u8],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut message = UInt8::constant_vec(ACTIVITY_HASH_DOMAIN);
+    message.extend(UInt8::new_witness_vec(cs, preimage)?);
+    let digest = Sha256Gadget::digest(&message)?;
+
+    // ダイジェストをビッグエンディアンの整数として体の元にする（from_be_bytes_mod_orderと同じくpを法として簡約される）
+    // 係数を掛けて足すだけなので制約は増えない
+    let mut value = FpVar::zero();
+    for byte in &digest.0 {
+        for bit in byte.to_bits_le()?.into_iter().rev() {
+            value = value.double()? + FpVar::from(bit);
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use chrono::{Duration, Utc};
+
+    use crate::circuit::ActivityCircuit;
+    use crate::test_utils::{seeded_rng, test_secret};
+    use crate::{hash_activity, ActivityData, Setup, ZkError};
+
+    #[test]
+    fn test_gadget_matches_hash_activity() {
+        // 1ブロックに収まる最大の34バイトと、2ブロックになる原像も含める
+        let activities = [
+            "",
+            "walk",
+            "thirty-four bytes of activity text",
+            "an activity name long enough for two blocks",
+        ];
+        for activity in activities {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let value = activity_hash_var(cs.clone(), activity.as_bytes()).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(value.value().unwrap(), Fr::from_be_bytes_mod_order(&hash_activity(activity)));
+        }
+    }
+
+    #[test]
+    fn test_correct_preimage_verifies() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = Setup::<ark_bn254::Bn254>::generate_with_preimage_length(4, rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("walk"), [6u8; 32], test_secret(7));
+
+        let (public_activity, proof) = prover.prove_preimage_with_rng(&activity, "walk", rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        // 原像を持たない証明者の鍵では、同じ公開入力でも検証を通らない
+        let (plain_prover, _) = Setup::generate_with_rng(rng);
+        let (_, plain_proof) = plain_prover.prove_activity_with_rng(&activity, rng).unwrap();
+        assert!(!verifier.verify_activity_bool(&public_activity, &plain_proof));
+    }
+
+    #[test]
+    fn test_wrong_preimage_fails() {
+        let rng = &mut seeded_rng();
+        let (prover, _) = Setup::<ark_bn254::Bn254>::generate_with_preimage_length(4, rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("walk"), [6u8; 32], test_secret(7));
+
+        // 同じ長さでもハッシュが一致しない原像は制約を満たさない
+        assert!(matches!(
+            prover.prove_preimage_with_rng(&activity, "swim", rng),
+            Err(ZkError::Unsatisfied)
+        ));
+        let mut circuit = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            activity.timestamp - Duration::days(30),
+        )
+        .unwrap();
+        circuit.preimage = Some(b"swim".to_vec());
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // 長さが鍵と違う原像や、原像なしの証明は生成前にエラー
+        assert!(matches!(
+            prover.prove_preimage_with_rng(&activity, "walks", rng),
+            Err(ZkError::PreimageLength { expected: 4, got: 5 })
+        ));
+        assert!(matches!(
+            prover.prove_activity_with_rng(&activity, rng),
+            Err(ZkError::MissingField("preimage"))
+        ));
+    }
+}
@@ -0,0 +1,1143 @@
+#[cfg(feature = "async")]
+use core::future::Future;
+use core::ops::Deref;
+
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::sync::{mpsc, Condvar, Mutex, OnceLock};
+#[cfg(feature = "std")]
+use std::thread;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::Groth16;
+#[cfg(feature = "std")]
+use ark_relations::r1cs::{ConstraintMatrices, OptimizationGoal};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef};
+#[cfg(feature = "std")]
+use ark_std::UniformRand;
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+#[cfg(feature = "std")]
+use crate::activity::UserId;
+use crate::activity::{checked_sub, hash_to_field_be, unix_seconds, ActivityData, PublicActivity};
+use crate::block::{BlockClock, PublicBlockActivity, DEFAULT_BLOCK_WINDOW};
+#[cfg(feature = "std")]
+use crate::circuit::wipe_assignment;
+use crate::circuit::{unsatisfied_indices, ActivityCircuit};
+use crate::envelope::circuit_id;
+#[cfg(feature = "std")]
+use crate::envelope::ProofEnvelope;
+use crate::epoch::EpochConfig;
+use crate::error::ZkError;
+use crate::merkle::Allowlist;
+use crate::pedersen::PedersenField;
+#[cfg(feature = "std")]
+use crate::setup::{read_key, write_key, SetupError};
+use crate::verifier::DEFAULT_FRESHNESS_WINDOW;
+
+// 証明者の実装（証明キーのみを保持する。Eはペアリング曲線、デフォルトはBN254）
+// 証明キーと回路の行列は共有するので、スレッドに渡すための複製は安い
+#[derive(Clone)]
+pub struct ActivityProver<E: Pairing = Bn254> {
+    pub(crate) proving_key: Arc<ark_groth16::ProvingKey<E>>,
+    freshness_window: Duration,
+    block_window: u64,
+    allowlist: Option<Allowlist<E::ScalarField>>,
+    epoch: Option<EpochConfig>,
+    preimage_length: Option<usize>,
+    salted_hash: bool,
+    range: bool,
+    // refresh_timestamp用に保存する回路の行列（許可リストやエポックの設定を変えると作り直す）
+    #[cfg(feature = "std")]
+    matrices: Arc<OnceLock<ConstraintMatrices<E::ScalarField>>>,
+}
+
+// 曲線ごとの証明者
+pub type Bn254Prover = ActivityProver<Bn254>;
+pub type Bls12Prover = ActivityProver<Bls12_381>;
+
+// 証明する回路と、検証者に渡す公開部分の組
+type CircuitWithPublic<F> = (ActivityCircuit<F>, PublicActivity<F>);
+
+impl<E: Pairing> ActivityProver<E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 証明キーから証明者を作成（鮮度の期間はデフォルト値）
+    pub(crate) fn from_proving_key(proving_key: ark_groth16::ProvingKey<E>) -> Self {
+        Self::from_shared_proving_key(Arc::new(proving_key))
+    }
+
+    // 他の証明者と共有する証明キーから証明者を作成
+    pub(crate) fn from_shared_proving_key(proving_key: Arc<ark_groth16::ProvingKey<E>>) -> Self {
+        Self {
+            proving_key,
+            freshness_window: DEFAULT_FRESHNESS_WINDOW,
+            block_window: DEFAULT_BLOCK_WINDOW,
+            allowlist: None,
+            epoch: None,
+            preimage_length: None,
+            salted_hash: false,
+            range: false,
+            #[cfg(feature = "std")]
+            matrices: Arc::default(),
+        }
+    }
+
+    // 許可リストに含まれる行動だけを、どの行動かを明かさずに証明する（鍵も同じ深さの許可リストで生成したもの）
+    pub fn with_allowlist(mut self, hashes: &[[u8; 32]]) -> Self {
+        self.allowlist = Some(Allowlist::new(hashes));
+        #[cfg(feature = "std")]
+        {
+            self.matrices = Arc::default();
+        }
+        self
+    }
+
+    // 正確な時刻を秘密にし、行動が属するエポックの番号だけを公開する（鍵も同じ設定で生成したもの）
+    pub fn with_epoch(mut self, config: EpochConfig) -> Self {
+        self.epoch = Some(config);
+        #[cfg(feature = "std")]
+        {
+            self.matrices = Arc::default();
+        }
+        self
+    }
+
+    // 行動ハッシュの原像（行動名）を知っていることも証明する（鍵も同じ長さの原像で生成したもの）
+    // この設定では原像を渡すprove_preimageでしか証明できない
+    pub fn with_preimage_length(mut self, length: usize) -> Self {
+        self.preimage_length = Some(length);
+        #[cfg(feature = "std")]
+        {
+            self.matrices = Arc::default();
+        }
+        self
+    }
+
+    // ソルト付きの行動ハッシュで証明する（鍵もSetup::generate_with_salted_hashで生成したもの）
+    // この設定ではActivityData::with_saltでソルトを設定した行動しか証明できず、設定がなければソルトは使えない
+    pub fn with_salted_hash(mut self) -> Self {
+        self.salted_hash = true;
+        #[cfg(feature = "std")]
+        {
+            self.matrices = Arc::default();
+        }
+        self
+    }
+
+    // 範囲付きの回路で証明する（鍵もSetup::generate_with_rangeで生成したもの）
+    // この設定ではprove_in_rangeでしか証明できず、設定がなければprove_in_rangeは使えない
+    pub fn with_range(mut self) -> Self {
+        self.range = true;
+        #[cfg(feature = "std")]
+        {
+            self.matrices = Arc::default();
+        }
+        self
+    }
+
+    // 回路内で下限として証明する期間を変更する
+    pub fn with_freshness_window(mut self, freshness_window: Duration) -> Self {
+        self.freshness_window = freshness_window;
+        self
+    }
+
+    // ブロック高で証明するときの鮮度の期間（ブロック数）を変更する
+    pub fn with_block_window(mut self, window_blocks: u64) -> Self {
+        self.block_window = window_blocks;
+        self
+    }
+
+    // 証明キー（シードから生成し直した鍵との比較など、監査用）
+    pub fn proving_key(&self) -> &ark_groth16::ProvingKey<E> {
+        &self.proving_key
+    }
+
+    // 証明キーに含まれる検証キーから計算した回路のID（封筒に入れ、検証者が自分の鍵と照合する）
+    pub fn circuit_id(&self) -> [u8; 32] {
+        circuit_id(&self.proving_key.vk)
+    }
+
+    // 証明キーをファイルに保存
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_key(&*self.proving_key, path)
+    }
+
+    // 保存済みの証明キーを読み込む
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SetupError> {
+        Ok(Self::from_proving_key(read_key(path)?))
+    }
+
+    // 証明の生成（証明ごとの乱数は呼び出し側のRNGから取る）
+    #[cfg(feature = "std")]
+    pub fn generate_proof<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        rng: &mut R,
+    ) -> Result<ark_groth16::Proof<E>, ZkError> {
+        Ok(self.prove_activity_with_rng(activity_data, rng)?.1)
+    }
+
+    // 期限までに終わらなければZkError::Timeoutを返す証明の生成（乱数はOsRngを使用）
+    #[cfg(feature = "std")]
+    pub fn generate_proof_with_deadline(
+        &self,
+        activity_data: &ActivityData,
+        deadline: Instant,
+    ) -> Result<ark_groth16::Proof<E>, ZkError> {
+        Ok(self.prove_activity_with_deadline(activity_data, deadline)?.1)
+    }
+
+    // 証明と公開部分を、期限付きで生成する
+    // arkworksの証明生成は途中で止められないので、別スレッドで実行して期限を過ぎたら結果を待たずに戻る。
+    // 置き去りにしたスレッドは証明が終わるまでCPUを使い続け、結果は捨てられる。
+    // 期限付きの証明のスレッドは置き去りにしたものも含めてCPUの数までしか動かさず、空きを待つうちに期限を過ぎれば
+    // スレッドを作らずにZkError::Timeoutを返す（証明者の複製は鍵を共有し、行動のデータだけを複製して渡す）
+    #[cfg(feature = "std")]
+    pub fn prove_activity_with_deadline(
+        &self,
+        activity_data: &ActivityData,
+        deadline: Instant,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let slot = DeadlineSlot::acquire(deadline)?;
+        let prover = self.clone();
+        let activity_data = activity_data.clone();
+        let (sender, receiver) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            // 枠はスレッドが終わるまで（パニックした場合も）持ち続ける
+            let _slot = slot;
+            // 期限を過ぎて受信側がなくなっていれば送信は失敗するが、結果は捨てるだけでよい
+            let _ = sender.send(prover.prove_activity(&activity_data));
+        });
+
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(ZkError::Timeout),
+            // 送信せずに送信側がなくなるのはスレッドがパニックした場合だけ
+            Err(mpsc::RecvTimeoutError::Disconnected) => match worker.join() {
+                Err(panic) => std::panic::resume_unwind(panic),
+                Ok(()) => unreachable!("The proving thread always sends its result"),
+            },
+        }
+    }
+
+    // 証明と、検証者に渡す公開部分を合わせて生成（OsRngを使用）
+    #[cfg(feature = "std")]
+    pub fn prove_activity(
+        &self,
+        activity_data: &ActivityData,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        self.prove_activity_with_rng(activity_data, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで証明と公開部分を生成
+    #[cfg(feature = "std")]
+    pub fn prove_activity_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        // 現在時刻から鮮度の期間だけ遡った時刻を下限とする（公開入力は秒単位）
+        let cutoff = self.cutoff_before(Utc::now())?;
+        self.prove_with_cutoff(activity_data, cutoff, rng)
+    }
+
+    // 証明と回路に割り当てた公開入力を、1つの封筒にまとめて生成する（OsRngを使用）
+    #[cfg(feature = "std")]
+    pub fn prove_envelope(&self, activity_data: &ActivityData) -> Result<ProofEnvelope<E>, ZkError> {
+        self.prove_envelope_with_rng(activity_data, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで封筒を生成する
+    // 公開入力は回路が割り当てた値をそのまま入れるので、許可リストやエポックの設定でも検証者は組み立て直さずに済む
+    #[cfg(feature = "std")]
+    pub fn prove_envelope_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        rng: &mut R,
+    ) -> Result<ProofEnvelope<E>, ZkError> {
+        let cutoff = self.cutoff_before(Utc::now())?;
+        let (circuit, public_activity) = self.circuit(activity_data, cutoff, E::ScalarField::zero(), None)?;
+        let public_inputs = circuit.public_inputs();
+        let (_, proof) = self.prove_circuit(circuit, public_activity, rng)?;
+        Ok(ProofEnvelope::new(self.circuit_id(), public_inputs, proof))
+    }
+
+    // 検証者が発行したチャレンジに対して証明を生成する（そのチャレンジでしか検証を通らない）
+    #[cfg(feature = "std")]
+    pub fn prove_activity_for_challenge(
+        &self,
+        activity_data: &ActivityData,
+        challenge: E::ScalarField,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        self.prove_activity_for_challenge_with_rng(activity_data, challenge, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGでチャレンジに対する証明を生成
+    #[cfg(feature = "std")]
+    pub fn prove_activity_for_challenge_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        challenge: E::ScalarField,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let cutoff = self.cutoff_before(Utc::now())?;
+        self.prove(activity_data, cutoff, challenge, None, rng)
+    }
+
+    // 利用者のIDに束縛した証明を生成する（そのIDでverify_for_userしたときだけ検証を通る）
+    #[cfg(feature = "std")]
+    pub fn prove_activity_for_user(
+        &self,
+        activity_data: &ActivityData,
+        user: UserId,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        self.prove_activity_for_user_with_rng(activity_data, user, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで利用者のIDに束縛した証明を生成
+    #[cfg(feature = "std")]
+    pub fn prove_activity_for_user_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        user: UserId,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let cutoff = self.cutoff_before(Utc::now())?;
+        let (mut circuit, mut public_activity) = self.circuit(activity_data, cutoff, E::ScalarField::zero(), None)?;
+        circuit.user_id = user.to_field();
+        public_activity.user_id = circuit.user_id;
+        self.prove_circuit(circuit, public_activity, rng)
+    }
+
+    // 有効期限を束縛した証明を生成する（検証者の時計で期限を過ぎると、行動が新しくても検証を通らない）
+    #[cfg(feature = "std")]
+    pub fn prove_activity_until(
+        &self,
+        activity_data: &ActivityData,
+        expiry: DateTime<Utc>,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        self.prove_activity_until_with_rng(activity_data, expiry, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで有効期限を束縛した証明を生成
+    // 回路はtimestamp <= expiryを課すので、行動より前の期限では証明できずExpiredProofを返す
+    #[cfg(feature = "std")]
+    pub fn prove_activity_until_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        expiry: DateTime<Utc>,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let cutoff = self.cutoff_before(Utc::now())?;
+        let (mut circuit, mut public_activity) = self.circuit(activity_data, cutoff, E::ScalarField::zero(), None)?;
+        circuit.expiry = unix_seconds(expiry)?;
+        if circuit.expiry < unix_seconds(activity_data.timestamp)? {
+            return Err(ZkError::ExpiredProof);
+        }
+        public_activity.expiry = circuit.expiry;
+        self.prove_circuit(circuit, public_activity, rng)
+    }
+
+    // 行動ハッシュの原像（hash_activityに渡した行動名）とともに証明を生成する（OsRngを使用）
+    #[cfg(feature = "std")]
+    pub fn prove_preimage(
+        &self,
+        activity_data: &ActivityData,
+        activity: &str,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        self.prove_preimage_with_rng(activity_data, activity, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで原像とともに証明を生成
+    // 原像は with_preimage_length を設定した証明者でだけ回路に渡し、それ以外では使わない
+    #[cfg(feature = "std")]
+    pub fn prove_preimage_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        activity: &str,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let cutoff = self.cutoff_before(Utc::now())?;
+        self.prove(activity_data, cutoff, E::ScalarField::zero(), Some(activity.as_bytes()), rng)
+    }
+
+    // テストのゴールデンデータ用に、シードから決定的に証明を生成する
+    // 同じシードの証明を使い回すと乱数の再利用になり安全ではないので、本番では絶対に使わないこと
+    // 下限も現在時刻ではなく行動の時刻から決めるので、同じ入力とシードなら常に同じバイト列になる
+    pub fn generate_proof_deterministic(
+        &self,
+        activity_data: &ActivityData,
+        seed: [u8; 32],
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let cutoff = self.cutoff_before(activity_data.timestamp)?;
+        self.prove_with_cutoff(activity_data, cutoff, &mut ChaCha20Rng::from_seed(seed))
+    }
+
+    // 指定した下限で証明と公開部分を生成（現在時刻を取れないno_std環境では呼び出し側が下限を決める）
+    pub fn prove_with_cutoff<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        cutoff: DateTime<Utc>,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        self.prove(activity_data, cutoff, E::ScalarField::zero(), None, rng)
+    }
+
+    // 行動が [start, end] の範囲（両端を含む）に含まれることを、正確な時刻を明かさずに証明する
+    // 鍵はSetup::generate_with_rangeで生成したもの。公開部分のタイムスタンプと下限は範囲の終わりと始まりになる
+    pub fn prove_in_range<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        if !self.range {
+            return Err(ZkError::RangeMismatch);
+        }
+        if start > end || self.epoch.is_some() {
+            return Err(ZkError::InvalidRange);
+        }
+        let (mut circuit, mut public_activity) =
+            self.circuit_with_range(activity_data, start, E::ScalarField::zero(), None)?;
+        circuit.range_end = Some(unix_seconds(end)?);
+        public_activity.timestamp = end;
+        self.prove_circuit(circuit, public_activity, rng)
+    }
+
+    // 壁時計の代わりにブロック高で鮮度を証明する（鍵は時刻の場合と同じもの）
+    // 公開部分のタイムスタンプと下限の位置には、行動のブロック高と clock.current - ブロック数の期間 が入る。
+    // 行動データのタイムスタンプは使わない。エポックとは併用しない
+    pub fn prove_at_block<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        activity_block: u64,
+        clock: BlockClock,
+        rng: &mut R,
+    ) -> Result<(PublicBlockActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        if self.epoch.is_some() {
+            return Err(ZkError::BlockWithEpoch);
+        }
+        if activity_block > clock.current {
+            return Err(ZkError::FutureTimestamp);
+        }
+        // 許可リストなどの設定は時刻の場合と同じように回路に反映し、タイムスタンプと下限だけをブロック高に置き換える
+        let cutoff_block = clock.cutoff(self.block_window);
+        let (mut circuit, public_activity) =
+            self.circuit(activity_data, activity_data.timestamp, E::ScalarField::zero(), None)?;
+        circuit.timestamp = activity_block;
+        circuit.cutoff = cutoff_block;
+
+        let (public_activity, proof) = self.prove_circuit(circuit, public_activity, rng)?;
+        let public_activity = PublicBlockActivity {
+            activity_block,
+            activity_hash: public_activity.activity_hash,
+            commitment: public_activity.commitment,
+            cutoff_block,
+            nullifier: public_activity.nullifier,
+            challenge: public_activity.challenge,
+            user_id: public_activity.user_id,
+        };
+        Ok((public_activity, proof))
+    }
+
+    // 証明を生成せずに、入力が回路の制約を満たすかだけを確かめる（現在時刻から下限を決める）
+    // 満たさなければ、最初に満たされない制約の番号をUnsatisfiedConstraintで返す
+    #[cfg(feature = "std")]
+    pub fn check_satisfied(&self, activity_data: &ActivityData) -> Result<(), ZkError> {
+        let cutoff = self.cutoff_before(Utc::now())?;
+        self.check_satisfied_with_cutoff(activity_data, cutoff)
+    }
+
+    // 指定した下限で制約を満たすかを確かめる（回路の合成だけなので、証明の生成よりずっと速い）
+    pub fn check_satisfied_with_cutoff(
+        &self,
+        activity_data: &ActivityData,
+        cutoff: DateTime<Utc>,
+    ) -> Result<(), ZkError> {
+        let (circuit, _) = self.circuit(activity_data, cutoff, E::ScalarField::zero(), None)?;
+        let cs = WitnessSystem::new();
+        circuit.generate_constraints(cs.clone())?;
+        if cs.is_satisfied()? {
+            return Ok(());
+        }
+        match first_unsatisfied(&cs)? {
+            Some(index) => Err(ZkError::UnsatisfiedConstraint(index)),
+            None => Err(ZkError::Unsatisfied),
+        }
+    }
+
+    // 同じ秘密値と行動で、タイムスタンプだけを新しくした証明を生成する
+    // Groth16の証明は証拠全体に依存する（商多項式hの計算と、証明ごとの乱数による再ランダム化）ため、
+    // 前回の証明を部分的に更新することはできない。代わりに回路の形（R1CSの行列）を初回に保存しておき、
+    // 2回目以降は行列の構築と、証明システム内での回路の再合成を省く
+    // 証明時間の大半は商多項式とMSMの計算なので効果は限られる（benches/prove_verify.rsのproveとrefreshで比較できる）
+    #[cfg(feature = "std")]
+    pub fn refresh_timestamp<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        new_timestamp: DateTime<Utc>,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let activity_data = ActivityData {
+            timestamp: new_timestamp,
+            ..activity_data.clone()
+        };
+        let cutoff = self.cutoff_before(Utc::now())?;
+        let (circuit, public_activity) = self.circuit(&activity_data, cutoff, E::ScalarField::zero(), None)?;
+
+        // 証明システムと同じ最適化の設定で1回だけ合成し、制約の検査と証明の両方に使う
+        let cs = WitnessSystem::new();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        circuit.generate_constraints(cs.clone())?;
+        if !cs.is_satisfied()? {
+            return Err(ZkError::Unsatisfied);
+        }
+        let matrices = self.matrices.get_or_init(|| {
+            cs.finalize();
+            cs.to_matrices().expect("Matrices are constructed in prove mode")
+        });
+
+        let (num_inputs, num_constraints) = (cs.num_instance_variables(), cs.num_constraints());
+        let mut full_assignment = {
+            let cs = cs.borrow().expect("The constraint system is not shared");
+            [cs.instance_assignment.as_slice(), cs.witness_assignment.as_slice()].concat()
+        };
+        let (r, s) = (E::ScalarField::rand(rng), E::ScalarField::rand(rng));
+        let proof = Groth16::<E>::create_proof_with_reduction_and_matrices(
+            &self.proving_key,
+            r,
+            s,
+            matrices,
+            num_inputs,
+            num_constraints,
+            &full_assignment,
+        );
+        wipe_assignment(&mut full_assignment);
+        Ok((public_activity, proof?))
+    }
+
+    fn prove<R: RngCore + CryptoRng>(
+        &self,
+        activity_data: &ActivityData,
+        cutoff: DateTime<Utc>,
+        challenge: E::ScalarField,
+        preimage: Option<&[u8]>,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        let (circuit, public_activity) = self.circuit(activity_data, cutoff, challenge, preimage)?;
+        self.prove_circuit(circuit, public_activity, rng)
+    }
+
+    fn prove_circuit<R: RngCore + CryptoRng>(
+        &self,
+        circuit: ActivityCircuit<E::ScalarField>,
+        public_activity: PublicActivity<E::ScalarField>,
+        rng: &mut R,
+    ) -> Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError> {
+        // 秘密入力や証拠の値は記録せず、回路の大きさと所要時間、結果だけを記録する
+        #[cfg(feature = "tracing")]
+        let (_span, started) = (tracing::debug_span!("prove").entered(), std::time::Instant::now());
+
+        // 制約を満たさない入力は証明生成前に弾く
+        let cs = WitnessSystem::new();
+        circuit.clone().generate_constraints(cs.clone())?;
+        if !cs.is_satisfied()? {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(constraints = cs.num_constraints(), "inputs do not satisfy the circuit");
+            return Err(ZkError::Unsatisfied);
+        }
+
+        // 証明の生成
+        let proof = Groth16::<E>::create_random_proof_with_reduction(circuit, &self.proving_key, rng)?;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            constraints = cs.num_constraints(),
+            "generated proof"
+        );
+        Ok((public_activity, proof))
+    }
+
+    // 基準時刻から鮮度の期間だけ遡った下限（公開入力は秒単位なので秒未満は切り捨てる）
+    fn cutoff_before(&self, time: DateTime<Utc>) -> Result<DateTime<Utc>, ZkError> {
+        Ok(checked_sub(time, self.freshness_window)?.trunc_subsecs(0))
+    }
+
+    // 証明者の設定（許可リスト・エポック・原像）に合わせた回路と、検証者に渡す公開部分
+    // 範囲付きの鍵では範囲の終わりを渡さないと証明が検証を通らないので、prove_in_range以外ではRangeMismatch
+    fn circuit(
+        &self,
+        activity_data: &ActivityData,
+        cutoff: DateTime<Utc>,
+        challenge: E::ScalarField,
+        preimage: Option<&[u8]>,
+    ) -> Result<CircuitWithPublic<E::ScalarField>, ZkError> {
+        if self.range {
+            return Err(ZkError::RangeMismatch);
+        }
+        self.circuit_with_range(activity_data, cutoff, challenge, preimage)
+    }
+
+    // circuitと同じだが範囲付きの鍵でも作る（範囲の終わりは呼び出し側が設定する）
+    fn circuit_with_range(
+        &self,
+        activity_data: &ActivityData,
+        cutoff: DateTime<Utc>,
+        challenge: E::ScalarField,
+        preimage: Option<&[u8]>,
+    ) -> Result<CircuitWithPublic<E::ScalarField>, ZkError> {
+        // 回路の作成
+        let mut circuit = ActivityCircuit::<E::ScalarField>::new(
+            activity_data.timestamp,
+            activity_data.activity_hash,
+            activity_data.user_secret,
+            activity_data.salt,
+            cutoff,
+        )?;
+        circuit.challenge = challenge;
+        let mut public_activity = activity_data.public(cutoff);
+        public_activity.challenge = challenge;
+
+        // 許可リストを使う場合は経路を証拠として渡し、公開部分の行動ハッシュは0で隠す
+        if let Some(allowlist) = &self.allowlist {
+            circuit.allowlist = Some(
+                allowlist
+                    .membership(&activity_data.activity_hash)
+                    .ok_or(ZkError::NotAllowlisted)?,
+            );
+            public_activity.activity_hash = [0u8; 32];
+        }
+
+        // エポックを使う場合は、公開部分のタイムスタンプをエポックの開始時刻に丸める
+        if let Some(config) = &self.epoch {
+            let window = config.window(activity_data.timestamp)?;
+            circuit.epoch = Some(window);
+            public_activity.timestamp = config.epoch_start(window.index)?;
+        }
+
+        // ソルトは鍵がソルト付きの回路で生成されている場合にだけ証拠として渡す（回路の形が変わるため）
+        match (self.salted_hash, activity_data.hash_salt) {
+            (true, Some(hash_salt)) => circuit.hash_salt = Some(hash_to_field_be(&hash_salt)),
+            (false, None) => {}
+            _ => return Err(ZkError::SaltedHashMismatch),
+        }
+
+        // 原像を使う場合は、鍵と同じ長さの原像を証拠として渡す（ハッシュが一致するかは回路の制約で確かめる）
+        if let Some(expected) = self.preimage_length {
+            let preimage = preimage.ok_or(ZkError::MissingField("preimage"))?;
+            if preimage.len() != expected {
+                return Err(ZkError::PreimageLength {
+                    expected,
+                    got: preimage.len(),
+                });
+            }
+            circuit.preimage = Some(preimage.to_vec());
+        }
+
+        Ok((circuit, public_activity))
+    }
+}
+
+// 期限付きの証明で動いているスレッドの数と、枠が空いたことの通知
+#[cfg(feature = "std")]
+static DEADLINE_WORKERS: (Mutex<usize>, Condvar) = (Mutex::new(0), Condvar::new());
+
+// 期限付きの証明のスレッド1つ分の枠（手放すと数を戻し、待っている呼び出しを起こす）
+#[cfg(feature = "std")]
+struct DeadlineSlot;
+
+#[cfg(feature = "std")]
+impl DeadlineSlot {
+    // CPUの数まで枠を割り当て、埋まっていれば期限まで空きを待つ
+    fn acquire(deadline: Instant) -> Result<Self, ZkError> {
+        let limit = thread::available_parallelism().map_or(1, |n| n.get());
+        let (running, freed) = &DEADLINE_WORKERS;
+        let mut running = running.lock().unwrap_or_else(|e| e.into_inner());
+        while *running >= limit {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(ZkError::Timeout);
+            }
+            running = freed.wait_timeout(running, remaining).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        *running += 1;
+        Ok(Self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for DeadlineSlot {
+    fn drop(&mut self) {
+        let (running, freed) = &DEADLINE_WORKERS;
+        *running.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        freed.notify_one();
+    }
+}
+
+// 最初に満たされない制約の番号を探す
+// ConstraintSystem::which_is_unsatisfiedは制約の名前を得るのにトレースの設定を必要とし、
+// 設定がなければ標準エラーに警告を出すので、行列と割り当てから直接評価する
+fn first_unsatisfied<F: PrimeField>(cs: &ConstraintSystemRef<F>) -> Result<Option<usize>, ZkError> {
+    Ok(unsatisfied_indices(cs)?.first().copied())
+}
+
+// 証明者が合成する制約システム（zeroize機能では、手放すときに秘密入力の割り当てを0で上書きする）
+// arkworksが証明の生成の中で合成し直す制約システムは、このクレートからは消せない
+struct WitnessSystem<F: PrimeField>(ConstraintSystemRef<F>);
+
+impl<F: PrimeField> WitnessSystem<F> {
+    fn new() -> Self {
+        Self(ConstraintSystem::new_ref())
+    }
+}
+
+impl<F: PrimeField> Deref for WitnessSystem<F> {
+    type Target = ConstraintSystemRef<F>;
+
+    fn deref(&self) -> &ConstraintSystemRef<F> {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> Drop for WitnessSystem<F> {
+    fn drop(&mut self) {
+        if let Some(mut cs) = self.0.borrow_mut() {
+            cs.witness_assignment.zeroize();
+        }
+    }
+}
+
+// 非同期の実行器から使う証明生成（同期版をtokioのブロッキング用スレッドで実行する）
+#[cfg(feature = "async")]
+impl<E: Pairing> ActivityProver<E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 証明の生成を別スレッドに任せ、完了を待つFutureを返す（乱数はOsRngを使用）
+    pub fn generate_proof_async(
+        &self,
+        activity_data: &ActivityData,
+    ) -> impl Future<Output = Result<ark_groth16::Proof<E>, ZkError>> + Send + 'static {
+        let proving = self.prove_activity_async(activity_data);
+        async move { Ok(proving.await?.1) }
+    }
+
+    // 証明と、検証者に渡す公開部分を合わせて非同期に生成
+    // スレッドに渡すため、証明者（証明キーは共有する）と行動のデータは複製する
+    pub fn prove_activity_async(
+        &self,
+        activity_data: &ActivityData,
+    ) -> impl Future<Output = Result<(PublicActivity<E::ScalarField>, ark_groth16::Proof<E>), ZkError>> + Send + 'static
+    {
+        let prover = self.clone();
+        let activity_data = activity_data.clone();
+        async move {
+            tokio::task::spawn_blocking(move || prover.prove_activity(&activity_data))
+                .await
+                .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+        }
+    }
+}
+
+// 多数の独立した行動の証明を並列に生成する（証明キーは1つを全タスクで共有する）
+#[cfg(feature = "parallel")]
+impl<E: Pairing> ActivityProver<E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 行動ごとの証明を入力と同じ順序で返す（乱数の元はOsRng）
+    pub fn generate_proofs_parallel(&self, activities: &[ActivityData]) -> Vec<Result<ark_groth16::Proof<E>, ZkError>> {
+        self.generate_proofs_parallel_with_rng(activities, &mut OsRng)
+    }
+
+    // 呼び出し側のRNGから行動ごとにシードを取り、タスクごとに別のChaCha20Rngで証明する
+    // シードは並列化する前に順番に取るので、どのタスクも乱数を共有せず、同じRNGなら結果も再現できる
+    pub fn generate_proofs_parallel_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        activities: &[ActivityData],
+        rng: &mut R,
+    ) -> Vec<Result<ark_groth16::Proof<E>, ZkError>> {
+        use rayon::prelude::*;
+
+        let seeds: Vec<[u8; 32]> = activities
+            .iter()
+            .map(|_| {
+                let mut seed = [0u8; 32];
+                rng.fill_bytes(&mut seed);
+                seed
+            })
+            .collect();
+        activities
+            .par_iter()
+            .zip(seeds)
+            .map(|(activity_data, seed)| self.generate_proof(activity_data, &mut ChaCha20Rng::from_seed(seed)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::BigInteger;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{
+        derive_nullifier, hash_activity, salted_activity_hash, serialize_proof, ConstraintLabel, PedersenCommitment,
+        Setup, VerificationReport,
+    };
+
+    #[test]
+    fn test_secret_shifted_by_group_order_cannot_be_proven() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let order = Fr::from_le_bytes_mod_order(&ark_ed_on_bn254::Fr::MODULUS.to_bytes_le());
+        let now = Utc::now();
+        let activity = ActivityData::new(now, hash_activity("shifted"), [5u8; 32], [6u8; 32]);
+        let (circuit, public_activity) = prover.circuit(&activity, now - Duration::days(1), Fr::zero(), None).unwrap();
+
+        // 回路の外では、ℓだけずらした秘密値も同じコミットメントを開けるが、nullifierは別の値になる
+        let shifted = circuit.user_secret + order;
+        let shifted_nullifier = derive_nullifier(shifted, circuit.activity_hash);
+        assert_eq!(PedersenCommitment::commit(shifted, circuit.salt), circuit.commitment);
+        assert_ne!(shifted_nullifier, circuit.nullifier);
+        assert!(!PedersenCommitment::in_range(shifted));
+        assert!(PedersenCommitment::in_range(order - Fr::from(1u64)));
+
+        // ずらした秘密値で同じコミットメントに対する2つ目のnullifierを作ろうとしても、コミットメントの開示を満たさない
+        let mut forged = circuit.clone();
+        forged.user_secret = shifted;
+        forged.nullifier = shifted_nullifier;
+        let mut forged_public = public_activity.clone();
+        forged_public.nullifier = shifted_nullifier;
+        assert_eq!(forged.unsatisfied_constraints().unwrap(), vec![ConstraintLabel::CommitmentOpen]);
+        assert!(matches!(prover.prove_circuit(forged, forged_public.clone(), rng), Err(ZkError::Unsatisfied)));
+
+        // 正規の証明も、ずらした秘密値のnullifierに対しては検証を通らない
+        let (_, proof) = prover.prove_circuit(circuit, public_activity, rng).unwrap();
+        assert!(!verifier.verify_proof(&proof, &forged_public.public_inputs().unwrap().to_array()).unwrap());
+
+        // ℓ以上になる秘密値やソルトのバイト列は、回路を作る時点で拒否する
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&shifted.into_bigint().to_bytes_be());
+        let shifted_activity = ActivityData::new(now, hash_activity("shifted"), bytes, [6u8; 32]);
+        assert!(matches!(prover.check_satisfied(&shifted_activity), Err(ZkError::SecretOutOfRange)));
+        let large_salt = ActivityData::new(now, hash_activity("shifted"), [5u8; 32], [0xffu8; 32]);
+        assert!(matches!(prover.check_satisfied(&large_salt), Err(ZkError::SecretOutOfRange)));
+    }
+
+    #[test]
+    fn test_os_rng_proof_verifies() {
+        // デフォルトのOsRngによる証明生成
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("os_rng_activity"),
+            user_secret: [5u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        let (public_activity, proof) = prover.prove_activity(&activity).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+    }
+
+    #[test]
+    fn test_proofs_use_fresh_randomness() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("fresh_randomness"),
+            user_secret: [6u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // 同じ入力でも証明のたびに乱数が変わり、どちらも検証を通る
+        let (public_1, proof_1) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let (public_2, proof_2) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        assert_ne!(proof_1, proof_2);
+        assert!(verifier.verify_activity_bool(&public_1, &proof_1));
+        assert!(verifier.verify_activity_bool(&public_2, &proof_2));
+    }
+
+    #[test]
+    fn test_deterministic_proofs_are_reproducible() {
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("golden_fixture"),
+            user_secret: test_secret(17),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // 同じシードならバイト列まで一致する
+        let (public_1, proof_1) = prover.generate_proof_deterministic(&activity, [1u8; 32]).unwrap();
+        let (_, proof_2) = prover.generate_proof_deterministic(&activity, [1u8; 32]).unwrap();
+        assert_eq!(serialize_proof(&proof_1), serialize_proof(&proof_2));
+
+        // 別のシードなら別の証明になり、どちらも検証を通る
+        let (public_3, proof_3) = prover.generate_proof_deterministic(&activity, [2u8; 32]).unwrap();
+        assert_ne!(serialize_proof(&proof_1), serialize_proof(&proof_3));
+        assert!(verifier.verify_activity_bool(&public_1, &proof_1));
+        assert!(verifier.verify_activity_bool(&public_3, &proof_3));
+    }
+
+    #[test]
+    fn test_in_circuit_freshness_bound() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let prover = prover.with_freshness_window(Duration::days(7));
+
+        // 下限の期間内の行動は証明・検証できる
+        let in_window = ActivityData {
+            timestamp: Utc::now() - Duration::days(6),
+            activity_hash: hash_activity("in_window"),
+            user_secret: test_secret(11),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&in_window, rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        // 公開入力の下限を改ざんすると検証に失敗する
+        let mut forged = public_activity.clone();
+        forged.cutoff = public_activity.cutoff + Duration::days(1);
+        assert!(matches!(
+            verifier.verify_activity(&forged, &proof).and_then(VerificationReport::into_result),
+            Err(ZkError::VerificationFailed)
+        ));
+
+        // 下限より古い行動は回路を満たさない
+        let out_of_window = ActivityData {
+            timestamp: Utc::now() - Duration::days(8),
+            activity_hash: hash_activity("out_of_window"),
+            user_secret: test_secret(11),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        assert!(matches!(
+            prover.prove_activity_with_rng(&out_of_window, rng),
+            Err(ZkError::Unsatisfied)
+        ));
+
+        let circuit = ActivityCircuit::new(
+            out_of_window.timestamp,
+            out_of_window.activity_hash,
+            out_of_window.user_secret,
+            out_of_window.salt,
+            Utc::now() - Duration::days(7),
+        )
+        .unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_refreshed_proof_verifies_against_new_timestamp() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now();
+        let activity = ActivityData::new(now - Duration::days(3), hash_activity("refresh"), test_secret(18), [0u8; 32]);
+        let (old_public, _) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 1回目は行列を保存し、2回目以降は保存した行列を使う。どちらも新しい時刻の公開入力で検証を通る
+        for new_timestamp in [now - Duration::days(1), now] {
+            let (public_activity, proof) = prover.refresh_timestamp(&activity, new_timestamp, rng).unwrap();
+            assert_eq!(public_activity.timestamp, new_timestamp);
+            assert_eq!(public_activity.nullifier, old_public.nullifier);
+            assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+            // 古い時刻の公開入力に対しては検証に失敗する
+            let stale = PublicActivity {
+                timestamp: old_public.timestamp,
+                ..public_activity
+            };
+            assert!(!verifier.verify_activity(&stale, &proof).unwrap().proof_valid);
+        }
+        assert!(prover.matrices.get().is_some());
+
+        // 期間外の時刻には更新できない
+        assert!(matches!(
+            prover.refresh_timestamp(&activity, now - Duration::days(31), rng),
+            Err(ZkError::Unsatisfied)
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_async_proofs_verify() {
+        let (prover, verifier) = shared_test_setup();
+        let activities: Vec<_> = (0..3u8)
+            .map(|i| {
+                ActivityData::new(Utc::now(), hash_activity(&format!("async_{}", i)), test_secret(40 + i), [i; 32])
+            })
+            .collect();
+
+        // 複数の証明を同時に生成しても、それぞれ検証を通る
+        let started = Utc::now().trunc_subsecs(0);
+        let (first, second, third) = tokio::join!(
+            prover.prove_activity_async(&activities[0]),
+            prover.prove_activity_async(&activities[1]),
+            prover.generate_proof_async(&activities[2]),
+        );
+        let finished = Utc::now();
+        for (public_activity, proof) in [first.unwrap(), second.unwrap()] {
+            assert!(verifier.verify_activity_bool(&public_activity, &proof));
+        }
+
+        // 公開部分を返さない版は、生成中のいずれかの秒を下限とした公開入力で検証を通る
+        let proof = third.unwrap();
+        let seconds = (finished - started).num_seconds() + 1;
+        assert!((0..=seconds).any(|offset| {
+            let cutoff = started + Duration::seconds(offset) - DEFAULT_FRESHNESS_WINDOW;
+            let public_inputs = activities[2].public::<Fr>(cutoff).public_inputs().unwrap().to_vec();
+            verifier.verify_proof(&proof, &public_inputs).unwrap()
+        }));
+
+        // 生成中のFutureは証明キーを複製せずに参照し、終われば手放す
+        let prover = ActivityProver::from_proving_key(prover.proving_key().clone());
+        let proving = prover.prove_activity_async(&activities[0]);
+        assert_eq!(Arc::strong_count(&prover.proving_key), 2);
+        proving.await.unwrap();
+        assert_eq!(Arc::strong_count(&prover.proving_key), 1);
+    }
+
+    #[test]
+    fn test_check_satisfied_reports_failing_constraint() {
+        let (prover, _) = shared_test_setup();
+        let prover = prover.with_freshness_window(Duration::days(7));
+
+        // 期間内の行動は証明を作らずに満たすと分かる
+        let now = Utc::now();
+        let in_window =
+            ActivityData::new(now - Duration::days(6), hash_activity("dry_run"), test_secret(28), [0u8; 32]);
+        assert!(prover.check_satisfied(&in_window).is_ok());
+
+        // 下限より古い行動は、満たされない制約の番号が返る（コミットメントなど前半の制約は満たしている）
+        let stale = ActivityData::new(now - Duration::days(8), hash_activity("dry_run"), test_secret(28), [0u8; 32]);
+        let Err(ZkError::UnsatisfiedConstraint(index)) = prover.check_satisfied(&stale) else {
+            panic!("Stale activity must not satisfy the circuit");
+        };
+        assert!(index > 0);
+
+        // 回路を作る前に分かる不正な入力は、そのままのエラーになる
+        let zero_secret = ActivityData::new(now, hash_activity("dry_run"), [0u8; 32], [0u8; 32]);
+        assert!(matches!(prover.check_satisfied(&zero_secret), Err(ZkError::InvalidCommitment)));
+    }
+
+    #[test]
+    fn test_salted_hashes_make_repeated_proofs_unlinkable() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = Setup::<Bn254>::generate_with_salted_hash(rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("salted"), test_secret(30), [0u8; 32]);
+
+        // 同じ行動でもソルトが違えば公開される値は異なり、どちらも検証を通る
+        let (public_a, proof_a) = prover.prove_activity_with_rng(&activity.clone().with_salt([1u8; 32]), rng).unwrap();
+        let (public_b, proof_b) = prover.prove_activity_with_rng(&activity.clone().with_salt([2u8; 32]), rng).unwrap();
+        assert_ne!(public_a.activity_hash, public_b.activity_hash);
+        assert_ne!(public_a.activity_hash, activity.activity_hash);
+        assert!(verifier.verify_activity_bool(&public_a, &proof_a));
+        assert!(verifier.verify_activity_bool(&public_b, &proof_b));
+
+        // ソルトを知っていれば、公開された値がどの行動のものかを確かめられる
+        assert_eq!(public_a.activity_hash, salted_activity_hash::<Fr>(&activity.activity_hash, &[1u8; 32]));
+        let mut other = public_a.clone();
+        other.activity_hash = salted_activity_hash::<Fr>(&hash_activity("other"), &[1u8; 32]);
+        assert!(!verifier.verify_activity_bool(&other, &proof_a));
+
+        // ソルトの有無が鍵と一致しなければ証明しない
+        assert!(matches!(prover.prove_activity_with_rng(&activity, rng), Err(ZkError::SaltedHashMismatch)));
+        let (unsalted_prover, _) = shared_test_setup();
+        assert!(matches!(
+            unsalted_prover.prove_activity_with_rng(&activity.with_salt([1u8; 32]), rng),
+            Err(ZkError::SaltedHashMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_proof_past_deadline_times_out() {
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("deadline"), test_secret(26), [0u8; 32]);
+
+        // 期限がすでに過ぎていれば、証明の完了を待たずにタイムアウトする
+        let result = prover.generate_proof_with_deadline(&activity, Instant::now());
+        assert!(matches!(result, Err(ZkError::Timeout)));
+
+        // 十分な期限があれば通常どおり証明できる
+        let deadline = Instant::now() + std::time::Duration::from_secs(600);
+        let (public_activity, proof) = prover.prove_activity_with_deadline(&activity, deadline).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        // スレッドに渡す複製は証明キーを複製せずに共有する
+        assert!(Arc::ptr_eq(&prover.clone().proving_key, &prover.proving_key));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_proofs_all_verify() {
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now();
+
+        // 同じ内容の行動も含め、すべての証明が検証を通る
+        let mut activities: Vec<ActivityData> = (0..6)
+            .map(|i| {
+                ActivityData::new(now, hash_activity(&format!("parallel_{}", i)), test_secret(20 + i as u8), [0u8; 32])
+            })
+            .collect();
+        activities.push(activities[0].clone());
+        let earliest = (Utc::now() - prover.freshness_window).trunc_subsecs(0);
+        let proofs = prover.generate_proofs_parallel_with_rng(&activities, &mut seeded_rng());
+        let latest = (Utc::now() - prover.freshness_window).trunc_subsecs(0);
+        assert_eq!(proofs.len(), activities.len());
+
+        // 返るのは証明だけなので、証明した時点の下限（呼び出しの前後の間のどこかの秒）で公開入力を作って検証する
+        for (activity, proof) in activities.iter().zip(&proofs) {
+            let proof = proof.as_ref().unwrap();
+            let verifies_at = |cutoff| {
+                let public_activity = activity.public::<Fr>(cutoff);
+                verifier.verify_proof(proof, &public_activity.public_inputs().unwrap().to_vec()).unwrap()
+            };
+            let seconds = (latest - earliest).num_seconds();
+            assert!((0..=seconds).any(|offset| verifies_at(earliest + Duration::seconds(offset))));
+        }
+
+        // タスクごとに別の乱数を使うので、同じ入力でも証明は異なる
+        assert_ne!(serialize_proof(proofs[0].as_ref().unwrap()), serialize_proof(proofs[6].as_ref().unwrap()));
+
+        // 不正な入力はその行動だけがエラーになる
+        activities[1].user_secret = [0u8; 32];
+        let results = prover.generate_proofs_parallel(&activities);
+        assert!(matches!(results[1], Err(ZkError::InvalidCommitment)));
+        assert!(results.iter().enumerate().all(|(i, result)| i == 1 || result.is_ok()));
+    }
+}
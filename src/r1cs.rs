@@ -0,0 +1,139 @@
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use alloc::vec::Vec;
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, SynthesisMode};
+
+use crate::circuit::ActivityCircuit;
+use crate::pedersen::PedersenField;
+
+// circom/snarkjsの.r1cs形式（iden3のバイナリ形式のバージョン1）
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const R1CS_VERSION: u32 = 1;
+
+// セクションの種類
+const HEADER_SECTION: u32 = 1;
+const CONSTRAINTS_SECTION: u32 = 2;
+const WIRE_TO_LABEL_SECTION: u32 = 3;
+
+impl<F: PedersenField> ActivityCircuit<F> {
+    // 鍵を生成せずに回路の制約を.r1cs形式のバイト列にする（他のバックエンドでの検査や証明の生成用）
+    // ワイヤの番号はarkworksの変数の並びと同じで、0番が定数1、続いて公開入力（PublicInputLayoutの順）、残りが秘密入力。
+    // ヘッダーでは公開出力を0個、公開入力をPublicInputLayoutの数、秘密入力をそれ以外のすべての変数として数える
+    pub fn r1cs_bytes() -> Vec<u8> {
+        let cs = ConstraintSystem::<F>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        Self::placeholder()
+            .generate_constraints(cs.clone())
+            .expect("The placeholder circuit always synthesizes");
+        cs.finalize();
+        let matrices = cs.to_matrices().expect("Matrices are constructed in setup mode");
+        r1cs_from_matrices(&matrices)
+    }
+
+    // 回路の制約を.r1cs形式でファイルに書き出す
+    #[cfg(feature = "std")]
+    pub fn export_r1cs(path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, Self::r1cs_bytes())
+    }
+}
+
+// 制約の行列を.r1cs形式にする（整数はすべてリトルエンディアン、係数はモンゴメリ形式ではない通常の値）
+fn r1cs_from_matrices<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> Vec<u8> {
+    let prime = F::MODULUS.to_bytes_le();
+    let num_wires = matrices.num_instance_variables + matrices.num_witness_variables;
+
+    let mut header = Vec::new();
+    push_u32(&mut header, prime.len());
+    header.extend_from_slice(&prime);
+    push_u32(&mut header, num_wires);
+    // 公開出力, 公開入力（定数1を除く）, 秘密入力
+    push_u32(&mut header, 0);
+    push_u32(&mut header, matrices.num_instance_variables - 1);
+    push_u32(&mut header, matrices.num_witness_variables);
+    header.extend_from_slice(&(num_wires as u64).to_le_bytes());
+    push_u32(&mut header, matrices.num_constraints);
+
+    // 制約ごとにA, B, Cの線形結合を(ワイヤの番号, 係数)の列として並べる
+    let mut constraints = Vec::new();
+    for i in 0..matrices.num_constraints {
+        for row in [&matrices.a[i], &matrices.b[i], &matrices.c[i]] {
+            push_u32(&mut constraints, row.len());
+            for (coeff, wire) in row {
+                push_u32(&mut constraints, *wire);
+                constraints.extend_from_slice(&coeff.into_bigint().to_bytes_le());
+            }
+        }
+    }
+
+    // 元の回路の信号の名前はないので、ワイヤの番号をそのままラベルにする
+    let labels = (0..num_wires as u64).flat_map(u64::to_le_bytes).collect::<Vec<_>>();
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(R1CS_MAGIC);
+    bytes.extend_from_slice(&R1CS_VERSION.to_le_bytes());
+    push_u32(&mut bytes, 3);
+    for (kind, section) in [
+        (HEADER_SECTION, header),
+        (CONSTRAINTS_SECTION, constraints),
+        (WIRE_TO_LABEL_SECTION, labels),
+    ] {
+        bytes.extend_from_slice(&kind.to_le_bytes());
+        bytes.extend_from_slice(&(section.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&section);
+    }
+    bytes
+}
+
+// 個数や番号を4バイトで書き込む（この回路の大きさはu32に収まる）
+fn push_u32(bytes: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("Circuit sizes fit in 32 bits");
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    use crate::test_utils::temp_dir;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn test_exported_r1cs_header_matches_metrics() {
+        let dir = temp_dir("export_r1cs");
+        let path = dir.join("activity.r1cs");
+        ActivityCircuit::<Fr>::export_r1cs(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(&bytes[..4], R1CS_MAGIC);
+        assert_eq!(read_u32(&bytes, 4), 1);
+        assert_eq!(read_u32(&bytes, 8), 3);
+
+        // 最初のセクションがヘッダー（種類4バイト, 大きさ8バイトの後に本体）
+        assert_eq!(read_u32(&bytes, 12) as u32, HEADER_SECTION);
+        let header = &bytes[24..];
+        let field_size = read_u32(header, 0);
+        assert_eq!(field_size, 32);
+        assert_eq!(&header[4..36], Fr::MODULUS.to_bytes_le().as_slice());
+
+        // ワイヤの数, 公開出力, 公開入力, 秘密入力, ラベルの数（8バイト）, 制約の数
+        let metrics = ActivityCircuit::<Fr>::metrics();
+        let counts = 4 + field_size;
+        assert_eq!(read_u32(header, counts), 1 + metrics.public_inputs + metrics.witness_variables);
+        assert_eq!(read_u32(header, counts + 4), 0);
+        assert_eq!(read_u32(header, counts + 8), metrics.public_inputs);
+        assert_eq!(read_u32(header, counts + 12), metrics.witness_variables);
+        assert_eq!(read_u32(header, counts + 24), metrics.constraints);
+    }
+}
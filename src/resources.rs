@@ -0,0 +1,149 @@
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+use ark_ec::pairing::Pairing;
+use ark_ff::{Field, PrimeField};
+use ark_groth16::Groth16;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::CanonicalSerialize;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::circuit::cached_per_type;
+use crate::pedersen::PedersenField;
+use crate::prover::ActivityProver;
+
+// 較正に使う回路の制約の数（公開入力の定数1と合わせて評価領域がちょうど2のべき乗になる）
+const CALIBRATION_CONSTRAINTS: usize = (1 << 10) - 1;
+
+// 証明に必要な時間とメモリの見積もり（マシンの大きさを決めるための目安で、精度は数倍程度）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceEstimate {
+    // 見積もりの元にした回路の大きさ（評価領域の大きさは制約と公開入力の数を2のべき乗に切り上げたもの）
+    pub domain_size: usize,
+    pub variables: usize,
+    // 1回の証明にかかる時間（較正した評価領域の1要素あたりの時間から外挿）
+    pub proving_time: Duration,
+    // セットアップと証明で同時に持つメモリの大まかな最大値（バイト）
+    pub setup_memory_bytes: usize,
+    pub proving_memory_bytes: usize,
+}
+
+impl<E: Pairing> ActivityProver<E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 証明キーの大きさから、このマシンで証明にかかる時間とメモリを見積もる
+    // 時間は曲線ごとに最初の呼び出しで小さな回路を実際に証明して較正し、以降はその値を使い回す
+    pub fn estimate_resources(&self) -> ResourceEstimate {
+        let pk = &self.proving_key;
+        // h_queryは評価領域の大きさより1つ少ない
+        let domain_size = pk.h_query.len() + 1;
+        let variables = pk.a_query.len();
+        let field_size = E::ScalarField::ONE.uncompressed_size();
+        let key_size = pk.uncompressed_size();
+
+        // セットアップはτでの評価（変数ごとにA, B, C）と、商の多項式の係数を鍵と同時に持つ
+        let setup_memory_bytes = key_size + (3 * variables + domain_size) * field_size;
+        // 証明は鍵と割り当てに加えて、評価領域の大きさのA, B, Cと商の多項式を持つ
+        let proving_memory_bytes = key_size + (variables + 4 * domain_size) * field_size;
+
+        let per_element = cached_per_type(Calibration::<E>::measure).per_element;
+        ResourceEstimate {
+            domain_size,
+            variables,
+            proving_time: per_element * domain_size as u32,
+            setup_memory_bytes,
+            proving_memory_bytes,
+        }
+    }
+}
+
+// 曲線ごとの較正の結果（複数のスレッドが同時に較正した場合はどちらかの値を使う）
+struct Calibration<E> {
+    per_element: Duration,
+    _curve: PhantomData<E>,
+}
+
+impl<E: Pairing> Calibration<E> {
+    // 評価領域が2^10の回路を3回証明し、最も速かった回の時間を評価領域の1要素あたりに直す
+    fn measure() -> Self {
+        let rng = &mut ChaCha20Rng::seed_from_u64(0);
+        let pk = Groth16::<E>::generate_random_parameters_with_reduction(SquaringChain(E::ScalarField::ONE), rng)
+            .expect("The calibration circuit synthesizes");
+        let fastest = (0..3)
+            .map(|_| {
+                let started = Instant::now();
+                Groth16::<E>::create_random_proof_with_reduction(SquaringChain(E::ScalarField::from(3u64)), &pk, rng)
+                    .expect("The calibration circuit is satisfied");
+                started.elapsed()
+            })
+            .min()
+            .expect("At least one calibration run");
+        Self {
+            per_element: fastest / (CALIBRATION_CONSTRAINTS as u32 + 1),
+            _curve: PhantomData,
+        }
+    }
+}
+
+// 較正用の回路（x_{i+1} = x_i^2 を制約の数だけ繰り返す）
+struct SquaringChain<F>(F);
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for SquaringChain<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let mut value = self.0;
+        let mut var = FpVar::new_witness(cs.clone(), || Ok(value))?;
+        for _ in 0..CALIBRATION_CONSTRAINTS {
+            value.square_in_place();
+            let next = FpVar::new_witness(cs.clone(), || Ok(value))?;
+            (&var * &var).enforce_equal(&next)?;
+            var = next;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityCircuit, ActivityData};
+
+    #[test]
+    fn test_estimate_matches_the_circuit_size() {
+        let (prover, _) = shared_test_setup();
+        let estimate = prover.estimate_resources();
+
+        // 回路の大きさは既定の回路の制約と公開入力の数から決まる
+        let metrics = ActivityCircuit::<ark_bn254::Fr>::metrics();
+        assert_eq!(estimate.domain_size, (metrics.constraints + metrics.public_inputs + 1).next_power_of_two());
+        assert_eq!(estimate.variables, 1 + metrics.public_inputs + metrics.witness_variables);
+        assert!(estimate.proving_memory_bytes > prover.proving_key().uncompressed_size());
+    }
+
+    // 実際の時間を測るので、ほかのテストと並行して動かすと較正と証明の負荷が揃わない
+    // CIでは cargo test --lib resources -- --ignored --test-threads=1 として単独で実行する
+    #[test]
+    #[ignore]
+    fn test_estimate_is_within_an_order_of_magnitude() {
+        let rng = &mut seeded_rng();
+        let (prover, _) = shared_test_setup();
+        let estimate = prover.estimate_resources();
+
+        // 実際の証明の時間と1桁以上離れない
+        let activity = ActivityData::new(Utc::now(), hash_activity("estimate"), test_secret(44), [0u8; 32]);
+        let started = Instant::now();
+        prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let measured = started.elapsed();
+        assert!(
+            measured > estimate.proving_time / 10 && measured < estimate.proving_time * 10,
+            "estimated {:?}, measured {:?}",
+            estimate.proving_time,
+            measured
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, fields::FieldVar, uint64::UInt64};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::activity::{unix_seconds, ActivityData};
+use crate::circuit::enforce_u64_geq;
+use crate::error::ZkError;
+
+// 公開入力の数（lengthとfinal_timestamp）
+pub const NUM_SEQUENCE_PUBLIC_INPUTS: usize = 2;
+
+// N件の行動が時刻の順に起きたこと（ts[i] < ts[i+1]）を1つの証明で示す回路
+// 個々の時刻は秘密入力のままで、公開するのは件数と最後の行動の時刻だけ
+#[derive(Clone)]
+pub struct SequenceCircuit<const N: usize, F: PrimeField = Fr> {
+    // 公開入力
+    pub final_timestamp: u64,
+
+    // 秘密入力
+    pub timestamps: [u64; N],
+    _field: core::marker::PhantomData<F>,
+}
+
+impl<const N: usize, F: PrimeField> SequenceCircuit<N, F> {
+    pub fn new(activities: &[ActivityData; N]) -> Result<Self, ZkError> {
+        let mut timestamps = [0u64; N];
+        for (timestamp, activity) in timestamps.iter_mut().zip(activities) {
+            *timestamp = unix_seconds(activity.timestamp)?;
+        }
+        Ok(Self::from_timestamps(timestamps))
+    }
+
+    // unix秒の時刻の列から回路を作る（最後の時刻が公開入力になる）
+    pub fn from_timestamps(timestamps: [u64; N]) -> Self {
+        Self {
+            final_timestamp: timestamps.last().copied().unwrap_or(0),
+            timestamps,
+            _field: core::marker::PhantomData,
+        }
+    }
+
+    // セットアップ用の空の回路（回路の形はNだけで決まる）
+    pub fn blank() -> Self {
+        Self::from_timestamps([0; N])
+    }
+
+    // 時刻が狭義単調増加になっているか（制約を満たすかどうかと一致する）
+    pub fn is_increasing(&self) -> bool {
+        self.timestamps.windows(2).all(|pair| pair[0] < pair[1])
+    }
+
+    // 回路に割り当てる公開入力（件数はNそのもので、検証者は期待する件数を渡す）
+    pub fn public_inputs(&self) -> Vec<F> {
+        vec![F::from(N as u64), F::from(self.final_timestamp)]
+    }
+}
+
+impl<const N: usize, F: PrimeField> ConstraintSynthesizer<F> for SequenceCircuit<N, F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // 公開入力の割り当て（public_inputsと同じ並び）
+        let length = FpVar::new_input(cs.clone(), || Ok(F::from(N as u64)))?;
+        let final_timestamp = FpVar::new_input(cs.clone(), || Ok(F::from(self.final_timestamp)))?;
+
+        // 件数は回路の形で決まるので、公開入力がNと一致することを課す
+        length.enforce_equal(&FpVar::constant(F::from(N as u64)))?;
+
+        // 秘密入力の割り当て（時刻はUInt64として割り当て、64ビットに収まることも課す）
+        let timestamps = self
+            .timestamps
+            .iter()
+            .map(|timestamp| UInt64::new_witness(cs.clone(), || Ok(*timestamp))?.to_fp())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // ts[i+1] >= ts[i] + 1 を課す（順序が逆なら差が64ビットに収まらず満たせない）
+        for (i, pair) in timestamps.windows(2).enumerate() {
+            let (earlier, later) = (self.timestamps[i], self.timestamps[i + 1]);
+            enforce_u64_geq(cs.clone(), &pair[1], &(&pair[0] + FpVar::one()), || {
+                Ok(later.wrapping_sub(earlier).wrapping_sub(1))
+            })?;
+        }
+
+        // 最後の時刻が公開されたfinal_timestampと一致することを課す
+        match timestamps.last() {
+            Some(last) => last.enforce_equal(&final_timestamp),
+            None => final_timestamp.enforce_equal(&FpVar::zero()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use ark_groth16::{prepare_verifying_key, Groth16};
+    use ark_relations::r1cs::ConstraintSystem;
+    use chrono::{Duration, Utc};
+
+    use crate::hash_activity;
+    use crate::test_utils::seeded_rng;
+
+    // 1日おきに、古いものから順に並んだ4件の行動
+    fn onboarding_steps() -> [ActivityData; 4] {
+        let now = Utc::now();
+        core::array::from_fn(|i| {
+            let timestamp = now - Duration::days(3 - i as i64);
+            ActivityData::new(timestamp, hash_activity(&format!("onboarding_{}", i)), [1u8; 32], [0u8; 32])
+        })
+    }
+
+    fn is_satisfied(circuit: SequenceCircuit<4>) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_increasing_sequence_verifies() {
+        let rng = &mut seeded_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(SequenceCircuit::<4>::blank(), rng)
+            .unwrap();
+        let pvk = prepare_verifying_key(&pk.vk);
+
+        let activities = onboarding_steps();
+        let circuit = SequenceCircuit::<4>::new(&activities).unwrap();
+        assert!(circuit.is_increasing());
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[0], Fr::from(4u64));
+        assert_eq!(public_inputs[1], Fr::from(unix_seconds(activities[3].timestamp).unwrap()));
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, rng).unwrap();
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &public_inputs).unwrap());
+
+        // 同じ証明で別の件数や、別の最後の時刻を主張することはできない
+        let other_length = [Fr::from(3u64), public_inputs[1]];
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other_length).unwrap());
+        let other_final = [public_inputs[0], public_inputs[1] + Fr::from(1u64)];
+        assert!(!Groth16::<Bn254>::verify_proof(&pvk, &proof, &other_final).unwrap());
+    }
+
+    #[test]
+    fn test_out_of_order_sequence_fails() {
+        // 途中の2件を入れ替えると制約を満たさない
+        let mut activities = onboarding_steps();
+        activities.swap(1, 2);
+        let circuit = SequenceCircuit::<4>::new(&activities).unwrap();
+        assert!(!circuit.is_increasing());
+        assert!(!is_satisfied(circuit));
+
+        // 同じ時刻の2件も順序が示せないので拒否する
+        let mut timestamps = SequenceCircuit::<4>::new(&onboarding_steps()).unwrap().timestamps;
+        timestamps[2] = timestamps[1];
+        assert!(!is_satisfied(SequenceCircuit::from_timestamps(timestamps)));
+        timestamps[2] = timestamps[1] + 1;
+        assert!(is_satisfied(SequenceCircuit::from_timestamps(timestamps)));
+    }
+}
@@ -0,0 +1,268 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+#[cfg(feature = "std")]
+use ark_groth16::ProvingKey;
+use ark_groth16::VerifyingKey;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::error::ZkError;
+
+// 証明をバイト列に変換（送信用。点を圧縮する形式）
+pub fn serialize_proof<E: Pairing>(proof: &ark_groth16::Proof<E>) -> Vec<u8> {
+    serialize_proof_with_mode(proof, Compress::Yes)
+}
+
+// 点の形式を指定して証明をバイト列に変換する
+// 圧縮しない形式は2倍の大きさになる代わりに、読み込み時の点の展開（平方根の計算）が要らない
+pub fn serialize_proof_with_mode<E: Pairing>(proof: &ark_groth16::Proof<E>, compress: Compress) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(proof.serialized_size(compress));
+    // Vecへの書き込みは失敗しない
+    proof
+        .serialize_with_mode(&mut bytes, compress)
+        .expect("Serializing into a Vec cannot fail");
+    bytes
+}
+
+// バイト列から証明を復元（曲線上の点であることも検証する）
+// 証明は固定長の3点だけで長さの情報を含まないので、arkworksの実装でそのまま読んでよい
+// 無限遠点のフラグが立った点は座標のバイトを無視して読まれるので、同じ証明に複数の表現ができてしまう。
+// 証明のバイト列を識別子として使えるよう、serialize_proofと同じ表現でなければエラーにする
+pub fn deserialize_proof<E: Pairing>(bytes: &[u8]) -> Result<ark_groth16::Proof<E>, ZkError> {
+    deserialize_proof_with_mode(bytes, Compress::Yes)
+}
+
+// serialize_proof_with_modeと同じ形式のバイト列から証明を復元する（形式が違えば長さが合わずエラーになる）
+// 圧縮しない形式でも、曲線上の点であることと部分群に含まれることは検証する
+pub fn deserialize_proof_with_mode<E: Pairing>(
+    bytes: &[u8],
+    compress: Compress,
+) -> Result<ark_groth16::Proof<E>, ZkError> {
+    let mut reader = bytes;
+    let proof = ark_groth16::Proof::<E>::deserialize_with_mode(&mut reader, compress, Validate::Yes)?;
+    if !reader.is_empty() {
+        return Err(ZkError::TrailingBytes(reader.len()));
+    }
+    if serialize_proof_with_mode(&proof, compress) != bytes {
+        return Err(SerializationError::InvalidData.into());
+    }
+    Ok(proof)
+}
+
+// 証明のバイト列がどちらの形式かを長さから判定する（証明は曲線ごとに形式ごとの固定長で、2つの長さは必ず異なる）
+pub fn proof_serialization_mode<E: Pairing>(bytes: &[u8]) -> Option<Compress> {
+    let proof = ark_groth16::Proof::<E>::default();
+    [Compress::Yes, Compress::No]
+        .into_iter()
+        .find(|compress| proof.serialized_size(*compress) == bytes.len())
+}
+
+// QRコードで提示する用の、証明の短い文字列（serialize_proofと同じ点の圧縮形式をbase64url、パディングなしで表す）
+// 点を圧縮しない形式の半分の大きさで、長さはcompact_proof_lenで確かめられる
+pub fn proof_to_compact_string<E: Pairing>(proof: &ark_groth16::Proof<E>) -> String {
+    URL_SAFE_NO_PAD.encode(serialize_proof(proof))
+}
+
+// 曲線ごとに固定の、短い文字列の長さ（バイト数）。BN254の証明は128バイトで171文字、BLS12-381は192バイトで256文字。
+// QRコードのバイトモードの誤り訂正Lなら、BN254はバージョン9（230バイト）、BLS12-381はバージョン10（271バイト）に収まる
+pub fn compact_proof_len<E: Pairing>() -> usize {
+    base64::encoded_len(ark_groth16::Proof::<E>::default().compressed_size(), false)
+        .expect("A proof of three points cannot overflow usize")
+}
+
+// proof_to_compact_stringの文字列から証明を復元（deserialize_proofと同じく正規の表現だけを受け付ける）
+pub fn proof_from_compact_string<E: Pairing>(compact: &str) -> Result<ark_groth16::Proof<E>, ZkError> {
+    let bytes = URL_SAFE_NO_PAD.decode(compact).map_err(|_| ZkError::InvalidBase64)?;
+    deserialize_proof(&bytes)
+}
+
+// 鍵をバイト列全体から読み込む（後ろに余分なバイトがあればエラー）
+// arkworksのVecのデシリアライズは、読み込んだ長さのままVec::with_capacityで領域を確保する。
+// 攻撃者が長さを書き換えると巨大な確保になり、容量の溢れによるパニックか、確保の失敗によるプロセスの異常終了が起きる
+// （Errとして捕まえられない）。鍵は点の列を含むので、arkworksの実装ではなく長さを確かめながら読む下記の関数を使う
+pub(crate) fn read_key_whole<T>(
+    mut bytes: &[u8],
+    compress: Compress,
+    read: impl FnOnce(&mut &[u8], Compress) -> Result<T, SerializationError>,
+) -> Result<T, SerializationError> {
+    let value = read(&mut bytes, compress)?;
+    if !bytes.is_empty() {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok(value)
+}
+
+// 検証キーを読む（arkworksのVerifyingKeyと同じ並び）
+pub(crate) fn read_verifying_key<E: Pairing>(
+    reader: &mut &[u8],
+    compress: Compress,
+) -> Result<VerifyingKey<E>, SerializationError> {
+    Ok(VerifyingKey {
+        alpha_g1: read_point(reader, compress)?,
+        beta_g2: read_point(reader, compress)?,
+        gamma_g2: read_point(reader, compress)?,
+        delta_g2: read_point(reader, compress)?,
+        gamma_abc_g1: read_points(reader, compress)?,
+    })
+}
+
+// 証明キーを読む（arkworksのProvingKeyと同じ並び。保存した鍵の読み込みでだけ使う）
+#[cfg(feature = "std")]
+pub(crate) fn read_proving_key<E: Pairing>(
+    reader: &mut &[u8],
+    compress: Compress,
+) -> Result<ProvingKey<E>, SerializationError> {
+    Ok(ProvingKey {
+        vk: read_verifying_key(reader, compress)?,
+        beta_g1: read_point(reader, compress)?,
+        delta_g1: read_point(reader, compress)?,
+        a_query: read_points(reader, compress)?,
+        b_g1_query: read_points(reader, compress)?,
+        b_g2_query: read_points(reader, compress)?,
+        h_query: read_points(reader, compress)?,
+        l_query: read_points(reader, compress)?,
+    })
+}
+
+// 曲線上・部分群内にあることを確かめて点を1つ読む
+fn read_point<A: AffineRepr>(reader: &mut &[u8], compress: Compress) -> Result<A, SerializationError> {
+    A::deserialize_with_mode(reader, compress, Validate::Yes)
+}
+
+// 長さ付きの点の列を読む（点の大きさは固定なので、残りのバイト数に収まらない長さは確保する前に拒否する）
+fn read_points<A: AffineRepr>(reader: &mut &[u8], compress: Compress) -> Result<Vec<A>, SerializationError> {
+    let len = u64::deserialize_with_mode(&mut *reader, compress, Validate::No)?;
+    let point_size = A::default().serialized_size(compress);
+    if len > (reader.len() / point_size) as u64 {
+        return Err(SerializationError::InvalidData);
+    }
+    (0..len).map(|_| read_point(reader, compress)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+    use chrono::Utc;
+
+    use ark_bls12_381::Bls12_381;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData};
+
+    #[test]
+    fn test_proof_serialization_round_trip() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("serialized_activity"),
+            user_secret: test_secret(7),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // シリアライズ → デシリアライズ後も同じ公開入力で検証できる
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let bytes = serialize_proof(&proof);
+        let restored = deserialize_proof::<Bn254>(&bytes).unwrap();
+
+        assert_eq!(restored, proof);
+        assert!(verifier.verify_activity_bool(&public_activity, &restored));
+    }
+
+    #[test]
+    fn test_malformed_proof_bytes_return_error() {
+        let rng = &mut seeded_rng();
+        let (prover, _) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("malformed_activity"),
+            user_secret: test_secret(7),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let proof = prover.generate_proof(&activity, rng).unwrap();
+        let bytes = serialize_proof(&proof);
+
+        // 途中で切れたバイト列
+        assert!(deserialize_proof::<Bn254>(&bytes[..bytes.len() - 1]).is_err());
+        assert!(deserialize_proof::<Bn254>(&[]).is_err());
+
+        // 破損したバイト列
+        let corrupted = vec![0xffu8; bytes.len()];
+        assert!(deserialize_proof::<Bn254>(&corrupted).is_err());
+
+        // 余分なバイトが付いたバイト列
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(matches!(deserialize_proof::<Bn254>(&extended), Err(ZkError::TrailingBytes(1))));
+
+        // Aに無限遠点のフラグを立てた、正規でない表現
+        let mut infinity = bytes.clone();
+        infinity[31] |= 0x40;
+        assert!(matches!(deserialize_proof::<Bn254>(&infinity), Err(ZkError::Serialization(_))));
+    }
+
+    #[test]
+    fn test_proof_round_trips_in_both_modes() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("modes"), test_secret(46), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 圧縮しない形式はちょうど2倍の大きさで、どちらの形式でも同じ証明に戻る
+        let compressed = serialize_proof_with_mode(&proof, Compress::Yes);
+        let uncompressed = serialize_proof_with_mode(&proof, Compress::No);
+        assert_eq!(compressed, serialize_proof(&proof));
+        assert_eq!((compressed.len(), uncompressed.len()), (128, 256));
+        for (bytes, compress) in [(&compressed, Compress::Yes), (&uncompressed, Compress::No)] {
+            assert!(proof_serialization_mode::<Bn254>(bytes) == Some(compress));
+            let restored = deserialize_proof_with_mode::<Bn254>(bytes, compress).unwrap();
+            assert!(verifier.verify_activity_bool(&public_activity, &restored));
+        }
+
+        // 形式を取り違えたバイト列と、どちらの長さでもないバイト列は読めない
+        assert!(deserialize_proof::<Bn254>(&uncompressed).is_err());
+        assert!(deserialize_proof_with_mode::<Bn254>(&compressed, Compress::No).is_err());
+        assert!(proof_serialization_mode::<Bn254>(&uncompressed[1..]).is_none());
+
+        // 圧縮しない形式でも、曲線上にない点は拒否する
+        let mut off_curve = uncompressed.clone();
+        off_curve[0] ^= 1;
+        assert!(deserialize_proof_with_mode::<Bn254>(&off_curve, Compress::No).is_err());
+    }
+
+    #[test]
+    fn test_compact_string_round_trip() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("compact"), test_secret(42), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // URLにそのまま載せられる文字だけで、長さは曲線ごとに固定
+        let compact = proof_to_compact_string(&proof);
+        assert_eq!(compact.len(), compact_proof_len::<Bn254>());
+        assert!(compact.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        let restored = proof_from_compact_string::<Bn254>(&compact).unwrap();
+        assert_eq!(restored, proof);
+        assert!(verifier.verify_activity_bool(&public_activity, &restored));
+
+        // 圧縮しない形式を文字列にするよりも大幅に短い
+        let mut uncompressed = Vec::new();
+        proof.serialize_uncompressed(&mut uncompressed).unwrap();
+        let uncompressed = URL_SAFE_NO_PAD.encode(uncompressed);
+        assert_eq!((compact.len(), uncompressed.len()), (171, 342));
+        assert_eq!(compact_proof_len::<Bls12_381>(), 256);
+
+        // base64urlとして読めない文字列と、1文字欠けた文字列
+        assert!(matches!(proof_from_compact_string::<Bn254>("not base64!"), Err(ZkError::InvalidBase64)));
+        assert!(proof_from_compact_string::<Bn254>(&compact[..compact.len() - 1]).is_err());
+    }
+}
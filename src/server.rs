@@ -0,0 +1,241 @@
+// tonicのハンドラーはStatus（大きな型）を返す決まりなので、補助関数も同じ型で揃える
+#![allow(clippy::result_large_err)]
+
+use std::fmt;
+use std::sync::Arc;
+
+use ark_bn254::Fr;
+use chrono::{DateTime, Utc};
+use tonic::{Request, Response, Status};
+
+use crate::activity::{field_to_bytes_be, hash_activity, ActivityData, PublicActivity};
+use crate::circuit::NO_EXPIRY;
+use crate::error::ZkError;
+use crate::prover::Bn254Prover;
+use crate::serialization::{deserialize_proof, serialize_proof};
+use crate::verifier::{Bn254Verifier, VerificationReport};
+use crate::wire::{bytes_to_field, datetime_from_unix};
+
+// build.rsで生成したサービスとクライアント（zk_sample.Activity）
+include!(concat!(env!("OUT_DIR"), "/zk_sample.Activity.rs"));
+
+pub use activity_client::ActivityClient;
+pub use activity_server::{Activity, ActivityServer};
+
+// 現在時刻の行動として証明する要求（秘密値とソルトは32バイト）
+// 秘密値をログに出さないよう、Debugでは行動名以外を伏せる
+#[derive(Clone, PartialEq, prost::Message)]
+#[prost(skip_debug)]
+pub struct ProveRequest {
+    #[prost(string, tag = "1")]
+    pub activity: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub user_secret: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub salt: Vec<u8>,
+}
+
+impl fmt::Debug for ProveRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProveRequest")
+            .field("activity", &self.activity)
+            .finish_non_exhaustive()
+    }
+}
+
+// 証明（serialize_proofの形式）と検証に必要な公開入力
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProveResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub proof: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub public: Option<PublicInputs>,
+}
+
+// 検証する要求（verifying_keyを省略するとサーバーの検証キーを使う）
+// verifying_keyはサーバーが受け付ける鍵のどれを使うかの指定で、受け付けていない鍵はPermissionDeniedになる
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub proof: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub public: Option<PublicInputs>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub verifying_key: Vec<u8>,
+}
+
+// 検証結果（不正な場合はreasonに理由が入る）
+// validは、サーバーが受け付ける鍵のうち要求で指定したもの（省略時はサーバーの鍵）で証明を検証し、
+// その鍵を設定した検証者の鮮度などの検査を通ったことを表す
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyResponse {
+    #[prost(bool, tag = "1")]
+    pub valid: bool,
+    #[prost(string, tag = "2")]
+    pub reason: String,
+}
+
+// PublicActivityのメッセージ表現（時刻はUnix秒、体の元は32バイトのビッグエンディアン）
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PublicInputs {
+    #[prost(int64, tag = "1")]
+    pub timestamp: i64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub activity_hash: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub commitment: Vec<u8>,
+    #[prost(int64, tag = "4")]
+    pub cutoff: i64,
+    #[prost(bytes = "vec", tag = "5")]
+    pub nullifier: Vec<u8>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub challenge: Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub user_id: Vec<u8>,
+    // 有効期限のUnix秒（省略は期限なし。既定値の0を1970年の期限と取り違えないようoptionalにする）
+    #[prost(uint64, optional, tag = "8")]
+    pub expiry: Option<u64>,
+}
+
+impl From<&PublicActivity> for PublicInputs {
+    fn from(activity: &PublicActivity) -> Self {
+        Self {
+            timestamp: activity.timestamp.timestamp(),
+            activity_hash: activity.activity_hash.to_vec(),
+            commitment: field_to_bytes_be(&activity.commitment),
+            cutoff: activity.cutoff.timestamp(),
+            nullifier: field_to_bytes_be(&activity.nullifier),
+            challenge: field_to_bytes_be(&activity.challenge),
+            user_id: field_to_bytes_be(&activity.user_id),
+            expiry: (activity.expiry != NO_EXPIRY).then_some(activity.expiry),
+        }
+    }
+}
+
+impl TryFrom<PublicInputs> for PublicActivity {
+    type Error = Status;
+
+    fn try_from(inputs: PublicInputs) -> Result<Self, Status> {
+        Ok(Self {
+            timestamp: from_unix(inputs.timestamp)?,
+            activity_hash: bytes32(&inputs.activity_hash, "activity_hash")?,
+            commitment: field(&inputs.commitment, "commitment")?,
+            cutoff: from_unix(inputs.cutoff)?,
+            nullifier: field(&inputs.nullifier, "nullifier")?,
+            challenge: field(&inputs.challenge, "challenge")?,
+            user_id: field(&inputs.user_id, "user_id")?,
+            expiry: inputs.expiry.unwrap_or(NO_EXPIRY),
+        })
+    }
+}
+
+// 証明者と検証者を保持するgRPCサービス
+// 証明キーを応答やログに出さないよう、Debugは実装しない
+pub struct ActivityService {
+    prover: Arc<Bn254Prover>,
+    verifier: Bn254Verifier,
+    // 要求のverifying_keyで選べる、サーバーの検証者以外に受け付ける検証者
+    accepted: Vec<Bn254Verifier>,
+}
+
+impl ActivityService {
+    pub fn new(prover: Bn254Prover, verifier: Bn254Verifier) -> Self {
+        Self {
+            prover: Arc::new(prover),
+            verifier,
+            accepted: Vec::new(),
+        }
+    }
+
+    // 要求で検証キーを指定したときに受け付ける検証者を追加する（鍵の回路のIDで選ぶ）
+    // 検証には追加した検証者の設定（鮮度の期間・許可リスト・エポックなど）をそのまま使う
+    pub fn with_accepted_verifier(mut self, verifier: Bn254Verifier) -> Self {
+        self.accepted.push(verifier);
+        self
+    }
+
+    // 要求で指定された検証キーに対応する、サーバーが受け付ける検証者
+    fn accepted_verifier(&self, verifying_key: &[u8]) -> Result<&Bn254Verifier, Status> {
+        let circuit_id = Bn254Verifier::from_vk_bytes(verifying_key)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?
+            .circuit_id();
+        std::iter::once(&self.verifier)
+            .chain(&self.accepted)
+            .find(|verifier| verifier.circuit_id() == circuit_id)
+            .ok_or_else(|| Status::permission_denied("Verifying key is not accepted by this server"))
+    }
+}
+
+#[tonic::async_trait]
+impl Activity for ActivityService {
+    // 証明の生成はブロッキング用のスレッドで行い、サーバーの実行器を止めない
+    async fn prove(&self, request: Request<ProveRequest>) -> Result<Response<ProveResponse>, Status> {
+        let request = request.into_inner();
+        let activity_data = ActivityData::new(
+            Utc::now(),
+            hash_activity(&request.activity),
+            bytes32(&request.user_secret, "user_secret")?,
+            bytes32(&request.salt, "salt")?,
+        );
+
+        let prover = Arc::clone(&self.prover);
+        let (public_activity, proof) = tokio::task::spawn_blocking(move || prover.prove_activity(&activity_data))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(invalid_argument)?;
+
+        Ok(Response::new(ProveResponse {
+            proof: serialize_proof(&proof),
+            public: Some(PublicInputs::from(&public_activity)),
+        }))
+    }
+
+    // 証明が正しくない・古い場合はvalid = false、要求の形式が不正ならエラー
+    async fn verify(&self, request: Request<VerifyRequest>) -> Result<Response<VerifyResponse>, Status> {
+        let request = request.into_inner();
+        let verifier = if request.verifying_key.is_empty() {
+            &self.verifier
+        } else {
+            self.accepted_verifier(&request.verifying_key)?
+        };
+        let proof = deserialize_proof(&request.proof).map_err(invalid_argument)?;
+        let activity = PublicActivity::try_from(
+            request
+                .public
+                .ok_or_else(|| Status::invalid_argument("Missing public inputs"))?,
+        )?;
+
+        let result = verifier.verify_activity(&activity, &proof).and_then(VerificationReport::into_result);
+        let (valid, reason) = match result {
+            Ok(_) => (true, String::new()),
+            Err(
+                e @ (ZkError::VerificationFailed
+                | ZkError::StaleTimestamp
+                | ZkError::FutureTimestamp
+                | ZkError::StaleCutoff
+                | ZkError::ExpiredProof),
+            ) => (false, e.to_string()),
+            Err(e) => return Err(invalid_argument(e)),
+        };
+        Ok(Response::new(VerifyResponse { valid, reason }))
+    }
+}
+
+fn invalid_argument(e: ZkError) -> Status {
+    Status::invalid_argument(e.to_string())
+}
+
+fn bytes32(bytes: &[u8], field: &str) -> Result<[u8; 32], Status> {
+    bytes
+        .try_into()
+        .map_err(|_| Status::invalid_argument(format!("Expected 32 bytes for {}, got {}", field, bytes.len())))
+}
+
+// 32バイトの体の元として読む（体の位数以上の値は簡約せずに拒否する）
+fn field(bytes: &[u8], name: &str) -> Result<Fr, Status> {
+    bytes_to_field(bytes).map_err(|e| Status::invalid_argument(format!("Invalid {}: {}", name, e)))
+}
+
+fn from_unix(seconds: i64) -> Result<DateTime<Utc>, Status> {
+    datetime_from_unix(seconds).map_err(|_| Status::invalid_argument(format!("Timestamp {} is out of range", seconds)))
+}
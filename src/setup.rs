@@ -0,0 +1,688 @@
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+#[cfg(feature = "std")]
+use ark_groth16::VerifyingKey;
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, ProvingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+#[cfg(feature = "std")]
+use ark_serialize::Compress;
+use ark_serialize::{CanonicalSerialize, SerializationError};
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::aggregate::{MultiActivityCircuit, MultiActivityProver, MultiActivityVerifier};
+use crate::circuit::{ActivityCircuit, CIRCUIT_VERSION};
+#[cfg(feature = "std")]
+use crate::circuit::NUM_PUBLIC_INPUTS;
+use crate::epoch::EpochConfig;
+use crate::error::ZkError;
+use crate::history::{HistoryCountCircuit, HistoryCountVerifier};
+use crate::merkle::Allowlist;
+use crate::pedersen::PedersenField;
+use crate::prover::ActivityProver;
+#[cfg(feature = "std")]
+use crate::serialization::{read_key_whole, read_proving_key, read_verifying_key};
+use crate::verifier::ActivityVerifier;
+
+// トラステッドセットアップ（Eはペアリング曲線、デフォルトはBN254）
+pub struct Setup<E: Pairing = Bn254>(PhantomData<E>);
+
+impl Setup {
+    // 証明キーと検証キーを一度だけ生成し、証明者と検証者に分けて渡す
+    #[cfg(feature = "std")]
+    pub fn generate() -> (ActivityProver, ActivityVerifier) {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGでセットアップを実行
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (ActivityProver, ActivityVerifier) {
+        Self::generate_for_curve(rng)
+    }
+
+    // generate_with_rngと同じだが、パラメータの生成に失敗したらパニックせずにエラーを返す
+    pub fn try_generate_with_rng<R: RngCore + CryptoRng>(
+        rng: &mut R,
+    ) -> Result<(ActivityProver, ActivityVerifier), ZkError> {
+        Self::try_generate_for_curve(rng)
+    }
+}
+
+impl<E: Pairing> Setup<E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 任意のペアリング曲線でセットアップを実行
+    pub fn generate_for_curve<R: RngCore + CryptoRng>(rng: &mut R) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        // ダミーの回路でパラメータを生成
+        Self::generate_for_circuit(ActivityCircuit::placeholder(), rng)
+    }
+
+    // generate_for_curveと同じだが、パラメータの生成に失敗したらパニックせずにエラーを返す
+    pub fn try_generate_for_curve<R: RngCore + CryptoRng>(
+        rng: &mut R,
+    ) -> Result<(ActivityProver<E>, ActivityVerifier<E>), ZkError> {
+        Self::try_generate_for_circuit(ActivityCircuit::placeholder(), rng)
+    }
+
+    // 監査用に、シードから決定的にセットアップを実行する（同じシードと回路なら常に同じ鍵になる）
+    // 監査者は公開された鍵が既知のシードと回路から生成されたことを、生成し直してkeys_equalで比べて確かめられる。
+    // シードを知っていればトキシックウェイスト（τ, α, β, γ, δ）を復元して証明を偽造できるので、本番の鍵には絶対に使わないこと
+    pub fn setup_from_seed(seed: [u8; 32]) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        Self::generate_for_curve(&mut ChaCha20Rng::from_seed(seed))
+    }
+
+    // 許可リスト付きの回路でセットアップを実行し、同じ許可リストを設定した証明者と検証者を返す
+    pub fn generate_with_allowlist<R: RngCore + CryptoRng>(
+        hashes: &[[u8; 32]],
+        rng: &mut R,
+    ) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        let circuit = ActivityCircuit::placeholder_with_allowlist(&Allowlist::new(hashes));
+        let (prover, verifier) = Self::generate_for_circuit(circuit, rng);
+        (prover.with_allowlist(hashes), verifier.with_allowlist(hashes))
+    }
+
+    // エポック付きの回路でセットアップを実行し、同じ設定の証明者と検証者を返す
+    // エポックの開始時刻と長さは回路の定数になるので、設定が不正ならエラー
+    pub fn generate_with_epoch<R: RngCore + CryptoRng>(
+        config: EpochConfig,
+        rng: &mut R,
+    ) -> Result<(ActivityProver<E>, ActivityVerifier<E>), ZkError> {
+        let circuit = ActivityCircuit::placeholder_with_epoch(config.blank_window()?);
+        let (prover, verifier) = Self::try_generate_for_circuit(circuit, rng)?;
+        Ok((prover.with_epoch(config), verifier.with_epoch(config)))
+    }
+
+    // 範囲付きの回路でセットアップを実行する（範囲は証明ごとに公開入力として決めるので、鍵は1組で済む）
+    // 証明はprove_in_range、検証はverify_in_rangeで行い、返す証明者と検証者はそれ以外を拒否する
+    pub fn generate_with_range<R: RngCore + CryptoRng>(rng: &mut R) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        let (prover, verifier) = Self::generate_for_circuit(ActivityCircuit::placeholder_with_range(), rng);
+        (prover.with_range(), verifier.with_range())
+    }
+
+    // 原像付きの回路でセットアップを実行し、同じ長さの原像を設定した証明者と検証者を返す
+    // 検証者の公開入力は変わらないので、検証者には設定がない
+    pub fn generate_with_preimage_length<R: RngCore + CryptoRng>(
+        length: usize,
+        rng: &mut R,
+    ) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        let circuit = ActivityCircuit::placeholder_with_preimage_length(length);
+        let (prover, verifier) = Self::generate_for_circuit(circuit, rng);
+        (prover.with_preimage_length(length), verifier)
+    }
+
+    // ソルト付きの行動ハッシュを使う回路でセットアップを実行する（ActivityData::with_saltの行動だけを証明できる）
+    // 公開入力の数と並びは変わらないので、検証者には設定がない
+    pub fn generate_with_salted_hash<R: RngCore + CryptoRng>(rng: &mut R) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        let (prover, verifier) = Self::generate_for_circuit(ActivityCircuit::placeholder_with_salted_hash(), rng);
+        (prover.with_salted_hash(), verifier)
+    }
+
+    // N件の行動をまとめて証明する回路でセットアップを実行する（Nは型で固定され、証明者と検証者は同じNの鍵を持つ）
+    pub fn generate_for_n_activities<const N: usize, R: RngCore + CryptoRng>(
+        rng: &mut R,
+    ) -> (MultiActivityProver<N, E>, MultiActivityVerifier<N, E>) {
+        let params = Groth16::<E>::generate_random_parameters_with_reduction(MultiActivityCircuit::<N, _>::blank(), rng)
+            .expect("The blank aggregate circuit synthesizes without witness values");
+        let verifying_key = prepare_verifying_key(&params.vk);
+        (MultiActivityProver::from_proving_key(params), MultiActivityVerifier::from_prepared_key(verifying_key))
+    }
+
+    // 深さdepthの履歴の木にK件の行動があることを証明する回路でセットアップを実行する
+    // 証明者の型はないので、証明キーはそのまま返す（HistoryCountCircuitの証明に使う）
+    pub fn generate_for_history_count<const K: usize, R: RngCore + CryptoRng>(
+        depth: usize,
+        rng: &mut R,
+    ) -> (ProvingKey<E>, HistoryCountVerifier<K, E>) {
+        let params =
+            Groth16::<E>::generate_random_parameters_with_reduction(HistoryCountCircuit::<K, _>::blank(depth), rng)
+                .expect("The blank history circuit synthesizes without witness values");
+        let verifying_key = prepare_verifying_key(&params.vk);
+        (params, HistoryCountVerifier::from_prepared_key(verifying_key))
+    }
+
+    // 組み込みのダミーの回路はセットアップで値を読まないので、生成は失敗しない
+    fn generate_for_circuit<R: RngCore + CryptoRng>(
+        circuit: ActivityCircuit<E::ScalarField>,
+        rng: &mut R,
+    ) -> (ActivityProver<E>, ActivityVerifier<E>) {
+        Self::try_generate_for_circuit(circuit, rng)
+            .expect("The placeholder circuit synthesizes without witness values")
+    }
+
+    // セットアップでは秘密入力の値が割り当てられないので、値によって制約を変える回路はここで失敗する
+    fn try_generate_for_circuit<R: RngCore + CryptoRng, C: ConstraintSynthesizer<E::ScalarField>>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(ActivityProver<E>, ActivityVerifier<E>), ZkError> {
+        #[cfg(feature = "tracing")]
+        let (_span, started) = (tracing::info_span!("setup").entered(), std::time::Instant::now());
+
+        // 証明キーと検証キーの生成
+        let params = Groth16::<E>::generate_random_parameters_with_reduction(circuit, rng)?;
+        let verifying_key = prepare_verifying_key(&params.vk);
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            variables = params.a_query.len(),
+            public_inputs = params.vk.gamma_abc_g1.len() - 1,
+            "generated keys"
+        );
+
+        Ok((
+            ActivityProver::from_proving_key(params),
+            ActivityVerifier::from_prepared_key(verifying_key),
+        ))
+    }
+
+    // 証明キーと検証キーが同じセットアップの組か（組でなければ、証明は生成できても決して検証を通らない）
+    // 証明キーに含まれる検証キーとの一致に加え、証明キーだけが持つβとδが検証キーのものと同じ値かを確かめる
+    // セットアップの生成元は乱数で鍵に残らないので、e(β·G1, δ·G2) = e(δ·G1, β·G2) の対称性で比べる
+    pub fn keys_match(pk: &ProvingKey<E>, pvk: &PreparedVerifyingKey<E>) -> bool {
+        pk.vk == pvk.vk && E::pairing(pk.beta_g1, pvk.vk.delta_g2) == E::pairing(pk.delta_g1, pvk.vk.beta_g2)
+    }
+
+    // 一度生成した鍵をファイルに保存する
+    #[cfg(feature = "std")]
+    pub fn save_keys(
+        prover: &ActivityProver<E>,
+        verifier: &ActivityVerifier<E>,
+        prover_path: impl AsRef<Path>,
+        verifier_path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        prover.save(prover_path)?;
+        verifier.save(verifier_path)
+    }
+
+    // 保存済みの鍵を読み込み、同じセットアップの組であることを確認する
+    #[cfg(feature = "std")]
+    pub fn load(
+        prover_path: impl AsRef<Path>,
+        verifier_path: impl AsRef<Path>,
+    ) -> Result<(ActivityProver<E>, ActivityVerifier<E>), SetupError> {
+        let prover = ActivityProver::load(prover_path)?;
+        let verifier = ActivityVerifier::load(verifier_path)?;
+
+        if !Self::keys_match(&prover.proving_key, &verifier.verifying_key) {
+            return Err(SetupError::KeyMismatch);
+        }
+
+        Ok((prover, verifier))
+    }
+}
+
+// 鍵の読み込みに関するエラー
+#[derive(Debug)]
+pub enum SetupError {
+    // ファイルの読み込みに失敗した
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    // 鍵として解釈できないバイト列だった
+    Serialization(SerializationError),
+    // 証明キーと検証キーが別々のセットアップから生成されている
+    KeyMismatch,
+    // 検証キーの公開入力の数が回路と合わない（別の回路のセレモニーで生成された鍵）
+    PublicInputCount { expected: usize, found: usize },
+    // 鍵のヘッダーが読めない、または別のバージョンの回路で生成された鍵
+    Header(ZkError),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            SetupError::Io(e) => write!(f, "Failed to read key file: {}", e),
+            SetupError::Serialization(e) => write!(f, "Failed to deserialize key: {}", e),
+            SetupError::KeyMismatch => write!(
+                f,
+                "Proving key and verifying key are from different setups, so its proofs would never verify"
+            ),
+            SetupError::PublicInputCount { expected, found } => write!(
+                f,
+                "Verifying key has {} public inputs, but the circuit expects {}",
+                found, expected
+            ),
+            SetupError::Header(e) => write!(f, "Invalid key header: {}", e),
+        }
+    }
+}
+
+impl ark_std::error::Error for SetupError {
+    fn source(&self) -> Option<&(dyn ark_std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            SetupError::Io(e) => Some(e),
+            SetupError::Serialization(e) => Some(e),
+            SetupError::Header(e) => Some(e),
+            SetupError::KeyMismatch | SetupError::PublicInputCount { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for SetupError {
+    fn from(e: io::Error) -> Self {
+        SetupError::Io(e)
+    }
+}
+
+impl From<SerializationError> for SetupError {
+    fn from(e: SerializationError) -> Self {
+        SetupError::Serialization(e)
+    }
+}
+
+impl From<ZkError> for SetupError {
+    fn from(e: ZkError) -> Self {
+        SetupError::Header(e)
+    }
+}
+
+// 保存した鍵の先頭に付けるヘッダー（マジック4バイト、鍵の種類1バイト、回路のバージョンと公開入力の数を2バイトずつ）
+// 古い回路の鍵を読み込んで検証が黙って失敗するのを防ぐため、読み込み時に回路と一致するかを確認する
+const KEY_MAGIC: [u8; 4] = *b"zksk";
+const KEY_HEADER_LEN: usize = 9;
+
+// 鍵の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Proving,
+    Verifying,
+}
+
+impl KeyKind {
+    // 鍵ファイルのヘッダーに書く種類の1バイト
+    #[cfg(feature = "std")]
+    fn tag(self) -> u8 {
+        match self {
+            KeyKind::Proving => b'P',
+            KeyKind::Verifying => b'V',
+        }
+    }
+}
+
+// 鍵ファイルのヘッダーから分かる情報（鍵の本体は読み込まない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyInfo {
+    pub kind: KeyKind,
+    pub circuit_version: u16,
+    pub public_inputs: usize,
+    // ヘッダーを除いた鍵の本体のバイト数
+    pub key_size: usize,
+}
+
+// 鍵のバイト列を、本体をデシリアライズせずにヘッダーだけ読んで調べる
+// 回路のバージョンが違えばKeyVersionMismatch、ヘッダーとして読めなければSerializationのエラー
+pub fn key_info(bytes: &[u8]) -> Result<KeyInfo, ZkError> {
+    let invalid = || ZkError::Serialization(SerializationError::InvalidData);
+    if bytes.len() < KEY_HEADER_LEN || bytes[..4] != KEY_MAGIC {
+        return Err(invalid());
+    }
+    let kind = match bytes[4] {
+        b'P' => KeyKind::Proving,
+        b'V' => KeyKind::Verifying,
+        _ => return Err(invalid()),
+    };
+    let circuit_version = u16::from_be_bytes([bytes[5], bytes[6]]);
+    if circuit_version != CIRCUIT_VERSION {
+        return Err(ZkError::KeyVersionMismatch {
+            expected: CIRCUIT_VERSION,
+            found: circuit_version,
+        });
+    }
+    Ok(KeyInfo {
+        kind,
+        circuit_version,
+        public_inputs: u16::from_be_bytes([bytes[7], bytes[8]]) as usize,
+        key_size: bytes.len() - KEY_HEADER_LEN,
+    })
+}
+
+// 2つの鍵（証明キー同士または検証キー同士）を非圧縮形式のバイト列で比べる
+// 公開された鍵はActivityVerifier::from_vk_bytesなどで読み込んでから、setup_from_seedで生成し直した鍵と比べる
+pub fn keys_equal<K: CanonicalSerialize>(a: &K, b: &K) -> bool {
+    let bytes = |key: &K| {
+        let mut bytes = Vec::with_capacity(key.uncompressed_size());
+        // Vecへの書き込みは失敗しない
+        key.serialize_uncompressed(&mut bytes)
+            .expect("Serializing into a Vec cannot fail");
+        bytes
+    };
+    bytes(a) == bytes(b)
+}
+
+// ヘッダー付きで保存できる鍵（証明キーと検証キー。検証キーは前処理せずに保存し、読み込んでから前処理する）
+// 読み込みはserializationの長さを確かめる関数で行う（arkworksの実装は壊れた長さでパニックすることがある）
+#[cfg(feature = "std")]
+pub(crate) trait PersistedKey: CanonicalSerialize + Sized {
+    const KIND: KeyKind;
+    fn public_inputs(&self) -> usize;
+    fn read(bytes: &[u8]) -> Result<Self, SerializationError>;
+}
+
+#[cfg(feature = "std")]
+impl<E: Pairing> PersistedKey for ProvingKey<E> {
+    const KIND: KeyKind = KeyKind::Proving;
+    fn public_inputs(&self) -> usize {
+        self.vk.gamma_abc_g1.len() - 1
+    }
+    fn read(bytes: &[u8]) -> Result<Self, SerializationError> {
+        read_key_whole(bytes, Compress::No, read_proving_key)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Pairing> PersistedKey for VerifyingKey<E> {
+    const KIND: KeyKind = KeyKind::Verifying;
+    fn public_inputs(&self) -> usize {
+        self.gamma_abc_g1.len() - 1
+    }
+    fn read(bytes: &[u8]) -> Result<Self, SerializationError> {
+        read_key_whole(bytes, Compress::No, read_verifying_key)
+    }
+}
+
+// 鍵をヘッダー付きのバイト列に変換する（読み込み時の検証コストを抑えるため本体は非圧縮形式）
+#[cfg(feature = "std")]
+pub(crate) fn key_to_bytes<T: PersistedKey>(key: &T) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(KEY_HEADER_LEN + key.uncompressed_size());
+    bytes.extend_from_slice(&KEY_MAGIC);
+    bytes.push(T::KIND.tag());
+    bytes.extend_from_slice(&CIRCUIT_VERSION.to_be_bytes());
+    // 公開入力の数は回路の定数で、2バイトに収まる
+    bytes.extend_from_slice(&(key.public_inputs() as u16).to_be_bytes());
+    // Vecへの書き込みは失敗しない
+    key.serialize_uncompressed(&mut bytes)
+        .expect("Serializing into a Vec cannot fail");
+    bytes
+}
+
+// ヘッダーを確認してからバイト列から鍵を復元する（曲線上の点であることも検証する）
+#[cfg(feature = "std")]
+pub(crate) fn key_from_bytes<T: PersistedKey>(bytes: &[u8]) -> Result<T, SetupError> {
+    let info = key_info(bytes)?;
+    if info.kind != T::KIND {
+        return Err(SerializationError::InvalidData.into());
+    }
+    if info.public_inputs != NUM_PUBLIC_INPUTS {
+        return Err(SetupError::PublicInputCount {
+            expected: NUM_PUBLIC_INPUTS,
+            found: info.public_inputs,
+        });
+    }
+    Ok(T::read(&bytes[KEY_HEADER_LEN..])?)
+}
+
+// 鍵をファイルに書き出す
+#[cfg(feature = "std")]
+pub(crate) fn write_key<T: PersistedKey>(key: &T, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, key_to_bytes(key))
+}
+
+// ファイルから鍵を読み込む
+#[cfg(feature = "std")]
+pub(crate) fn read_key<T: PersistedKey>(path: impl AsRef<Path>) -> Result<T, SetupError> {
+    let bytes = fs::read(path)?;
+    key_from_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+    use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, shared_test_verifier, temp_dir, test_secret};
+    use crate::{hash_activity, ActivityData, Bn254Prover, Bn254Verifier, NullifierSet};
+
+    // どの曲線でも同じ手順でセットアップ・証明・検証・二重計上の判定ができる
+    fn run_flow<E: Pairing>()
+    where
+        E::ScalarField: PedersenField,
+    {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = Setup::<E>::generate_for_curve(rng);
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("generic_curve"),
+            user_secret: test_secret(14),
+            salt: test_secret(15),
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let nullifier = verifier.verify_activity(&public_activity, &proof).unwrap().nullifier.unwrap();
+
+        let mut nullifiers = NullifierSet::new();
+        assert!(nullifiers.insert_if_new(nullifier));
+        assert!(!nullifiers.insert_if_new(nullifier));
+    }
+
+    #[test]
+    fn test_keys_loaded_from_disk_verify() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = Setup::generate_with_rng(rng);
+
+        let dir = temp_dir("keys_loaded_from_disk");
+        let prover_path = dir.join("prover.key");
+        let verifier_path = dir.join("verifier.key");
+        Setup::save_keys(&prover, &verifier, &prover_path, &verifier_path).unwrap();
+
+        // 読み込んだ鍵同士で証明と検証ができる
+        let (loaded_prover, loaded_verifier) = Setup::load(&prover_path, &verifier_path).unwrap();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("persisted_keys"),
+            user_secret: test_secret(8),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = loaded_prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        assert!(loaded_verifier.verify_activity_bool(&public_activity, &proof));
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_seeded_setup_is_reproducible() {
+        let (prover, verifier) = Setup::<Bn254>::setup_from_seed([42u8; 32]);
+        let (again_prover, again_verifier) = Setup::<Bn254>::setup_from_seed([42u8; 32]);
+        let (_, other_verifier) = Setup::<Bn254>::setup_from_seed([43u8; 32]);
+
+        // 同じシードからは証明キーも検証キーもバイト列まで同じになり、別のシードからは別の鍵になる
+        assert!(keys_equal(verifier.verifying_key(), again_verifier.verifying_key()));
+        assert!(keys_equal(&prover.proving_key, &again_prover.proving_key));
+        assert!(!keys_equal(verifier.verifying_key(), other_verifier.verifying_key()));
+
+        // 公開された検証キーのバイト列から読み込んだ鍵とも比べられる
+        let mut published = Vec::new();
+        verifier.verifying_key().serialize_compressed(&mut published).unwrap();
+        let published = ActivityVerifier::<Bn254>::from_vk_bytes(&published).unwrap();
+        assert!(keys_equal(published.verifying_key(), again_verifier.verifying_key()));
+    }
+
+    #[test]
+    fn test_shared_test_verifier_is_identical_across_calls() {
+        // 呼び出すたびに同じ鍵の新しい検証者になり、どれで検証しても結果は同じ
+        let first = shared_test_verifier();
+        let second = shared_test_verifier();
+        assert!(keys_equal(first.verifying_key(), second.verifying_key()));
+
+        let (prover, third) = shared_test_setup();
+        assert!(keys_equal(first.verifying_key(), third.verifying_key()));
+        let activity = ActivityData::new(Utc::now(), hash_activity("shared"), test_secret(41), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, &mut seeded_rng()).unwrap();
+        assert!(first.verify_activity_bool(&public_activity, &proof));
+        assert!(second.verify_activity_bool(&public_activity, &proof));
+    }
+
+    #[test]
+    fn test_same_flow_on_both_curves() {
+        run_flow::<Bn254>();
+        run_flow::<Bls12_381>();
+    }
+
+    #[test]
+    fn test_keys_match_detects_pairs_from_other_setups() {
+        let (prover, verifier) = shared_test_setup();
+        let (other_prover, other_verifier) = Setup::generate_with_rng(&mut seeded_rng());
+        assert!(Setup::keys_match(prover.proving_key(), &verifier.verifying_key));
+        assert!(!Setup::keys_match(prover.proving_key(), &other_verifier.verifying_key));
+
+        // 証明キーに埋め込まれた検証キーだけを差し替えても、βとδが合わないので組とみなさない
+        let mut spliced = prover.proving_key().clone();
+        spliced.vk = other_prover.proving_key().vk.clone();
+        assert!(!Setup::keys_match(&spliced, &other_verifier.verifying_key));
+
+        // 読み込み時には、証明を作る前に組でないことをエラーとして知らせる
+        let dir = temp_dir("spliced_key_pair");
+        let (prover_path, verifier_path) = (dir.join("prover.key"), dir.join("verifier.key"));
+        Setup::save_keys(&Bn254Prover::from_proving_key(spliced), &other_verifier, &prover_path, &verifier_path)
+            .unwrap();
+        let error = Setup::<Bn254>::load(&prover_path, &verifier_path).err().unwrap();
+        assert!(matches!(error, SetupError::KeyMismatch));
+        assert!(error.to_string().contains("different setups"));
+    }
+
+    #[test]
+    fn test_mismatched_key_pair_fails_to_load() {
+        let rng = &mut seeded_rng();
+        let (prover, _) = Setup::generate_with_rng(rng);
+        let (_, other_verifier) = Setup::generate_with_rng(rng);
+
+        let dir = temp_dir("mismatched_key_pair");
+        let prover_path = dir.join("prover.key");
+        let verifier_path = dir.join("verifier.key");
+        Setup::save_keys(&prover, &other_verifier, &prover_path, &verifier_path).unwrap();
+
+        // 別々のセットアップの鍵は組として読み込めない
+        assert!(matches!(
+            Setup::<Bn254>::load(&prover_path, &verifier_path),
+            Err(SetupError::KeyMismatch)
+        ));
+
+        // 存在しないファイルや壊れたファイルもエラーになる
+        assert!(matches!(
+            Bn254Verifier::load(dir.join("missing.key")),
+            Err(SetupError::Io(_))
+        ));
+        fs::write(dir.join("broken.key"), [0u8; 16]).unwrap();
+        assert!(matches!(
+            Bn254Verifier::load(dir.join("broken.key")),
+            Err(SetupError::Header(ZkError::Serialization(_)))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_key_header_describes_the_key() {
+        let (prover, verifier) = shared_test_setup();
+        let proving_key = key_to_bytes(prover.proving_key());
+        let verifying_key = key_to_bytes(&verifier.verifying_key.vk);
+
+        // ヘッダーだけで鍵の種類・回路のバージョン・公開入力の数・本体の大きさが分かる
+        let info = key_info(&verifying_key).unwrap();
+        assert_eq!(
+            info,
+            KeyInfo {
+                kind: KeyKind::Verifying,
+                circuit_version: CIRCUIT_VERSION,
+                public_inputs: NUM_PUBLIC_INPUTS,
+                key_size: verifier.verifying_key.vk.uncompressed_size(),
+            }
+        );
+        let info = key_info(&proving_key).unwrap();
+        assert_eq!(info.kind, KeyKind::Proving);
+        assert_eq!(info.key_size, prover.proving_key.uncompressed_size());
+        assert!(info.key_size > key_info(&verifying_key).unwrap().key_size);
+
+        // 種類の違う鍵としては読み込めない
+        assert_eq!(key_from_bytes::<VerifyingKey<Bn254>>(&verifying_key).unwrap(), verifier.verifying_key.vk);
+        assert_eq!(key_from_bytes::<ProvingKey<Bn254>>(&proving_key).unwrap(), *prover.proving_key);
+        assert!(matches!(
+            key_from_bytes::<ProvingKey<Bn254>>(&verifying_key),
+            Err(SetupError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_key_version_is_rejected() {
+        let (prover, verifier) = shared_test_setup();
+
+        let dir = temp_dir("tampered_key_version");
+        let prover_path = dir.join("prover.key");
+        let verifier_path = dir.join("verifier.key");
+        Setup::save_keys(&prover, &verifier, &prover_path, &verifier_path).unwrap();
+
+        // 回路のバージョンの下位バイトを書き換えると、本体を読む前に拒否される
+        let mut bytes = fs::read(&verifier_path).unwrap();
+        bytes[6] ^= 1;
+        assert!(matches!(
+            key_info(&bytes),
+            Err(ZkError::KeyVersionMismatch { expected: CIRCUIT_VERSION, found }) if found == CIRCUIT_VERSION ^ 1
+        ));
+        fs::write(&verifier_path, &bytes).unwrap();
+        assert!(matches!(
+            Bn254Verifier::load(&verifier_path),
+            Err(SetupError::Header(ZkError::KeyVersionMismatch { found, .. })) if found == CIRCUIT_VERSION ^ 1
+        ));
+
+        // 公開入力の数を書き換えた鍵や、ヘッダーのない以前の形式の鍵も読み込めない
+        let mut bytes = fs::read(&prover_path).unwrap();
+        bytes[8] += 1;
+        fs::write(&prover_path, &bytes).unwrap();
+        assert!(matches!(
+            Bn254Prover::load(&prover_path),
+            Err(SetupError::PublicInputCount { found, .. }) if found == NUM_PUBLIC_INPUTS + 1
+        ));
+        assert!(key_info(&bytes[KEY_HEADER_LEN..]).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // 秘密入力の値で分岐する回路（セットアップでは値がないので合成に失敗する）
+    struct ValueDependentCircuit;
+
+    impl ConstraintSynthesizer<Fr> for ValueDependentCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let value = FpVar::new_witness(cs, || Ok(Fr::from(1u64)))?;
+            value.value()?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failed_setup_returns_error() {
+        let rng = &mut seeded_rng();
+        assert!(matches!(
+            Setup::<Bn254>::try_generate_for_circuit(ValueDependentCircuit, rng),
+            Err(ZkError::Synthesis(SynthesisError::AssignmentMissing))
+        ));
+
+        // 組み込みの回路は成功する
+        let (prover, verifier) = Setup::try_generate_with_rng(rng).unwrap();
+        let activity = ActivityData::new(Utc::now(), hash_activity("try_setup"), test_secret(16), test_secret(17));
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+    }
+}
@@ -0,0 +1,236 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
+use ark_groth16::Proof;
+
+use crate::error::ZkError;
+
+// snarkjsのJSON（proof.json・verification_key.json）での点の表現
+// 座標は10進文字列なので、バイト列のエンディアンは関係しない（serialize_proofが使うarkworksの圧縮形式はリトルエンディアン）。
+// 点はZ = 1の射影座標[x, y, z]で、G2の座標はFq2を[c0, c1]（実部, 虚部）の順に並べる。
+// Solidityの検証コントラクトに渡すときだけEIP-197の順に合わせて[c1, c0]に入れ替えるが、
+// それはsnarkjsのzkey export soliditycalldataが行うので、JSONの段階では入れ替えない
+pub(crate) fn g1_json(point: &G1Affine) -> String {
+    format!(r#"["{}","{}","1"]"#, point.x, point.y)
+}
+
+pub(crate) fn g2_json(point: &G2Affine) -> String {
+    format!(
+        r#"[["{}","{}"],["{}","{}"],["1","0"]]"#,
+        point.x.c0, point.x.c1, point.y.c0, point.y.c1
+    )
+}
+
+// 証明をsnarkjsのproof.jsonと同じ形式で書き出す（snarkjs groth16 verifyにそのまま渡せる）
+pub fn proof_to_snarkjs_json(proof: &Proof<Bn254>) -> String {
+    format!(
+        r#"{{"pi_a":{},"pi_b":{},"pi_c":{},"protocol":"groth16","curve":"bn128"}}"#,
+        g1_json(&proof.a),
+        g2_json(&proof.b),
+        g1_json(&proof.c),
+    )
+}
+
+// snarkjsのproof.jsonから証明を読み込む（点が曲線上・部分群内にあることも検証する）
+// 座標はpより小さい正規の10進表記だけを受け付け、同じ証明に別の表現ができないようにする
+pub fn proof_from_snarkjs_json(json: &str) -> Result<Proof<Bn254>, ZkError> {
+    let fields = Parser { rest: json }.object()?;
+    let field = |name: &'static str| {
+        fields
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+            .ok_or(ZkError::InvalidProofJson(name))
+    };
+    if let Some((_, curve)) = fields.iter().find(|(key, _)| *key == "curve") {
+        if !matches!(curve, Json::Str("bn128")) {
+            return Err(ZkError::InvalidProofJson("curve"));
+        }
+    }
+
+    Ok(Proof {
+        a: read_g1(field("pi_a")?).ok_or(ZkError::InvalidProofJson("pi_a"))?,
+        b: read_g2(field("pi_b")?).ok_or(ZkError::InvalidProofJson("pi_b"))?,
+        c: read_g1(field("pi_c")?).ok_or(ZkError::InvalidProofJson("pi_c"))?,
+    })
+}
+
+fn read_g1(value: &Json) -> Option<G1Affine> {
+    let [x, y, z] = value.array()? else { return None };
+    if !matches!(z, Json::Str("1")) {
+        return None;
+    }
+    let point = G1Affine::new_unchecked(read_fq(x)?, read_fq(y)?);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn read_g2(value: &Json) -> Option<G2Affine> {
+    let [x, y, z] = value.array()? else { return None };
+    if read_fq2(z)? != Fq2::new(Fq::from(1u64), Fq::from(0u64)) {
+        return None;
+    }
+    let point = G2Affine::new_unchecked(read_fq2(x)?, read_fq2(y)?);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn read_fq2(value: &Json) -> Option<Fq2> {
+    let [c0, c1] = value.array()? else { return None };
+    Some(Fq2::new(read_fq(c0)?, read_fq(c1)?))
+}
+
+// from_strはpを法として簡約してしまうので、書き戻して同じ表記になるものだけを受け付ける
+fn read_fq(value: &Json) -> Option<Fq> {
+    let Json::Str(digits) = value else { return None };
+    let fq = Fq::from_str(digits).ok()?;
+    (fq.to_string() == *digits).then_some(fq)
+}
+
+// proof.jsonを読むのに必要な分だけのJSON（値は文字列と配列のみ）
+enum Json<'a> {
+    Str(&'a str),
+    Array(Vec<Json<'a>>),
+}
+
+impl<'a> Json<'a> {
+    fn array(&self) -> Option<&[Json<'a>]> {
+        match self {
+            Json::Array(items) => Some(items),
+            Json::Str(_) => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    // 文字列か配列を値に持つオブジェクト1つだけからなる文書を読む
+    fn object(mut self) -> Result<Vec<(&'a str, Json<'a>)>, ZkError> {
+        let mut fields = Vec::new();
+        self.expect('{')?;
+        if !self.eat('}') {
+            loop {
+                let key = self.string()?;
+                self.expect(':')?;
+                fields.push((key, self.value()?));
+                if self.eat('}') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        if !self.rest.trim_start().is_empty() {
+            return Err(ZkError::InvalidProofJson("trailing characters"));
+        }
+        Ok(fields)
+    }
+
+    fn value(&mut self) -> Result<Json<'a>, ZkError> {
+        if !self.eat('[') {
+            return Ok(Json::Str(self.string()?));
+        }
+        let mut items = Vec::new();
+        if !self.eat(']') {
+            loop {
+                items.push(self.value()?);
+                if self.eat(']') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(Json::Array(items))
+    }
+
+    // エスケープを含む文字列は使わないので受け付けない
+    fn string(&mut self) -> Result<&'a str, ZkError> {
+        self.expect('"')?;
+        let end = self.rest.find('"').ok_or(ZkError::InvalidProofJson("unterminated string"))?;
+        let value = &self.rest[..end];
+        if value.contains('\\') {
+            return Err(ZkError::InvalidProofJson("escaped string"));
+        }
+        self.rest = &self.rest[end + 1..];
+        Ok(value)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.trim_start().strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ZkError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(ZkError::InvalidProofJson("unexpected character"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData};
+
+    #[test]
+    fn test_snarkjs_proof_json_round_trip() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("snarkjs"), test_secret(14), test_secret(15));
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // snarkjsのproof.jsonと同じキーと並びで、読み戻すと同じ証明になる
+        let json = proof_to_snarkjs_json(&proof);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["protocol"], "groth16");
+        assert_eq!(value["curve"], "bn128");
+        assert_eq!(value["pi_a"][0], proof.a.x.to_string());
+        assert_eq!(value["pi_b"][0][1], proof.b.x.c1.to_string());
+        assert_eq!(value["pi_b"][2], serde_json::json!(["1", "0"]));
+        let restored = proof_from_snarkjs_json(&json).unwrap();
+        assert_eq!(restored, proof);
+        assert!(verifier.verify_activity_bool(&public_activity, &restored));
+
+        // snarkjsが出力する改行・インデント付きの形式も読める
+        let pretty = serde_json::to_string_pretty(&value).unwrap();
+        assert_eq!(proof_from_snarkjs_json(&pretty).unwrap(), proof);
+    }
+
+    #[test]
+    fn test_invalid_snarkjs_proof_json_is_rejected() {
+        let rng = &mut seeded_rng();
+        let (prover, _) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("snarkjs"), test_secret(14), test_secret(15));
+        let proof = prover.generate_proof(&activity, rng).unwrap();
+        let json = proof_to_snarkjs_json(&proof);
+        let y = proof.a.y.to_string();
+
+        // 曲線上にない点、先頭に0を付けた非正規の座標、G2の実部と虚部の入れ替え、欠けたキーはエラー
+        let off_curve = json.replacen(&y, &(proof.a.y + Fq::from(1u64)).to_string(), 1);
+        let non_canonical = json.replacen(&y, &format!("0{}", y), 1);
+        let swapped = json.replacen(
+            &format!(r#"[["{}","{}"]"#, proof.b.x.c0, proof.b.x.c1),
+            &format!(r#"[["{}","{}"]"#, proof.b.x.c1, proof.b.x.c0),
+            1,
+        );
+        let missing = json.replacen("pi_c", "pi_d", 1);
+        for (invalid, key) in [(off_curve, "pi_a"), (non_canonical, "pi_a"), (swapped, "pi_b"), (missing, "pi_c")] {
+            assert!(matches!(proof_from_snarkjs_json(&invalid), Err(ZkError::InvalidProofJson(k)) if k == key));
+        }
+        assert!(proof_from_snarkjs_json(&json[..json.len() - 1]).is_err());
+        assert!(proof_from_snarkjs_json(&json.replacen("bn128", "bls12381", 1)).is_err());
+    }
+}
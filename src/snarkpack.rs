@@ -0,0 +1,637 @@
+use alloc::vec::Vec;
+
+use ark_bn254::Bn254;
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::{AffineRepr, CurveGroup, PrimeGroup, VariableBaseMSM};
+use ark_ff::{Field, PrimeField, UniformRand, Zero};
+use ark_groth16::{Groth16, Proof};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::error::ZkError;
+use crate::verifier::ActivityVerifier;
+
+// SnarkPack（Gailly, Maller, Nitulescu 2021）による複数のGroth16の証明の集約
+// 同じ検証キーのn個の証明を、O(log n)の大きさの1つの証明にまとめる。検証の手間もペアリングはO(log n)回で、
+// 公開入力の線形結合の計算だけがnに比例する。
+//
+// 集約にはGroth16のセットアップとは別に、2つの秘密a, bのpowers of tauによるSRSが必要になる。
+// - 回路には依存しないので、既存のpowers of tauのセレモニー（Filecoin、Zcashなど）の出力から作って使い回せる
+// - aかbを知っていれば、正しくない証明を含む集約を受理させることができる。本番ではそれぞれ別のセレモニーの出力を使い、
+//   AggregationSrs::setupはテスト用に限る
+// - 集約できる証明の数はSRSの大きさnまで（証明者はg側に2n個、h側にn個の点を持つ。検証者は6個の点だけを持つ）
+//
+// 1ラウンドごとにGTの元を10個送るので、BN254では数が少ないうちは元の証明を並べるより大きい
+// （圧縮形式で8個なら約14KB、元の証明は合わせて1KB）。1ラウンドで約3.9KB増えるので、元の証明より小さくなるのは512個から
+
+// 集約の証明者が使うSRS（g^{a^i}, g^{b^i}はi < 2n、h^{a^i}, h^{b^i}はi < n）
+pub struct AggregationSrs<E: Pairing = Bn254> {
+    g_alpha_powers: Vec<E::G1Affine>,
+    g_beta_powers: Vec<E::G1Affine>,
+    h_alpha_powers: Vec<E::G2Affine>,
+    h_beta_powers: Vec<E::G2Affine>,
+}
+
+// 集約の検証者が使うSRSの一部
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregationVerifierKey<E: Pairing = Bn254> {
+    g: E::G1Affine,
+    h: E::G2Affine,
+    g_alpha: E::G1Affine,
+    g_beta: E::G1Affine,
+    h_alpha: E::G2Affine,
+    h_beta: E::G2Affine,
+}
+
+// 読み込む集約した証明のラウンド数の上限（2^32個を超える証明は集約しない）
+// ラウンド数は書き換えられる長さなので、この上限を超えるものは領域を確保する前に拒否する
+const MAX_AGGREGATION_ROUNDS: u64 = 32;
+
+// 集約した証明。大きさはcompressed_sizeで確かめられる
+// CanonicalDeserializeで読み込むときは、点が曲線と部分群に含まれることとラウンド数の上限も確かめる
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize)]
+pub struct AggregateProof<E: Pairing = Bn254> {
+    // 証明のA, Bと、Cへのコミットメント
+    com_ab: (PairingOutput<E>, PairingOutput<E>),
+    com_c: (PairingOutput<E>, PairingOutput<E>),
+    // Π e(A_i, B_i)^{r^i} と Σ r^i C_i
+    ip_ab: PairingOutput<E>,
+    agg_c: E::G1Affine,
+    // 長さを半分にしていく各ラウンドの左右の交差項
+    rounds: Vec<GipaRound<E>>,
+    // 長さ1まで畳み込んだ値と、畳み込んだコミットメントの鍵
+    final_a: E::G1Affine,
+    final_b: E::G2Affine,
+    final_c: E::G1Affine,
+    final_v: (E::G2Affine, E::G2Affine),
+    final_w: (E::G1Affine, E::G1Affine),
+    // 畳み込んだ鍵が正しい多項式の評価であることのKZGの開示
+    opening_v: (E::G2Affine, E::G2Affine),
+    opening_w: (E::G1Affine, E::G1Affine),
+}
+
+// 1ラウンドの交差項（_lはチャレンジx、_rはx^{-1}を掛けて畳み込む側）
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct GipaRound<E: Pairing> {
+    com_ab_l: (PairingOutput<E>, PairingOutput<E>),
+    com_ab_r: (PairingOutput<E>, PairingOutput<E>),
+    z_ab_l: PairingOutput<E>,
+    z_ab_r: PairingOutput<E>,
+    com_c_l: (PairingOutput<E>, PairingOutput<E>),
+    com_c_r: (PairingOutput<E>, PairingOutput<E>),
+    z_c_l: E::G1Affine,
+    z_c_r: E::G1Affine,
+}
+
+impl<E: Pairing> Valid for AggregateProof<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        (self.com_ab, self.com_c, self.ip_ab, self.agg_c).check()?;
+        self.rounds.check()?;
+        (self.final_a, self.final_b, self.final_c).check()?;
+        (self.final_v, self.final_w, self.opening_v, self.opening_w).check()
+    }
+}
+
+// CanonicalSerializeの導出と同じ並びで読む（ラウンドの列は長さを先に確かめる）
+impl<E: Pairing> CanonicalDeserialize for AggregateProof<E> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let (com_ab, com_c, ip_ab, agg_c) =
+            CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let len = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        if len > MAX_AGGREGATION_ROUNDS {
+            return Err(SerializationError::InvalidData);
+        }
+        let rounds = (0..len)
+            .map(|_| GipaRound::deserialize_with_mode(&mut reader, compress, validate))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (final_a, final_b, final_c) = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let (final_v, final_w, opening_v, opening_w) =
+            CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self {
+            com_ab,
+            com_c,
+            ip_ab,
+            agg_c,
+            rounds,
+            final_a,
+            final_b,
+            final_c,
+            final_v,
+            final_w,
+            opening_v,
+            opening_w,
+        })
+    }
+}
+
+impl<E: Pairing> AggregationSrs<E> {
+    // テスト用に、秘密a, bをその場で選んでSRSを作る（a, bは捨てるが、本番では既存のセレモニーの出力を使うこと）
+    pub fn setup<R: RngCore + CryptoRng>(max_proofs: usize, rng: &mut R) -> Self {
+        let alpha = E::ScalarField::rand(rng);
+        let beta = E::ScalarField::rand(rng);
+        Self {
+            g_alpha_powers: powers(E::G1::generator(), alpha, 2 * max_proofs),
+            g_beta_powers: powers(E::G1::generator(), beta, 2 * max_proofs),
+            h_alpha_powers: powers(E::G2::generator(), alpha, max_proofs),
+            h_beta_powers: powers(E::G2::generator(), beta, max_proofs),
+        }
+    }
+
+    // 集約できる証明の最大の数
+    pub fn max_proofs(&self) -> usize {
+        self.h_alpha_powers.len()
+    }
+
+    pub fn verifier_key(&self) -> AggregationVerifierKey<E> {
+        AggregationVerifierKey {
+            g: self.g_alpha_powers[0],
+            h: self.h_alpha_powers[0],
+            g_alpha: self.g_alpha_powers[1],
+            g_beta: self.g_beta_powers[1],
+            h_alpha: self.h_alpha_powers[1],
+            h_beta: self.h_beta_powers[1],
+        }
+    }
+
+    // 同じ検証キーの証明を集約する（数は2以上の2のべき乗で、public_inputsは証明と同じ順の公開入力）
+    // 個々の証明は検証しないので、正しくない証明が混ざっていれば集約した証明が検証を通らなくなる
+    pub fn aggregate(
+        &self,
+        proofs: &[Proof<E>],
+        public_inputs: &[Vec<E::ScalarField>],
+    ) -> Result<AggregateProof<E>, ZkError> {
+        let n = proofs.len();
+        if public_inputs.len() != n || !is_aggregatable(n) || n > self.max_proofs() {
+            return Err(ZkError::AggregationSize(n));
+        }
+
+        // コミットメントの鍵（vはG1の点に、wはG2の点に対する鍵）
+        let mut v = (self.h_alpha_powers[..n].to_vec(), self.h_beta_powers[..n].to_vec());
+        let w = (&self.g_alpha_powers[n..2 * n], &self.g_beta_powers[n..2 * n]);
+
+        let mut a = proofs.iter().map(|proof| proof.a).collect::<Vec<_>>();
+        let b = proofs.iter().map(|proof| proof.b).collect::<Vec<_>>();
+        let mut c = proofs.iter().map(|proof| proof.c).collect::<Vec<_>>();
+
+        let com_ab = (
+            inner_pairing::<E>(&a, &v.0) + inner_pairing::<E>(w.0, &b),
+            inner_pairing::<E>(&a, &v.1) + inner_pairing::<E>(w.1, &b),
+        );
+        let com_c = (inner_pairing::<E>(&c, &v.0), inner_pairing::<E>(&c, &v.1));
+
+        let mut transcript = Transcript::new();
+        transcript.append(public_inputs);
+        transcript.append(&com_ab);
+        transcript.append(&com_c);
+        let r = transcript.challenge::<E::ScalarField>();
+
+        // Bをr^iで、wの鍵をr^{-i}で重み付けすると、コミットメントを変えずに Π e(A_i, B_i)^{r^i} を内積として扱える
+        let mut r_vec = powers_of(r, n);
+        let r_inv = r.inverse().expect("Challenges are nonzero");
+        let mut b = scale(&b, &r_vec);
+        let r_inv_vec = powers_of(r_inv, n);
+        let mut w = (scale(w.0, &r_inv_vec), scale(w.1, &r_inv_vec));
+
+        let ip_ab = inner_pairing::<E>(&a, &b);
+        let agg_c = E::G1::msm_unchecked(&c, &r_vec).into_affine();
+        transcript.append(&ip_ab);
+        transcript.append(&agg_c);
+
+        let mut rounds = Vec::new();
+        let mut challenges = Vec::new();
+        while a.len() > 1 {
+            let k = a.len() / 2;
+            let (a_l, a_r) = a.split_at(k);
+            let (b_l, b_r) = b.split_at(k);
+            let (c_l, c_r) = c.split_at(k);
+            let (r_l, r_r) = r_vec.split_at(k);
+            let (v0_l, v0_r) = v.0.split_at(k);
+            let (v1_l, v1_r) = v.1.split_at(k);
+            let (w0_l, w0_r) = w.0.split_at(k);
+            let (w1_l, w1_r) = w.1.split_at(k);
+
+            let round = GipaRound {
+                com_ab_l: (
+                    inner_pairing::<E>(a_r, v0_l) + inner_pairing::<E>(w0_r, b_l),
+                    inner_pairing::<E>(a_r, v1_l) + inner_pairing::<E>(w1_r, b_l),
+                ),
+                com_ab_r: (
+                    inner_pairing::<E>(a_l, v0_r) + inner_pairing::<E>(w0_l, b_r),
+                    inner_pairing::<E>(a_l, v1_r) + inner_pairing::<E>(w1_l, b_r),
+                ),
+                z_ab_l: inner_pairing::<E>(a_r, b_l),
+                z_ab_r: inner_pairing::<E>(a_l, b_r),
+                com_c_l: (inner_pairing::<E>(c_r, v0_l), inner_pairing::<E>(c_r, v1_l)),
+                com_c_r: (inner_pairing::<E>(c_l, v0_r), inner_pairing::<E>(c_l, v1_r)),
+                z_c_l: E::G1::msm_unchecked(c_r, r_l).into_affine(),
+                z_c_r: E::G1::msm_unchecked(c_l, r_r).into_affine(),
+            };
+            transcript.append(&round);
+            let x = transcript.challenge::<E::ScalarField>();
+            let x_inv = x.inverse().expect("Challenges are nonzero");
+
+            // A, Cは右半分にx、Bは右半分にx^{-1}を掛けて足す。鍵はそれぞれ対になる側と逆の係数で畳み込む
+            a = fold(&a, x);
+            b = fold(&b, x_inv);
+            c = fold(&c, x);
+            r_vec = r_l.iter().zip(r_r).map(|(l, r)| *l + x_inv * r).collect();
+            v = (fold(&v.0, x_inv), fold(&v.1, x_inv));
+            w = (fold(&w.0, x), fold(&w.1, x));
+            rounds.push(round);
+            challenges.push(x);
+        }
+
+        let (final_a, final_b, final_c) = (a[0], b[0], c[0]);
+        let (final_v, final_w) = ((v.0[0], v.1[0]), (w.0[0], w.1[0]));
+        transcript.append(&(final_a, final_b, final_c));
+        transcript.append(&(final_v, final_w));
+        let z = transcript.challenge::<E::ScalarField>();
+
+        // 畳み込んだ鍵はh^{f_v(a)}とg^{f_w(a)}なので、zでの評価の商をSRSで計算して開示する
+        let (f_v, f_w) = key_polynomials(n, &challenges, r_inv);
+        let (q_v, q_w) = (divide_by_linear(&f_v, z), divide_by_linear(&f_w, z));
+        Ok(AggregateProof {
+            com_ab,
+            com_c,
+            ip_ab,
+            agg_c,
+            rounds,
+            final_a,
+            final_b,
+            final_c,
+            final_v,
+            final_w,
+            opening_v: (
+                E::G2::msm_unchecked(&self.h_alpha_powers[..q_v.len()], &q_v).into_affine(),
+                E::G2::msm_unchecked(&self.h_beta_powers[..q_v.len()], &q_v).into_affine(),
+            ),
+            opening_w: (
+                E::G1::msm_unchecked(&self.g_alpha_powers[..q_w.len()], &q_w).into_affine(),
+                E::G1::msm_unchecked(&self.g_beta_powers[..q_w.len()], &q_w).into_affine(),
+            ),
+        })
+    }
+}
+
+impl<E: Pairing> ActivityVerifier<E> {
+    // 集約した証明を、集約した順の公開入力で検証する（公開入力の数が合わなければErr）
+    // trueなら、どの集約の証明者に対しても、すべての元の証明が公開入力に対して正しかったことになる（SRSの秘密が漏れていなければ）
+    pub fn verify_aggregate(
+        &self,
+        key: &AggregationVerifierKey<E>,
+        aggregate: &AggregateProof<E>,
+        public_inputs: &[Vec<E::ScalarField>],
+    ) -> Result<bool, ZkError> {
+        let n = public_inputs.len();
+        if !is_aggregatable(n) {
+            return Err(ZkError::AggregationSize(n));
+        }
+        if aggregate.rounds.len() != n.trailing_zeros() as usize {
+            return Ok(false);
+        }
+
+        // 証明者と同じ順でトランスクリプトからチャレンジを導き直す
+        let mut transcript = Transcript::new();
+        transcript.append(public_inputs);
+        transcript.append(&aggregate.com_ab);
+        transcript.append(&aggregate.com_c);
+        let r = transcript.challenge::<E::ScalarField>();
+        transcript.append(&aggregate.ip_ab);
+        transcript.append(&aggregate.agg_c);
+        let challenges = aggregate
+            .rounds
+            .iter()
+            .map(|round| {
+                transcript.append(round);
+                transcript.challenge::<E::ScalarField>()
+            })
+            .collect::<Vec<_>>();
+        transcript.append(&(aggregate.final_a, aggregate.final_b, aggregate.final_c));
+        transcript.append(&(aggregate.final_v, aggregate.final_w));
+        let z = transcript.challenge::<E::ScalarField>();
+
+        // Groth16の検証式をr^iで重み付けしてまとめたもの
+        // Π e(A_i, B_i)^{r^i} = e(α, β)^{Σ r^i}・e(Σ r^i IC_i, γ)・e(Σ r^i C_i, δ)
+        let r_vec = powers_of(r, n);
+        let pvk = &self.verifying_key;
+        let mut inputs_acc = E::G1::zero();
+        for (public_inputs, r_i) in public_inputs.iter().zip(&r_vec) {
+            self.check_arity(public_inputs)?;
+            inputs_acc += Groth16::<E>::prepare_inputs(pvk, public_inputs)? * r_i;
+        }
+        let r_sum = r_vec.iter().sum::<E::ScalarField>();
+        let expected_ab = PairingOutput::<E>(pvk.alpha_g1_beta_g2) * r_sum
+            + E::multi_pairing([inputs_acc.into_affine(), aggregate.agg_c], [pvk.vk.gamma_g2, pvk.vk.delta_g2]);
+        let groth16_ok = aggregate.ip_ab == expected_ab;
+
+        // 交差項でコミットメントと内積を畳み込み、長さ1の値と一致するかを確かめる
+        let (mut com_ab, mut com_c) = (aggregate.com_ab, aggregate.com_c);
+        let (mut z_ab, mut z_c) = (aggregate.ip_ab, aggregate.agg_c.into_group());
+        for (round, x) in aggregate.rounds.iter().zip(&challenges) {
+            let x_inv = x.inverse().expect("Challenges are nonzero");
+            com_ab.0 += round.com_ab_l.0 * x + round.com_ab_r.0 * x_inv;
+            com_ab.1 += round.com_ab_l.1 * x + round.com_ab_r.1 * x_inv;
+            z_ab += round.z_ab_l * x + round.z_ab_r * x_inv;
+            com_c.0 += round.com_c_l.0 * x + round.com_c_r.0 * x_inv;
+            com_c.1 += round.com_c_l.1 * x + round.com_c_r.1 * x_inv;
+            z_c += round.z_c_l * x + round.z_c_r * x_inv;
+        }
+        let (a, b, c) = (aggregate.final_a, aggregate.final_b, aggregate.final_c);
+        let (v, w) = (aggregate.final_v, aggregate.final_w);
+        let r_final = folded_r(n, &challenges, r);
+        let gipa_ok = com_ab.0 == E::multi_pairing([a, w.0], [v.0, b])
+            && com_ab.1 == E::multi_pairing([a, w.1], [v.1, b])
+            && z_ab == E::pairing(a, b)
+            && com_c.0 == E::pairing(c, v.0)
+            && com_c.1 == E::pairing(c, v.1)
+            && z_c == c * r_final;
+
+        // 畳み込んだ鍵がSRSの鍵から正しく畳み込まれたもの（多項式の評価）であることをKZGで確かめる
+        let r_inv = r.inverse().expect("Challenges are nonzero");
+        let (f_v, f_w) = key_polynomials(n, &challenges, r_inv);
+        let (f_v_z, f_w_z) = (evaluate(&f_v, z), evaluate(&f_w, z));
+        let kzg_ok = key.check_v_opening(key.g_alpha, aggregate.opening_v.0, v.0, z, f_v_z)
+            && key.check_v_opening(key.g_beta, aggregate.opening_v.1, v.1, z, f_v_z)
+            && key.check_w_opening(key.h_alpha, aggregate.opening_w.0, w.0, z, f_w_z)
+            && key.check_w_opening(key.h_beta, aggregate.opening_w.1, w.1, z, f_w_z);
+
+        Ok(groth16_ok && gipa_ok && kzg_ok)
+    }
+}
+
+impl<E: Pairing> AggregationVerifierKey<E> {
+    // G2の鍵の開示: e(g^{s - z}, π) = e(g, v - h^{f(z)})（g_secretはg^aかg^b）
+    fn check_v_opening(
+        &self,
+        g_secret: E::G1Affine,
+        opening: E::G2Affine,
+        folded: E::G2Affine,
+        z: E::ScalarField,
+        f_z: E::ScalarField,
+    ) -> bool {
+        let lhs = E::pairing(g_secret.into_group() - self.g * z, opening);
+        lhs == E::pairing(self.g, folded.into_group() - self.h * f_z)
+    }
+
+    // G1の鍵の開示: e(π, h^{s - z}) = e(w - g^{f(z)}, h)（h_secretはh^aかh^b）
+    fn check_w_opening(
+        &self,
+        h_secret: E::G2Affine,
+        opening: E::G1Affine,
+        folded: E::G1Affine,
+        z: E::ScalarField,
+        f_z: E::ScalarField,
+    ) -> bool {
+        let lhs = E::pairing(opening, h_secret.into_group() - self.h * z);
+        lhs == E::pairing(folded.into_group() - self.g * f_z, self.h)
+    }
+}
+
+// 集約できる証明の数（2以上の2のべき乗）
+fn is_aggregatable(n: usize) -> bool {
+    n >= 2 && n.is_power_of_two()
+}
+
+// Π e(g1_i, g2_i)
+fn inner_pairing<E: Pairing>(g1: &[E::G1Affine], g2: &[E::G2Affine]) -> PairingOutput<E> {
+    E::multi_pairing(g1.iter().copied(), g2.iter().copied())
+}
+
+// 1, x, x^2, ..., x^{len-1}
+fn powers_of<F: Field>(x: F, len: usize) -> Vec<F> {
+    core::iter::successors(Some(F::one()), |power| Some(*power * x)).take(len).collect()
+}
+
+// generator^{x^i}（i < len）
+fn powers<G: CurveGroup>(generator: G, x: G::ScalarField, len: usize) -> Vec<G::Affine> {
+    let points = powers_of(x, len).into_iter().map(|power| generator * power).collect::<Vec<_>>();
+    G::normalize_batch(&points)
+}
+
+// 点ごとに係数を掛ける
+fn scale<A: AffineRepr>(points: &[A], scalars: &[A::ScalarField]) -> Vec<A> {
+    let scaled = points.iter().zip(scalars).map(|(point, scalar)| *point * scalar).collect::<Vec<_>>();
+    A::Group::normalize_batch(&scaled)
+}
+
+// 左半分 + 右半分・coeff で長さを半分にする
+fn fold<A: AffineRepr>(points: &[A], coeff: A::ScalarField) -> Vec<A> {
+    let (left, right) = points.split_at(points.len() / 2);
+    let folded = left.iter().zip(right).map(|(l, r)| *r * coeff + l).collect::<Vec<_>>();
+    A::Group::normalize_batch(&folded)
+}
+
+// 畳み込んだ鍵の指数にあたる多項式の係数（低い次数から）
+// ラウンドjで畳み込む長さをk_j = n / 2^{j+1}とすると
+//   f_v(X) = Π_j (1 + x_j^{-1} X^{k_j})、f_w(X) = X^n Π_j (1 + x_j r^{-k_j} X^{k_j})
+// k_jは互いに異なる2のべき乗なので、X^iの係数はiのビットが立っているラウンドの係数の積になる
+fn key_polynomials<F: PrimeField>(n: usize, challenges: &[F], r_inv: F) -> (Vec<F>, Vec<F>) {
+    let v_coeffs = challenges
+        .iter()
+        .map(|x| x.inverse().expect("Challenges are nonzero"))
+        .collect::<Vec<_>>();
+    let w_coeffs = challenges
+        .iter()
+        .enumerate()
+        .map(|(j, x)| *x * r_inv.pow([(n >> (j + 1)) as u64]))
+        .collect::<Vec<_>>();
+    let expand = |coeffs: &[F]| {
+        (0..n)
+            .map(|i| {
+                coeffs
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| i & (n >> (j + 1)) != 0)
+                    .map(|(_, coeff)| *coeff)
+                    .product::<F>()
+            })
+            .collect::<Vec<_>>()
+    };
+    let f_v = expand(&v_coeffs);
+    let mut f_w = alloc::vec![F::zero(); n];
+    f_w.extend(expand(&w_coeffs));
+    (f_v, f_w)
+}
+
+// rのべきの列を畳み込んだ値 Π_j (1 + x_j^{-1} r^{k_j})
+fn folded_r<F: PrimeField>(n: usize, challenges: &[F], r: F) -> F {
+    challenges
+        .iter()
+        .enumerate()
+        .map(|(j, x)| F::one() + x.inverse().expect("Challenges are nonzero") * r.pow([(n >> (j + 1)) as u64]))
+        .product()
+}
+
+fn evaluate<F: Field>(coeffs: &[F], z: F) -> F {
+    coeffs.iter().rev().fold(F::zero(), |acc, coeff| acc * z + coeff)
+}
+
+// (f(X) - f(z)) / (X - z) の係数（組立除法）
+fn divide_by_linear<F: Field>(coeffs: &[F], z: F) -> Vec<F> {
+    let mut quotient = alloc::vec![F::zero(); coeffs.len() - 1];
+    let mut carry = F::zero();
+    for i in (1..coeffs.len()).rev() {
+        carry = carry * z + coeffs[i];
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+// Fiat-Shamirの変換用のトランスクリプト（これまでに加えた値すべてのSHA256を状態として持つ）
+struct Transcript {
+    state: [u8; 32],
+}
+
+impl Transcript {
+    fn new() -> Self {
+        Self {
+            state: Sha256::digest(b"zk_sample/snarkpack/v1").into(),
+        }
+    }
+
+    fn append<T: CanonicalSerialize + ?Sized>(&mut self, value: &T) {
+        let mut bytes = Vec::with_capacity(value.uncompressed_size());
+        // Vecへの書き込みは失敗しない
+        value
+            .serialize_uncompressed(&mut bytes)
+            .expect("Serializing into a Vec cannot fail");
+        self.state = Sha256::new().chain_update(self.state).chain_update(&bytes).finalize().into();
+    }
+
+    // 逆元を持つ（0でない）チャレンジを導く
+    fn challenge<F: PrimeField>(&mut self) -> F {
+        loop {
+            self.state = Sha256::new().chain_update(self.state).chain_update(b"challenge").finalize().into();
+            let challenge = F::from_le_bytes_mod_order(&self.state);
+            if !challenge.is_zero() {
+                return challenge;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use chrono::Utc;
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, test_secret};
+    use crate::{hash_activity, ActivityData};
+
+    // 8件の行動の証明と、それぞれの公開入力
+    fn eight_proofs() -> (ActivityVerifier, Vec<Proof<Bn254>>, Vec<Vec<Fr>>) {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let (proofs, public_inputs) = (0..8u8)
+            .map(|i| {
+                let activity =
+                    ActivityData::new(Utc::now(), hash_activity("aggregated"), test_secret(50 + i), [0u8; 32]);
+                let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+                (proof, public_activity.public_inputs().unwrap().to_vec())
+            })
+            .unzip();
+        (verifier, proofs, public_inputs)
+    }
+
+    #[test]
+    fn test_aggregate_of_eight_proofs_verifies() {
+        let (verifier, proofs, public_inputs) = eight_proofs();
+        let srs = AggregationSrs::<Bn254>::setup(8, &mut seeded_rng());
+        let key = srs.verifier_key();
+
+        let aggregate = srs.aggregate(&proofs, &public_inputs).unwrap();
+        assert_eq!(aggregate.rounds.len(), 3);
+        assert!(verifier.verify_aggregate(&key, &aggregate, &public_inputs).unwrap());
+
+        // 公開入力の並びを入れ替えたり、1つを書き換えたりすると通らない
+        let mut swapped = public_inputs.clone();
+        swapped.swap(0, 1);
+        assert!(!verifier.verify_aggregate(&key, &aggregate, &swapped).unwrap());
+        let mut tampered = public_inputs.clone();
+        tampered[5][0] += Fr::from(1u64);
+        assert!(!verifier.verify_aggregate(&key, &aggregate, &tampered).unwrap());
+
+        // 半分の数の公開入力では検証しない
+        assert!(!verifier.verify_aggregate(&key, &aggregate, &public_inputs[..4]).unwrap());
+        assert!(matches!(
+            verifier.verify_aggregate(&key, &aggregate, &public_inputs[..3]),
+            Err(ZkError::AggregationSize(3))
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_round_trips_through_bytes() {
+        let (verifier, proofs, public_inputs) = eight_proofs();
+        let srs = AggregationSrs::<Bn254>::setup(8, &mut seeded_rng());
+        let aggregate = srs.aggregate(&proofs, &public_inputs).unwrap();
+
+        // 書き出したバイト列から読み戻した集約は、元と一致して検証を通る
+        for compress in [Compress::Yes, Compress::No] {
+            let mut bytes = Vec::new();
+            aggregate.serialize_with_mode(&mut bytes, compress).unwrap();
+            assert_eq!(bytes.len(), aggregate.serialized_size(compress));
+            let read = |bytes: &[u8]| AggregateProof::<Bn254>::deserialize_with_mode(bytes, compress, Validate::Yes);
+            let restored = read(&bytes).unwrap();
+            assert_eq!(restored, aggregate);
+            assert!(verifier.verify_aggregate(&srs.verifier_key(), &restored, &public_inputs).unwrap());
+            assert!(read(&bytes[..bytes.len() - 1]).is_err());
+        }
+
+        // ラウンド数を書き換えた巨大な長さは、領域を確保する前に拒否する
+        let mut bytes = Vec::new();
+        aggregate.serialize_compressed(&mut bytes).unwrap();
+        let offset = (aggregate.com_ab, aggregate.com_c, aggregate.ip_ab, aggregate.agg_c).compressed_size();
+        assert_eq!(bytes[offset..offset + 8], 3u64.to_le_bytes());
+        bytes[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            AggregateProof::<Bn254>::deserialize_compressed(&bytes[..]),
+            Err(SerializationError::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_with_one_invalid_proof_fails() {
+        let (verifier, mut proofs, public_inputs) = eight_proofs();
+        let srs = AggregationSrs::<Bn254>::setup(8, &mut seeded_rng());
+
+        // 3番目の証明を別の行動の証明に取り替えると、集約自体はできても検証を通らない
+        proofs[3] = proofs[4].clone();
+        assert!(!verifier.verify_proof(&proofs[3], &public_inputs[3]).unwrap());
+        let aggregate = srs.aggregate(&proofs, &public_inputs).unwrap();
+        assert!(!verifier.verify_aggregate(&srs.verifier_key(), &aggregate, &public_inputs).unwrap());
+
+        // 2のべき乗でない数や、SRSの大きさを超える数は集約できない
+        assert!(matches!(srs.aggregate(&proofs[..6], &public_inputs[..6]), Err(ZkError::AggregationSize(6))));
+        let small = AggregationSrs::<Bn254>::setup(4, &mut seeded_rng());
+        assert!(matches!(small.aggregate(&proofs, &public_inputs), Err(ZkError::AggregationSize(8))));
+    }
+
+    #[test]
+    fn test_key_polynomials_match_folded_keys() {
+        // SRSの鍵を実際に畳み込んだ結果と、多項式の評価が一致する
+        let rng = &mut seeded_rng();
+        let (n, alpha, r_inv) = (8, Fr::rand(rng), Fr::rand(rng));
+        let challenges = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+        let (mut v, mut w) = (powers_of(alpha, n), powers_of(alpha, 2 * n).split_off(n));
+        w = w.iter().zip(powers_of(r_inv, n)).map(|(w, r)| *w * r).collect();
+        for x in challenges {
+            let k = v.len() / 2;
+            v = (0..k).map(|i| v[i] + x.inverse().unwrap() * v[i + k]).collect();
+            w = (0..k).map(|i| w[i] + x * w[i + k]).collect();
+        }
+        let (f_v, f_w) = key_polynomials(n, &challenges, r_inv);
+        assert_eq!(evaluate(&f_v, alpha), v[0]);
+        assert_eq!(evaluate(&f_w, alpha), w[0]);
+
+        // 商と余りから元の多項式に戻る
+        let z = Fr::rand(rng);
+        let quotient = divide_by_linear(&f_w, z);
+        assert_eq!(evaluate(&quotient, alpha) * (alpha - z) + evaluate(&f_w, z), evaluate(&f_w, alpha));
+    }
+}
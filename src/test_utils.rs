@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use ark_bn254::Bn254;
+use ark_groth16::{prepare_verifying_key, ProvingKey};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{ActivityProver, ActivityVerifier, Setup};
+
+// テストを再現可能にするための固定シードRNG
+pub(crate) fn seeded_rng() -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(42)
+}
+
+// 既定の回路の鍵はテスト全体で一度だけ生成し、呼び出しごとに新しい証明者と検証者に包んで返す
+// （検証者のキャッシュなどの状態はテストの間で共有しない）。seeded_rngとは別のシードを使うので、
+// テストの中でseeded_rngからセットアップした鍵とは異なる鍵になる
+pub(crate) fn shared_test_setup() -> (ActivityProver, ActivityVerifier) {
+    static PROVING_KEY: OnceLock<Arc<ProvingKey<Bn254>>> = OnceLock::new();
+    let proving_key = PROVING_KEY.get_or_init(|| {
+        let (prover, _) = Setup::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(7));
+        prover.proving_key
+    });
+    (
+        ActivityProver::from_shared_proving_key(proving_key.clone()),
+        ActivityVerifier::from_prepared_key(prepare_verifying_key(&proving_key.vk)),
+    )
+}
+
+// 共有の鍵を使う検証者だけが必要なテスト用
+pub(crate) fn shared_test_verifier() -> ActivityVerifier {
+    shared_test_setup().1
+}
+
+// テストごとに衝突しない一時ディレクトリ
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zk_sample_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// nを並べた秘密値やソルト（先頭を0にして、コミットメントの部分群の位数未満にする）
+pub(crate) fn test_secret(n: u8) -> [u8; 32] {
+    let mut secret = [n; 32];
+    secret[0] = 0;
+    secret
+}
@@ -0,0 +1,2402 @@
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ark_bls12_381::Bls12_381;
+use ark_bn254::{Bn254, Fr};
+use ark_ec::pairing::Pairing;
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, VerifyingKey};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalSerialize, Compress};
+use ark_std::UniformRand;
+use chrono::{DateTime, Duration, Utc};
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
+
+use crate::activity::{
+    check_not_future, checked_sub, hash_to_hex, is_canonical_be, HashField, PublicActivity, PublicInputVersion,
+    TimestampField, TypedPublicInputs, UserId,
+};
+use crate::block::{BlockClock, PublicBlockActivity, DEFAULT_BLOCK_WINDOW};
+#[cfg(feature = "std")]
+use crate::cache::{cache_key, VerificationCache};
+use crate::circuit::NUM_PUBLIC_INPUTS;
+#[cfg(feature = "std")]
+use crate::clock::{Clock, SystemClock};
+use crate::core_verify::core_verify;
+use crate::envelope::{circuit_id, ProofEnvelope};
+use crate::epoch::{EpochConfig, EpochIndex};
+use crate::error::ZkError;
+use crate::merkle::Allowlist;
+use crate::pedersen::PedersenField;
+use crate::serialization::{read_key_whole, read_verifying_key};
+#[cfg(feature = "std")]
+use crate::setup::{read_key, write_key};
+use crate::setup::SetupError;
+use crate::snarkjs::{g1_json, g2_json};
+use crate::wire::{datetime_from_unix, field_to_hex};
+
+// 行動の検証結果（失敗した理由を呼び出し側で区別できるようにする）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport<F: PrimeField = Fr> {
+    // 証明が公開入力に対して正しいか
+    pub proof_valid: bool,
+    // タイムスタンプが鮮度の期間内か
+    pub timestamp_fresh: bool,
+    // タイムスタンプが検証者の時計から見て許容誤差を超えて未来でないか
+    pub timestamp_not_future: bool,
+    // 証明に束縛された下限が、検証者の時計から見て許容誤差の範囲内か
+    pub cutoff_accepted: bool,
+    // 証明に束縛された有効期限を、検証者の時計から見て許容誤差を超えて過ぎていないか
+    pub proof_not_expired: bool,
+    // すべての検査に通った場合のみ、二重計上の判定に使うnullifier
+    pub nullifier: Option<F>,
+    // エポックを使う検証者で、すべての検査に通った場合のみ、証明に束縛されたエポックの番号
+    pub epoch: Option<EpochIndex>,
+    // 検証した時刻
+    pub checked_at: DateTime<Utc>,
+    // 実際に検証に使った公開入力（監査ログ用）
+    pub context: VerificationContext<F>,
+}
+
+// 検証に使った公開入力と、その人が読める形（監査ログにそのまま書き出せるよう、値を導き直さずに残す）
+// 時刻はRFC3339、行動ハッシュは公開部分の32バイト（体の元に簡約する前の値）、他の体の元は32バイトのビッグエンディアンの
+// 16進文字列。エポックや許可リストの番号・根は読める形には含まれず、public_inputsにだけ現れる
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationContext<F: PrimeField = Fr> {
+    pub public_inputs: Vec<F>,
+    pub timestamp: String,
+    pub activity_hash: String,
+    pub commitment: String,
+    pub cutoff: String,
+    pub nullifier: String,
+    pub challenge: String,
+    pub user_id: String,
+    // Unix秒の10進文字列（期限なしはNO_EXPIRYの値）
+    pub expiry: String,
+    // 検証した時刻（報告のchecked_atと同じ）
+    pub checked_at: DateTime<Utc>,
+}
+
+impl<F: PrimeField> VerificationContext<F> {
+    fn new(activity: &PublicActivity<F>, public_inputs: Vec<F>, checked_at: DateTime<Utc>) -> Self {
+        Self {
+            timestamp: activity.timestamp.to_rfc3339(),
+            activity_hash: hash_to_hex(&activity.activity_hash),
+            commitment: field_to_hex(&activity.commitment),
+            cutoff: activity.cutoff.to_rfc3339(),
+            nullifier: field_to_hex(&activity.nullifier),
+            challenge: field_to_hex(&activity.challenge),
+            user_id: field_to_hex(&activity.user_id),
+            expiry: format!("{}", activity.expiry),
+            public_inputs,
+            checked_at,
+        }
+    }
+}
+
+impl<F: PrimeField> VerificationReport<F> {
+    pub fn is_valid(&self) -> bool {
+        self.proof_valid
+            && self.timestamp_fresh
+            && self.timestamp_not_future
+            && self.cutoff_accepted
+            && self.proof_not_expired
+    }
+
+    // 成功ならnullifier、失敗なら理由に応じたエラーに変換する（証明の偽造を優先して報告）
+    pub fn into_result(self) -> Result<F, ZkError> {
+        match self.nullifier {
+            Some(nullifier) => Ok(nullifier),
+            None if !self.proof_valid => Err(ZkError::VerificationFailed),
+            None if !self.timestamp_fresh => Err(ZkError::StaleTimestamp),
+            None if !self.timestamp_not_future => Err(ZkError::FutureTimestamp),
+            None if !self.cutoff_accepted => Err(ZkError::StaleCutoff),
+            None => Err(ZkError::ExpiredProof),
+        }
+    }
+}
+
+// 行動を新しいとみなす期間のデフォルト値
+pub const DEFAULT_FRESHNESS_WINDOW: Duration = Duration::days(30);
+
+// 証明を生成してから検証するまでの時間や時計のずれとして許す幅のデフォルト値
+pub const DEFAULT_CLOCK_TOLERANCE: Duration = Duration::minutes(10);
+
+// 検証システムの実装（検証キーのみを保持する。Eはペアリング曲線、デフォルトはBN254）
+pub struct ActivityVerifier<E: Pairing = Bn254> {
+    pub(crate) verifying_key: ark_groth16::PreparedVerifyingKey<E>,
+    freshness_window: Duration,
+    clock_tolerance: Duration,
+    block_window: u64,
+    allowlist_root: Option<E::ScalarField>,
+    epoch: Option<EpochConfig>,
+    minimum_input_version: PublicInputVersion,
+    canonical_hashes: bool,
+    day_granularity: bool,
+    range: bool,
+    // 同じ証明を繰り返し検証するときに、ペアリングの結果を使い回すためのキャッシュ
+    #[cfg(feature = "std")]
+    pub(crate) cache: Option<Mutex<VerificationCache>>,
+    // 時刻を渡さない検証で「今」とみなす時刻の取得元
+    #[cfg(feature = "std")]
+    clock: Box<dyn Clock>,
+}
+
+// 曲線ごとの検証者
+pub type Bn254Verifier = ActivityVerifier<Bn254>;
+pub type Bls12Verifier = ActivityVerifier<Bls12_381>;
+
+// 公開部分の時刻についての検査結果（証明の検証とは別に、検証者の時計で判定する）
+struct TimestampChecks {
+    timestamp_fresh: bool,
+    timestamp_not_future: bool,
+    cutoff_accepted: bool,
+    proof_not_expired: bool,
+}
+
+impl TimestampChecks {
+    fn accepted(&self) -> bool {
+        self.timestamp_fresh && self.timestamp_not_future && self.cutoff_accepted && self.proof_not_expired
+    }
+}
+
+// 時刻の検査の結果を判定に直す（時刻の計算が範囲を超えた場合だけは判定できないのでErrのまま返す）
+fn accepted(check: Result<(), ZkError>) -> Result<bool, ZkError> {
+    match check {
+        Ok(()) => Ok(true),
+        Err(ZkError::TimeArithmetic) => Err(ZkError::TimeArithmetic),
+        Err(_) => Ok(false),
+    }
+}
+
+// 利用者ごとにまとめて検証する項目（利用者のID, 公開部分, 証明）
+type UserProof<E> = (UserId, PublicActivity<<E as Pairing>::ScalarField>, ark_groth16::Proof<E>);
+
+impl<E: Pairing> ActivityVerifier<E> {
+    // 検証キーから検証者を作成（鮮度の期間と時計の許容誤差はデフォルト値）
+    pub(crate) fn from_prepared_key(verifying_key: ark_groth16::PreparedVerifyingKey<E>) -> Self {
+        Self {
+            verifying_key,
+            freshness_window: DEFAULT_FRESHNESS_WINDOW,
+            clock_tolerance: DEFAULT_CLOCK_TOLERANCE,
+            block_window: DEFAULT_BLOCK_WINDOW,
+            allowlist_root: None,
+            epoch: None,
+            minimum_input_version: PublicInputVersion::LATEST,
+            canonical_hashes: false,
+            day_granularity: false,
+            range: false,
+            #[cfg(feature = "std")]
+            cache: None,
+            #[cfg(feature = "std")]
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    // 行動を新しいとみなす期間を変更する
+    pub fn with_freshness_window(mut self, freshness_window: Duration) -> Self {
+        self.freshness_window = freshness_window;
+        self
+    }
+
+    // 現在設定されている鮮度の期間
+    pub fn freshness_window(&self) -> Duration {
+        self.freshness_window
+    }
+
+    // 鮮度の期間を日単位で数える（受理する最も古い時刻を、期間を引いた時刻の日の0時（UTC）にする）
+    // 同じ日のうちは受理する範囲が変わらず、「過去30日」がちょうど30日前の行動を含むかが検証した時刻で変わらない。
+    // 範囲はUTCの日付が変わった時点で翌日に進む（許容誤差は未来の時刻と有効期限の判定にだけ使う）
+    pub fn with_day_granularity(mut self) -> Self {
+        self.day_granularity = true;
+        self
+    }
+
+    // 時計の許容誤差を変更する（0にすると証明した瞬間の下限しか受理せず、少しでも未来のタイムスタンプは拒否する）
+    pub fn with_clock_tolerance(mut self, clock_tolerance: Duration) -> Self {
+        self.clock_tolerance = clock_tolerance;
+        self
+    }
+
+    // 現在設定されている時計の許容誤差
+    pub fn clock_tolerance(&self) -> Duration {
+        self.clock_tolerance
+    }
+
+    // ブロック高で検証するときの鮮度の期間（ブロック数）を変更する
+    pub fn with_block_window(mut self, window_blocks: u64) -> Self {
+        self.block_window = window_blocks;
+        self
+    }
+
+    // 現在設定されているブロック数の期間
+    pub fn block_window(&self) -> u64 {
+        self.block_window
+    }
+
+    // 証明と公開入力ごとに、ペアリングによる検証結果を最大capacity件まで覚える（古いものから捨てるLRU）
+    // 覚えるのはverify_proofの結果だけで、鮮度と下限は呼び出しのたびに検証者の時計で判定し直す
+    #[cfg(feature = "std")]
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Mutex::new(VerificationCache::new(capacity)));
+        self
+    }
+
+    // キャッシュが覚えている結果の数（キャッシュを使わない場合は0）
+    #[cfg(feature = "std")]
+    pub fn cached_results(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| lock_cache(cache).len())
+    }
+
+    // verify_activity_versionedで受け付ける最も古い形式（デフォルトは最新の形式だけ）
+    // 古い形式を受け付けると、その形式が持たないチャレンジや利用者への束縛なしの証明も受理することになる
+    pub fn with_minimum_input_version(mut self, version: PublicInputVersion) -> Self {
+        self.minimum_input_version = version;
+        self
+    }
+
+    // 体の位数以上の行動ハッシュ（体の元にすると簡約され、同じ公開入力になる別のバイト列がある）をNonCanonicalFieldで拒否する
+    // SHA256の出力の約8割は簡約されるので、行動ハッシュを位数未満に揃えて発行する運用でだけ使う。
+    // 行動の原像そのものを回路で確かめたい場合は、Setup::generate_with_preimage_lengthの鍵を使う
+    pub fn with_canonical_hashes(mut self) -> Self {
+        self.canonical_hashes = true;
+        self
+    }
+
+    // 時刻を渡さない検証が使う時計を差し替える（デフォルトはOSの時計）
+    #[cfg(feature = "std")]
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    // 正確な時刻の代わりにエポックの番号だけを受け取る（証明者と鍵も同じ設定であること）
+    // 公開部分のタイムスタンプはエポックの開始時刻として扱い、鮮度はエポックの最後の時刻で判定する
+    pub fn with_epoch(mut self, config: EpochConfig) -> Self {
+        self.epoch = Some(config);
+        self
+    }
+
+    // 範囲付きの鍵の証明をverify_in_rangeで検証する（鍵もSetup::generate_with_rangeで生成したもの）
+    // この設定ではverify_in_range以外の行動の検証はRangeMismatchになり、設定がなければverify_in_rangeは使えない
+    pub fn with_range(mut self) -> Self {
+        self.range = true;
+        self
+    }
+
+    // 前処理する前の検証キー（Solidityの検証コントラクトの生成など、外部のツールに渡す用）
+    pub fn verifying_key(&self) -> &VerifyingKey<E> {
+        &self.verifying_key.vk
+    }
+
+    // 新しいセレモニーの検証キーに差し替える（鮮度の期間などの設定はそのまま）
+    // 前処理を終えてから1回の代入で入れ替え、公開入力の数が合わなければ古い鍵のまま残す。
+    // &mut selfを取るので、検証中（&selfを借用中）に入れ替わることはなく、どの検証も古い鍵か新しい鍵の一方だけを使う
+    // （スレッド間で共有する検証者を差し替える場合は、RwLockなどで包んで書き込みロックを取ってから呼ぶ）
+    pub fn rotate_vk(&mut self, vk: VerifyingKey<E>) -> Result<(), SetupError> {
+        check_public_input_count(&vk)?;
+        self.verifying_key = prepare_verifying_key(&vk);
+        // 古い鍵での結果は新しい鍵には当てはまらない
+        #[cfg(feature = "std")]
+        if let Some(cache) = &mut self.cache {
+            cache.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        Ok(())
+    }
+
+    // 検証キーをファイルに保存
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_key(&self.verifying_key.vk, path)
+    }
+
+    // 保存済みの検証キーを読み込む
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SetupError> {
+        Ok(Self::from_prepared_key(prepare_verifying_key(&read_key(path)?)))
+    }
+
+    // セレモニーなど外部で生成された検証キー（arkworksの圧縮・非圧縮どちらの形式でもよい）から検証者を作成する
+    // 点が曲線上・部分群内にあることを確認し、公開入力の数がActivityCircuitと一致しなければ拒否する
+    pub fn from_vk_bytes(bytes: &[u8]) -> Result<Self, SetupError> {
+        let vk = read_key_whole(bytes, Compress::No, read_verifying_key::<E>)
+            .or_else(|_| read_key_whole(bytes, Compress::Yes, read_verifying_key::<E>))?;
+
+        check_public_input_count(&vk)?;
+        Ok(Self::from_prepared_key(prepare_verifying_key(&vk)))
+    }
+
+    // 証明の検証（Ok(false)は正しく検証した結果の不正、Errは検証処理自体の失敗）
+    pub fn verify_proof(
+        &self,
+        proof: &ark_groth16::Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<bool, ZkError> {
+        self.check_arity(public_inputs)?;
+        #[cfg(feature = "tracing")]
+        let (_span, started) = (tracing::debug_span!("verify_proof").entered(), std::time::Instant::now());
+
+        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+        let (valid, cached) = self.verify_proof_with_cache(proof, public_inputs)?;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            valid,
+            cached,
+            elapsed_us = started.elapsed().as_micros() as u64,
+            "verified proof"
+        );
+        Ok(valid)
+    }
+
+    // 鍵を入れ替える猶予期間向けに、受け入れる検証キーの一覧の先頭から順に検証し、最初に受理した鍵の添字を返す
+    #[cfg(feature = "std")]
+    pub fn verify_any(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+        vks: &[ark_groth16::PreparedVerifyingKey<E>],
+    ) -> Result<Option<usize>, ZkError> {
+        self.verify_any_with_clock(activity, proof, vks, self.clock.now())
+    }
+
+    // 検証者の時計を指定して、一覧の鍵で順に検証する
+    // タイムスタンプ・下限・有効期限や許可リストなどはverify_activityと同じ判定をし、受理できなければどの鍵も試さずにNone。
+    // 自分の検証キーとキャッシュは使わない。公開入力の数が合わない鍵や検証処理の失敗は、その鍵では通らなかったとみなす
+    pub fn verify_any_with_clock(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+        vks: &[ark_groth16::PreparedVerifyingKey<E>],
+        now: DateTime<Utc>,
+    ) -> Result<Option<usize>, ZkError> {
+        let (public_inputs, checks) = self.check_public_activity(activity, now)?;
+        if !checks.accepted() {
+            return Ok(None);
+        }
+        let public_inputs = public_inputs.to_array();
+        Ok(vks.iter().position(|vk| {
+            num_public_inputs(vk) == public_inputs.len()
+                && Groth16::<E>::verify_proof(vk, proof, &public_inputs).unwrap_or(false)
+        }))
+    }
+
+    // キャッシュがあれば覚えた結果を使い、(検証結果, キャッシュの結果か)を返す
+    fn verify_proof_with_cache(
+        &self,
+        proof: &ark_groth16::Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<(bool, bool), ZkError> {
+        #[cfg(feature = "std")]
+        if let Some(cache) = &self.cache {
+            // ペアリングの計算中はロックを放し、他のスレッドの検証を待たせない
+            let key = cache_key(proof, public_inputs);
+            if let Some(valid) = lock_cache(cache).get(&key) {
+                return Ok((valid, true));
+            }
+            let valid = self.verify_proof_slice(proof, public_inputs)?;
+            lock_cache(cache).insert(key, valid);
+            return Ok((valid, false));
+        }
+        Ok((self.verify_proof_slice(proof, public_inputs)?, false))
+    }
+
+    // 借りた公開入力で、このクレートの中では何も確保せずに検証する（キャッシュはキーの計算で確保するので使わない）
+    // 公開入力はTypedPublicInputs::to_arrayの配列をそのまま渡せる。arkworksのペアリングの計算は内部で確保する
+    pub fn verify_proof_slice(
+        &self,
+        proof: &ark_groth16::Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<bool, ZkError> {
+        self.check_arity(public_inputs)?;
+        Ok(core_verify(&self.verifying_key, proof, public_inputs))
+    }
+
+    // 検証結果をsubtle::Choiceで返す（結果による分岐や早期リターンを呼び出し側に見せない）
+    // ペアリングの計算自体は定数時間ではない。定数時間にしているのは最後の e(α, β) との比較だけで、
+    // 公開入力の数が合わないなどのエラーは秘密に依存しないのでErrとして返す
+    pub fn verify_proof_ct(
+        &self,
+        proof: &ark_groth16::Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<Choice, ZkError> {
+        self.check_arity(public_inputs)?;
+        let pvk = &self.verifying_key;
+        let prepared_inputs = Groth16::<E>::prepare_inputs(pvk, public_inputs)?;
+
+        let qap = E::multi_miller_loop(
+            [proof.a, prepared_inputs.into_affine(), proof.c],
+            [
+                E::G2Prepared::from(proof.b),
+                pvk.gamma_g2_neg_pc.clone(),
+                pvk.delta_g2_neg_pc.clone(),
+            ],
+        );
+        let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+        // 体の元を固定長のバイト列にしてから定数時間で比較する
+        let mut actual = Vec::with_capacity(test.0.uncompressed_size());
+        let mut expected = Vec::with_capacity(pvk.alpha_g1_beta_g2.uncompressed_size());
+        test.0.serialize_uncompressed(&mut actual)?;
+        pvk.alpha_g1_beta_g2.serialize_uncompressed(&mut expected)?;
+        Ok(actual.ct_eq(&expected))
+    }
+
+    // 複数の証明をまとめて検証し、証明ごとの結果を返す
+    // まずランダムな線形結合で一括検証し、失敗した場合のみ個別に検証して不正な証明を特定する
+    #[cfg(feature = "std")]
+    pub fn verify_batch(&self, items: &[(ark_groth16::Proof<E>, Vec<E::ScalarField>)]) -> Vec<bool> {
+        self.verify_batch_with_rng(items, &mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGで一括検証の係数を選ぶ
+    pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        items: &[(ark_groth16::Proof<E>, Vec<E::ScalarField>)],
+        rng: &mut R,
+    ) -> Vec<bool> {
+        if items.is_empty() {
+            return Vec::new();
+        }
+
+        if self.verify_combined(items, rng).unwrap_or(false) {
+            return vec![true; items.len()];
+        }
+
+        items
+            .iter()
+            .map(|(proof, public_inputs)| self.verify_proof(proof, public_inputs).unwrap_or(false))
+            .collect()
+    }
+
+    // 各証明の検証式 e(A, B) = e(α, β)・e(IC, γ)・e(C, δ) を乱数 r_i で重み付けして1つにまとめる
+    // Π e(r_i A_i, B_i)・e(-Σ r_i IC_i, γ)・e(-Σ r_i C_i, δ) = e(α, β)^(Σ r_i)
+    fn verify_combined<R: RngCore + CryptoRng>(
+        &self,
+        items: &[(ark_groth16::Proof<E>, Vec<E::ScalarField>)],
+        rng: &mut R,
+    ) -> Result<bool, ZkError> {
+        let pvk = &self.verifying_key;
+
+        let mut g1 = Vec::with_capacity(items.len() + 2);
+        let mut g2 = Vec::with_capacity(items.len() + 2);
+        let mut inputs_acc = E::G1::zero();
+        let mut c_acc = E::G1::zero();
+        let mut r_sum = E::ScalarField::zero();
+
+        for (proof, public_inputs) in items {
+            self.check_arity(public_inputs)?;
+            let r = E::ScalarField::rand(rng);
+            let prepared_inputs = Groth16::<E>::prepare_inputs(pvk, public_inputs)?;
+
+            g1.push((proof.a * r).into_affine());
+            g2.push(E::G2Prepared::from(proof.b));
+            inputs_acc += prepared_inputs * r;
+            c_acc += proof.c * r;
+            r_sum += r;
+        }
+
+        // γ と δ のペアリングは全証明で共通なので1回ずつで済む
+        g1.push(inputs_acc.into_affine());
+        g2.push(pvk.gamma_g2_neg_pc.clone());
+        g1.push(c_acc.into_affine());
+        g2.push(pvk.delta_g2_neg_pc.clone());
+
+        let qap = E::multi_miller_loop(g1, g2);
+        let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+        Ok(test.0 == pvk.alpha_g1_beta_g2.pow(r_sum.into_bigint()))
+    }
+
+    // 公開入力の数が検証キーの束縛する公開入力の数と一致することを、ペアリングの計算の前に確認する
+    // 鍵を読み込む経路（load・from_vk_bytes・rotate_vk）は、この数がPublicInputLayoutの値の数であることを確かめている
+    pub(crate) fn check_arity(&self, public_inputs: &[E::ScalarField]) -> Result<(), ZkError> {
+        let expected = num_public_inputs(&self.verifying_key);
+        if public_inputs.len() != expected {
+            return Err(ZkError::PublicInputArity {
+                expected,
+                got: public_inputs.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // 行動の検証（メインの検証ロジック）。どの検査で失敗したかを報告にまとめて返す
+    // Errになるのは公開入力が作れない・検証処理自体が失敗したなど、判定ができない場合だけ
+    #[cfg(feature = "std")]
+    pub fn verify_activity(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+    ) -> Result<VerificationReport<E::ScalarField>, ZkError> {
+        self.verify_activity_with_clock(activity, proof, self.clock.now())
+    }
+
+    // 検証者の時計を指定して行動を検証する（テストや、信頼できる時刻源を使う場合向け）
+    // 下限は証明者が決めて証明に束縛した値なので、検証者は自分の時計でその下限が許容できるかを判定する
+    pub fn verify_activity_with_clock(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+        now: DateTime<Utc>,
+    ) -> Result<VerificationReport<E::ScalarField>, ZkError> {
+        let checked_at = now;
+        // 検証には配列を借りて渡し、報告に残す分だけVecにする
+        let (public_inputs, checks) = self.check_public_activity(activity, checked_at)?;
+        let timestamp_slot = public_inputs.timestamp;
+        let public_inputs = public_inputs.to_array();
+        let proof_valid = self.verify_proof(proof, &public_inputs)?;
+        let accepted = proof_valid && checks.accepted();
+
+        Ok(VerificationReport {
+            proof_valid,
+            timestamp_fresh: checks.timestamp_fresh,
+            timestamp_not_future: checks.timestamp_not_future,
+            cutoff_accepted: checks.cutoff_accepted,
+            proof_not_expired: checks.proof_not_expired,
+            nullifier: accepted.then_some(activity.nullifier),
+            // 公開部分の時刻から導き直さず、検証に通った公開入力の値を読む
+            epoch: self
+                .epoch
+                .filter(|_| accepted)
+                .and_then(|_| EpochIndex::from_field(timestamp_slot.into_field())),
+            checked_at,
+            context: VerificationContext::new(activity, public_inputs.to_vec(), checked_at),
+        })
+    }
+
+    // 公開部分から公開入力を組み立て、タイムスタンプが新しいか・未来でないか・下限を受理できるか・期限内かを判定する
+    // 受理できない場合も公開入力は返し、証明の検証は続けて偽造と区別できるようにする
+    fn check_public_activity(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        now: DateTime<Utc>,
+    ) -> Result<(TypedPublicInputs<E::ScalarField>, TimestampChecks), ZkError> {
+        // 範囲付きの鍵はverify_in_rangeでしか検証できない
+        if self.range {
+            return Err(ZkError::RangeMismatch);
+        }
+
+        // エポックを使う場合は、公開部分のタイムスタンプをエポックの番号に読み替える
+        let timestamp_slot = match &self.epoch {
+            Some(config) => TimestampField::from_field(E::ScalarField::from(config.window(activity.timestamp)?.index)),
+            None => TimestampField::from_datetime(activity.timestamp)?,
+        };
+
+        let checks = self.timestamp_checks(activity.timestamp, activity.cutoff, activity.expiry, now)?;
+
+        // 許可リストを使う場合、公開部分の行動ハッシュは使わないので確かめない
+        let hash_is_reduced = !is_canonical_be::<E::ScalarField>(&activity.activity_hash);
+        if self.canonical_hashes && self.allowlist_root.is_none() && hash_is_reduced {
+            return Err(ZkError::NonCanonicalField);
+        }
+
+        // 許可リストを使う場合は行動ハッシュの代わりに根を入れる
+        let public_inputs = activity.public_inputs_with(timestamp_slot, self.hash_slot(&activity.activity_hash))?;
+        Ok((public_inputs, checks))
+    }
+
+    // 検証キーから計算した回路のID
+    pub fn circuit_id(&self) -> [u8; 32] {
+        circuit_id(&self.verifying_key.vk)
+    }
+
+    // 封筒に入った証明を、現在時刻で検証する（回路のIDが自分の検証キーと違えばCircuitMismatch）
+    #[cfg(feature = "std")]
+    pub fn verify_envelope(&self, envelope: &ProofEnvelope<E>) -> Result<bool, ZkError> {
+        self.verify_envelope_with_clock(envelope, self.clock.now())
+    }
+
+    // 検証者の時計を指定して封筒を検証する
+    // 許可リストの根は封筒の値ではなく検証者の根を使い、タイムスタンプ・下限・有効期限はverify_activityと同じ判定をする。
+    // 範囲付きの鍵は検証者が範囲を渡すverify_in_rangeでしか検証できないのでRangeMismatch。
+    // チャレンジと利用者のIDは封筒の値を使うので、それらに束縛する場合は公開部分で検証する
+    pub fn verify_envelope_with_clock(&self, envelope: &ProofEnvelope<E>, now: DateTime<Utc>) -> Result<bool, ZkError> {
+        if envelope.circuit_id != self.circuit_id() {
+            return Err(ZkError::CircuitMismatch);
+        }
+        if self.range {
+            return Err(ZkError::RangeMismatch);
+        }
+        self.check_arity(&envelope.public_inputs)?;
+        let mut public_inputs = envelope.public_inputs.clone();
+        if let Some(root) = self.allowlist_root {
+            public_inputs[1] = root;
+        }
+
+        // 時刻として読めない値は回路の範囲チェックを通らないので、受理しない
+        let [timestamp, _, _, cutoff, _, _, _, expiry] = public_inputs[..] else {
+            unreachable!("check_arity fixes the number of public inputs");
+        };
+        let (Some(timestamp), Some(cutoff), Some(expiry)) = (slot_u64(timestamp), slot_u64(cutoff), slot_u64(expiry))
+        else {
+            return Ok(false);
+        };
+        // エポックを使う場合は、タイムスタンプの位置のエポックの番号をそのエポックの開始時刻に戻す
+        let timestamp = match &self.epoch {
+            Some(config) => config.epoch_start(timestamp),
+            None => slot_datetime(timestamp),
+        };
+        let (Ok(timestamp), Ok(cutoff)) = (timestamp, slot_datetime(cutoff)) else {
+            return Ok(false);
+        };
+
+        let checks = self.timestamp_checks(timestamp, cutoff, expiry, now)?;
+        Ok(checks.accepted() && self.verify_proof(&envelope.proof, &public_inputs)?)
+    }
+
+    // 再利用を防ぐためのチャレンジを発行する（証明者はこの値に対して証明し、検証者は同じ値で検証する）
+    #[cfg(feature = "std")]
+    pub fn issue_challenge(&self) -> E::ScalarField {
+        self.issue_challenge_with_rng(&mut OsRng)
+    }
+
+    // 呼び出し側が用意したRNGでチャレンジを発行する
+    pub fn issue_challenge_with_rng<R: RngCore + CryptoRng>(&self, rng: &mut R) -> E::ScalarField {
+        E::ScalarField::rand(rng)
+    }
+
+    // 発行したチャレンジに対する証明として検証する（公開部分のチャレンジではなく検証者が覚えている値を使う）
+    // 別のセッションで作られた証明を使い回しても、チャレンジが異なるので検証を通らない
+    #[cfg(feature = "std")]
+    pub fn verify_activity_for_challenge(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+        challenge: E::ScalarField,
+    ) -> Result<VerificationReport<E::ScalarField>, ZkError> {
+        let activity = PublicActivity {
+            challenge,
+            ..activity.clone()
+        };
+        self.verify_activity(&activity, proof)
+    }
+
+    // クライアントが明示した形式のバージョンで検証する（古い形式が送らないフィールドは既定値で埋める）
+    // バージョンを受け取った値から推測しないので、チャレンジを省いただけで古い形式として扱われることはない
+    #[cfg(feature = "std")]
+    pub fn verify_activity_versioned(
+        &self,
+        version: PublicInputVersion,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+    ) -> Result<VerificationReport<E::ScalarField>, ZkError> {
+        if version < self.minimum_input_version {
+            return Err(ZkError::InputVersionRejected {
+                minimum: self.minimum_input_version as u16,
+                found: version as u16,
+            });
+        }
+        self.verify_activity(&version.fill_defaults(activity), proof)
+    }
+
+    // 指定した利用者の証明として検証する（公開部分のIDではなく検証者が指定したIDを使う）
+    // 別の利用者のIDで作られた証明は、公開入力が異なるので検証を通らない
+    #[cfg(feature = "std")]
+    pub fn verify_for_user(
+        &self,
+        user: UserId,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+    ) -> Result<VerificationReport<E::ScalarField>, ZkError> {
+        let activity = PublicActivity {
+            user_id: user.to_field(),
+            ..activity.clone()
+        };
+        self.verify_activity(&activity, proof)
+    }
+
+    // 複数の利用者の証明をまとめて検証し、入力と同じ順序で結果を返す（verify_batchで一括検証する）
+    // 結果がtrueになるのは、その利用者の証明として正しく、タイムスタンプと下限も受理できる場合だけ
+    #[cfg(feature = "std")]
+    pub fn verify_batch_for_users(
+        &self,
+        items: &[UserProof<E>],
+    ) -> Vec<bool> {
+        self.verify_batch_for_users_with_rng(items, self.clock.now(), &mut OsRng)
+    }
+
+    // 検証者の時計と、一括検証の係数を選ぶRNGを指定してまとめて検証する
+    pub fn verify_batch_for_users_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        items: &[UserProof<E>],
+        now: DateTime<Utc>,
+        rng: &mut R,
+    ) -> Vec<bool> {
+        // 公開入力が作れない項目は一括検証に含めず、その項目だけを不正とする
+        let checked: Vec<_> = items
+            .iter()
+            .map(|(user, activity, proof)| {
+                let activity = PublicActivity {
+                    user_id: user.to_field(),
+                    ..activity.clone()
+                };
+                let (public_inputs, checks) = self.check_public_activity(&activity, now).ok()?;
+                Some((proof.clone(), public_inputs.to_vec(), checks.accepted()))
+            })
+            .collect();
+
+        let batch: Vec<_> = checked
+            .iter()
+            .flatten()
+            .map(|(proof, inputs, _)| (proof.clone(), inputs.clone()))
+            .collect();
+        let mut proofs_valid = self.verify_batch_with_rng(&batch, rng).into_iter();
+        checked
+            .iter()
+            .map(|item| item.as_ref().is_some_and(|(_, _, accepted)| proofs_valid.next() == Some(true) && *accepted))
+            .collect()
+    }
+
+    // (公開部分, 証明)の列を1件ずつ検証し、入力と同じ順序で結果を返す（全件をメモリに集めない）
+    // 行動データには秘密値が含まれるので、受け取るのは検証者に渡してよい公開部分
+    #[cfg(feature = "std")]
+    pub fn verify_stream<'a, I>(
+        &'a self,
+        items: I,
+    ) -> impl Iterator<Item = Result<VerificationReport<E::ScalarField>, ZkError>> + 'a
+    where
+        I: IntoIterator<Item = (PublicActivity<E::ScalarField>, ark_groth16::Proof<E>)>,
+        I::IntoIter: 'a,
+    {
+        items
+            .into_iter()
+            .map(move |(activity, proof)| self.verify_activity(&activity, &proof))
+    }
+
+    // 行動が [start, end] の範囲に含まれることの証明を検証する（鍵はSetup::generate_with_rangeで生成したもの）
+    // 範囲は検証者が決めて渡すので、公開部分のタイムスタンプと下限は使わない。鮮度の検査もしない
+    pub fn verify_in_range(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<bool, ZkError> {
+        if !self.range {
+            return Err(ZkError::RangeMismatch);
+        }
+        if start > end {
+            return Err(ZkError::InvalidRange);
+        }
+        let activity = PublicActivity {
+            cutoff: start,
+            ..activity.clone()
+        };
+        let public_inputs = activity
+            .public_inputs_with(TimestampField::from_datetime(end)?, self.hash_slot(&activity.activity_hash))?
+            .to_vec();
+        self.verify_proof(proof, &public_inputs)
+    }
+
+    // ブロック高で鮮度を示す証明を、検証者のブロック高の時計で検証する（成功ならnullifierを返す）
+    // 失敗の理由はVerificationReport::into_resultと同じ順に、証明の偽造・古い行動・未来の行動・古い下限で報告する。
+    // ブロック高はオンチェーンで合意された値なので、時刻の場合のような許容誤差は設けない
+    pub fn verify_at_block(
+        &self,
+        activity: &PublicBlockActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+        clock: BlockClock,
+    ) -> Result<E::ScalarField, ZkError> {
+        if self.epoch.is_some() {
+            return Err(ZkError::BlockWithEpoch);
+        }
+        if self.range {
+            return Err(ZkError::RangeMismatch);
+        }
+        let public_inputs = activity.public_inputs_with(self.hash_slot(&activity.activity_hash)).to_vec();
+        let earliest = clock.cutoff(self.block_window);
+        if !self.verify_proof(proof, &public_inputs)? {
+            return Err(ZkError::VerificationFailed);
+        }
+        if activity.activity_block < earliest {
+            return Err(ZkError::StaleTimestamp);
+        }
+        if activity.activity_block > clock.current {
+            return Err(ZkError::FutureTimestamp);
+        }
+        if activity.cutoff_block < earliest {
+            return Err(ZkError::StaleCutoff);
+        }
+        Ok(activity.nullifier)
+    }
+
+    // 検証に通ったかどうかだけを返す簡易版
+    // 報告を組み立てず、公開入力も配列のまま借りて渡すので、キャッシュを使わなければこのクレートの中では確保しない。
+    // 時刻の検査で受理できなければ証明の検証を省く
+    #[cfg(feature = "std")]
+    pub fn verify_activity_bool(
+        &self,
+        activity: &PublicActivity<E::ScalarField>,
+        proof: &ark_groth16::Proof<E>,
+    ) -> bool {
+        self.check_public_activity(activity, self.clock.now())
+            .is_ok_and(|(public_inputs, checks)| {
+                checks.accepted() && self.verify_proof(proof, &public_inputs.to_array()).unwrap_or(false)
+            })
+    }
+
+    // 公開入力の2番目の値（許可リストを使う場合は行動ハッシュの代わりに根を入れる）
+    fn hash_slot(&self, activity_hash: &[u8; 32]) -> HashField<E::ScalarField> {
+        match self.allowlist_root {
+            Some(root) => HashField::from_field(root),
+            None => HashField::from_hash(activity_hash),
+        }
+    }
+
+    // 証明を検証する前の絞り込み向けに、タイムスタンプが検証者の時計で新しいかだけを判定する
+    // verify_activityの報告のtimestamp_freshと同じ判定で、エポックを使う場合はエポックの最後の時刻で判定する。
+    // 時刻の計算が範囲を超えるなど判定できない場合はfalse
+    #[cfg(feature = "std")]
+    pub fn is_fresh(&self, timestamp: DateTime<Utc>) -> bool {
+        self.is_fresh_at(timestamp, self.clock.now())
+    }
+
+    // 検証者の時計を指定してタイムスタンプが新しいかを判定する（verify_activity_with_clockと同じ判定）
+    pub fn is_fresh_at(&self, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.fresh_at(timestamp, now).unwrap_or(false)
+    }
+
+    // タイムスタンプが新しいか・未来でないか・下限を受理できるか・期限内かを判定する
+    // 鮮度はis_freshと同じ判定を使う
+    // 未来かどうかはエポックの開始時刻で判定する（最後の時刻は今のエポックでも未来になりうる）
+    fn timestamp_checks(
+        &self,
+        timestamp: DateTime<Utc>,
+        cutoff: DateTime<Utc>,
+        expiry: u64,
+        now: DateTime<Utc>,
+    ) -> Result<TimestampChecks, ZkError> {
+        Ok(TimestampChecks {
+            timestamp_fresh: self.fresh_at(timestamp, now)?,
+            timestamp_not_future: accepted(check_not_future(timestamp, now, self.clock_tolerance))?,
+            cutoff_accepted: accepted(self.check_cutoff(cutoff, now))?,
+            proof_not_expired: accepted(self.check_expiry(expiry, now))?,
+        })
+    }
+
+    // 公開部分のタイムスタンプが新しいか（エポックを使う場合は、エポックの最後の時刻で判定する）
+    fn fresh_at(&self, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> Result<bool, ZkError> {
+        let latest_timestamp = match &self.epoch {
+            Some(config) => {
+                let last = config.window(timestamp)?.last_second();
+                DateTime::from_timestamp(last as i64, 0).ok_or(ZkError::InvalidEpoch)?
+            }
+            None => timestamp,
+        };
+        accepted(self.check_freshness(latest_timestamp, now))
+    }
+
+    // 基準時刻から鮮度の期間と許容誤差を合わせた範囲内であれば新しい行動とみなす（境界ちょうどは受理）
+    fn check_freshness(&self, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), ZkError> {
+        if timestamp < self.earliest_accepted(now)? {
+            return Err(ZkError::StaleTimestamp);
+        }
+        Ok(())
+    }
+
+    // 証明された下限が検証者の期待する下限（now - 期間）より許容誤差を超えて古ければ拒否する
+    // 期待より新しい下限はより厳しい主張なので受理する
+    fn check_cutoff(&self, cutoff: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), ZkError> {
+        if cutoff < self.earliest_accepted(now)? {
+            return Err(ZkError::StaleCutoff);
+        }
+        Ok(())
+    }
+
+    // 有効期限が now - 許容誤差 より前なら拒否する（期限ちょうどは受理）
+    // 時刻として表せない期限（NO_EXPIRYを含む）は期限なしとして扱う
+    fn check_expiry(&self, expiry: u64, now: DateTime<Utc>) -> Result<(), ZkError> {
+        let Ok(expiry) = i64::try_from(expiry) else {
+            return Ok(());
+        };
+        if checked_sub(now, self.clock_tolerance)?.timestamp() > expiry {
+            return Err(ZkError::ExpiredProof);
+        }
+        Ok(())
+    }
+
+    // 受理する最も古い時刻（now - 鮮度の期間 - 許容誤差）
+    // 日単位なら now - 鮮度の期間 をその日の0時に切り捨て、許容誤差は引かない（切り替わりをUTCの日付の境界に揃える）
+    fn earliest_accepted(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, ZkError> {
+        let earliest = checked_sub(now, self.freshness_window)?;
+        if self.day_granularity {
+            return start_of_day(earliest);
+        }
+        checked_sub(earliest, self.clock_tolerance)
+    }
+}
+
+// 公開入力の値を64ビットの整数として読む（収まらない値はNone）
+fn slot_u64<F: PrimeField>(value: F) -> Option<u64> {
+    let value = value.into_bigint();
+    (value.num_bits() <= 64).then(|| value.as_ref()[0])
+}
+
+// 公開入力のUnix秒を時刻にする
+fn slot_datetime(seconds: u64) -> Result<DateTime<Utc>, ZkError> {
+    datetime_from_unix(i64::try_from(seconds).map_err(|_| ZkError::TimeArithmetic)?)
+}
+
+// その日の0時（UTC）
+fn start_of_day(time: DateTime<Utc>) -> Result<DateTime<Utc>, ZkError> {
+    let day = Duration::days(1).num_seconds();
+    DateTime::from_timestamp(time.timestamp().div_euclid(day) * day, 0).ok_or(ZkError::TimeArithmetic)
+}
+
+impl<E: Pairing> ActivityVerifier<E>
+where
+    E::ScalarField: PedersenField,
+{
+    // 許可リストに含まれる行動だけを受理する（証明者も同じ許可リストで証明していること）
+    // 公開部分の行動ハッシュは使わず、どの行動かは検証者にも分からない
+    pub fn with_allowlist(mut self, hashes: &[[u8; 32]]) -> Self {
+        self.allowlist_root = Some(Allowlist::<E::ScalarField>::new(hashes).root());
+        self
+    }
+
+    // 設定をまとめて指定して検証者を組み立てる（組み合わせられない設定はbuildでエラーにする）
+    pub fn builder() -> ActivityVerifierBuilder<E> {
+        ActivityVerifierBuilder::default()
+    }
+}
+
+// ActivityVerifierのビルダー（検証キーは必須、その他は省略するとActivityVerifierのデフォルト値）
+pub struct ActivityVerifierBuilder<E: Pairing = Bn254> {
+    verifying_key: Option<VerifyingKey<E>>,
+    freshness_window: Duration,
+    clock_tolerance: Duration,
+    block_window: Option<u64>,
+    allowlist: Option<Vec<[u8; 32]>>,
+    epoch: Option<EpochConfig>,
+    exact_timestamps: bool,
+    minimum_input_version: PublicInputVersion,
+    canonical_hashes: bool,
+    day_granularity: bool,
+    range: bool,
+    #[cfg(feature = "std")]
+    cache_capacity: Option<usize>,
+    #[cfg(feature = "std")]
+    clock: Option<Box<dyn Clock>>,
+}
+
+impl<E: Pairing> Default for ActivityVerifierBuilder<E> {
+    fn default() -> Self {
+        Self {
+            verifying_key: None,
+            freshness_window: DEFAULT_FRESHNESS_WINDOW,
+            clock_tolerance: DEFAULT_CLOCK_TOLERANCE,
+            block_window: None,
+            allowlist: None,
+            epoch: None,
+            exact_timestamps: false,
+            minimum_input_version: PublicInputVersion::LATEST,
+            canonical_hashes: false,
+            day_granularity: false,
+            range: false,
+            #[cfg(feature = "std")]
+            cache_capacity: None,
+            #[cfg(feature = "std")]
+            clock: None,
+        }
+    }
+}
+
+impl<E: Pairing> ActivityVerifierBuilder<E>
+where
+    E::ScalarField: PedersenField,
+{
+    pub fn verifying_key(mut self, verifying_key: VerifyingKey<E>) -> Self {
+        self.verifying_key = Some(verifying_key);
+        self
+    }
+
+    pub fn freshness_window(mut self, freshness_window: Duration) -> Self {
+        self.freshness_window = freshness_window;
+        self
+    }
+
+    pub fn clock_tolerance(mut self, clock_tolerance: Duration) -> Self {
+        self.clock_tolerance = clock_tolerance;
+        self
+    }
+
+    // ブロック高で検証するときの期間（エポックとは組み合わせられない）
+    pub fn block_window(mut self, window_blocks: u64) -> Self {
+        self.block_window = Some(window_blocks);
+        self
+    }
+
+    pub fn allowlist(mut self, hashes: &[[u8; 32]]) -> Self {
+        self.allowlist = Some(hashes.to_vec());
+        self
+    }
+
+    // 正確な時刻の代わりにエポックの番号で検証する（exact_timestampsとは組み合わせられない）
+    pub fn epoch(mut self, config: EpochConfig) -> Self {
+        self.epoch = Some(config);
+        self
+    }
+
+    // 正確な時刻で検証することを明示する（デフォルトも正確な時刻だが、後からエポックを設定する取り違えを防ぐ）
+    pub fn exact_timestamps(mut self) -> Self {
+        self.exact_timestamps = true;
+        self
+    }
+
+    pub fn minimum_input_version(mut self, version: PublicInputVersion) -> Self {
+        self.minimum_input_version = version;
+        self
+    }
+
+    pub fn canonical_hashes(mut self) -> Self {
+        self.canonical_hashes = true;
+        self
+    }
+
+    pub fn day_granularity(mut self) -> Self {
+        self.day_granularity = true;
+        self
+    }
+
+    // 範囲付きの鍵の証明をverify_in_rangeで検証する（エポックやブロック高とは組み合わせられない）
+    pub fn range(mut self) -> Self {
+        self.range = true;
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub fn cache(mut self, capacity: usize) -> Self {
+        self.cache_capacity = Some(capacity);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    // 設定を検証して組み立てる。検証キーがなければMissingField、公開入力の数が回路と合わない鍵はPublicInputArity、
+    // 組み合わせられない設定はInvalidVerifierConfig
+    pub fn build(self) -> Result<ActivityVerifier<E>, ZkError> {
+        let verifying_key = self.verifying_key.ok_or(ZkError::MissingField("verifying_key"))?;
+        if let Err(SetupError::PublicInputCount { expected, found }) = check_public_input_count(&verifying_key) {
+            return Err(ZkError::PublicInputArity { expected, got: found });
+        }
+        if self.freshness_window < Duration::zero() || self.clock_tolerance < Duration::zero() {
+            return Err(ZkError::InvalidVerifierConfig("durations must not be negative"));
+        }
+        if self.epoch.is_some() && self.exact_timestamps {
+            return Err(ZkError::InvalidVerifierConfig("epoch mode conflicts with exact timestamps"));
+        }
+        if self.epoch.is_some() && self.block_window.is_some() {
+            return Err(ZkError::BlockWithEpoch);
+        }
+        if self.range && self.epoch.is_some() {
+            return Err(ZkError::InvalidVerifierConfig("range mode conflicts with epoch mode"));
+        }
+        if self.range && self.block_window.is_some() {
+            return Err(ZkError::InvalidVerifierConfig("range mode conflicts with block heights"));
+        }
+        if self.allowlist.is_some() && self.canonical_hashes {
+            return Err(ZkError::InvalidVerifierConfig("allowlisted proofs do not reveal the activity hash"));
+        }
+        if let Some(config) = &self.epoch {
+            config.window(config.start)?;
+        }
+
+        let mut verifier = ActivityVerifier::from_prepared_key(prepare_verifying_key(&verifying_key))
+            .with_freshness_window(self.freshness_window)
+            .with_clock_tolerance(self.clock_tolerance)
+            .with_minimum_input_version(self.minimum_input_version);
+        if let Some(window_blocks) = self.block_window {
+            verifier = verifier.with_block_window(window_blocks);
+        }
+        if let Some(hashes) = &self.allowlist {
+            verifier = verifier.with_allowlist(hashes);
+        }
+        if let Some(config) = self.epoch {
+            verifier = verifier.with_epoch(config);
+        }
+        if self.canonical_hashes {
+            verifier = verifier.with_canonical_hashes();
+        }
+        if self.day_granularity {
+            verifier = verifier.with_day_granularity();
+        }
+        if self.range {
+            verifier = verifier.with_range();
+        }
+        #[cfg(feature = "std")]
+        if let Some(capacity) = self.cache_capacity {
+            verifier = verifier.with_cache(capacity);
+        }
+        #[cfg(feature = "std")]
+        if let Some(clock) = self.clock {
+            verifier.clock = clock;
+        }
+        Ok(verifier)
+    }
+}
+
+impl ActivityVerifier<Bn254> {
+    // 検証キーをsnarkjsのverification_key.jsonと同じ形式で書き出す（座標は10進文字列）
+    // snarkjsのSolidityテンプレートやそこから生成した検証コントラクトにそのまま渡せる
+    pub fn export_vk_json(&self) -> String {
+        let vk = self.verifying_key();
+        let ic = vk.gamma_abc_g1.iter().map(g1_json).collect::<Vec<_>>().join(",");
+        format!(
+            concat!(
+                r#"{{"protocol":"groth16","curve":"bn128","nPublic":{},"#,
+                r#""vk_alpha_1":{},"vk_beta_2":{},"vk_gamma_2":{},"vk_delta_2":{},"IC":[{}]}}"#,
+            ),
+            vk.gamma_abc_g1.len() - 1,
+            g1_json(&vk.alpha_g1),
+            g2_json(&vk.beta_g2),
+            g2_json(&vk.gamma_g2),
+            g2_json(&vk.delta_g2),
+            ic,
+        )
+    }
+}
+
+// 前処理していない検証キーで1回だけ検証する（状態を持たない検証サービス向け）
+// 呼び出しのたびに鍵を前処理するので、同じ鍵で何度も検証するならActivityVerifierを使う
+pub fn verify_with_vk(
+    vk: &VerifyingKey<Bn254>,
+    proof: &ark_groth16::Proof<Bn254>,
+    public_inputs: &[Fr],
+) -> Result<bool, ZkError> {
+    Bn254Verifier::from_prepared_key(prepare_verifying_key(vk)).verify_proof(proof, public_inputs)
+}
+
+// 前処理した検証キーが束縛する公開入力の数（gamma_abc_g1は定数項の分だけ1つ多い）
+// 読み込んだ鍵やセレモニーの鍵が、使うつもりの回路と同じ数の公開入力を持つかを突き合わせる用
+pub fn num_public_inputs<E: Pairing>(pvk: &PreparedVerifyingKey<E>) -> usize {
+    pvk.vk.gamma_abc_g1.len().saturating_sub(1)
+}
+
+// 検証キーの公開入力の数がActivityCircuitと一致するか確認する（gamma_abc_g1は定数項の分だけ1つ多い）
+fn check_public_input_count<E: Pairing>(vk: &VerifyingKey<E>) -> Result<(), SetupError> {
+    let found = vk.gamma_abc_g1.len().saturating_sub(1);
+    if found != NUM_PUBLIC_INPUTS {
+        return Err(SetupError::PublicInputCount {
+            expected: NUM_PUBLIC_INPUTS,
+            found,
+        });
+    }
+    Ok(())
+}
+
+// キャッシュのロックを取る（他のスレッドがパニックしても、中身は結果の集まりなのでそのまま使える）
+#[cfg(feature = "std")]
+fn lock_cache(cache: &Mutex<VerificationCache>) -> std::sync::MutexGuard<'_, VerificationCache> {
+    cache.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fq, G1Affine, G2Affine};
+    use chrono::SubsecRound;
+    use core::str::FromStr;
+
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+    use crate::test_utils::{seeded_rng, shared_test_setup, shared_test_verifier, test_secret};
+    use crate::{
+        hash_activity, timestamp_to_field, ActivityCircuit, ActivityData, FixedClock, MerkleActivityCircuit, Setup,
+        NO_EXPIRY,
+    };
+
+    #[test]
+    fn test_recent_activity_verification() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        // 有効な行動データのテスト
+        let valid_activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("valid_activity"),
+            user_secret: [1u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        let (public_activity, proof) = prover.prove_activity_with_rng(&valid_activity, rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        // 1ヶ月以上前の行動データのテスト
+        let old_activity = ActivityData {
+            timestamp: Utc::now() - Duration::days(31),
+            activity_hash: hash_activity("old_activity"),
+            user_secret: [1u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // 回路内の下限チェックにより証明自体が生成できない
+        assert!(matches!(
+            prover.prove_activity_with_rng(&old_activity, rng),
+            Err(ZkError::Unsatisfied)
+        ));
+    }
+
+    #[test]
+    fn test_proof_for_different_activity_is_rejected() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity_a = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("activity_a"),
+            user_secret: [4u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let activity_b = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("activity_b"),
+            user_secret: [4u8; 32],
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+
+        // Aの証明をBの公開情報に対して提示しても受理されない
+        let (_, proof_a) = prover.prove_activity_with_rng(&activity_a, rng).unwrap();
+        let (public_b, proof_b) = prover.prove_activity_with_rng(&activity_b, rng).unwrap();
+
+        assert!(matches!(
+            verifier.verify_activity(&public_b, &proof_a).and_then(VerificationReport::into_result),
+            Err(ZkError::VerificationFailed)
+        ));
+        assert!(verifier.verify_activity_bool(&public_b, &proof_b));
+    }
+
+    #[test]
+    fn test_num_public_inputs_matches_default_circuit() {
+        let verifier = shared_test_verifier();
+        let count = num_public_inputs(&verifier.verifying_key);
+        assert_eq!(count, ActivityCircuit::<Fr>::metrics().public_inputs);
+        assert_eq!(count, NUM_PUBLIC_INPUTS);
+        assert_eq!(num_public_inputs(&prepare_verifying_key(verifier.verifying_key())), count);
+    }
+
+    #[test]
+    fn test_verification_errors_are_distinguishable() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("error_activity"),
+            user_secret: test_secret(9),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 公開入力の数が合わない場合は、期待する数と渡された数を含むエラーになる
+        assert!(matches!(
+            verifier.verify_proof(&proof, &[]),
+            Err(ZkError::PublicInputArity {
+                expected: NUM_PUBLIC_INPUTS,
+                got: 0
+            })
+        ));
+
+        // 公開入力の値が違うだけなら正常に検証した結果のfalse
+        let wrong_inputs = vec![
+            timestamp_to_field::<Fr>(public_activity.timestamp).unwrap() + Fr::from(1u64),
+            Fr::from_be_bytes_mod_order(&public_activity.activity_hash),
+            public_activity.commitment,
+            timestamp_to_field(public_activity.cutoff).unwrap(),
+            public_activity.nullifier,
+            public_activity.challenge,
+            public_activity.user_id,
+            Fr::from(public_activity.expiry),
+        ];
+        assert!(!verifier.verify_proof(&proof, &wrong_inputs).unwrap());
+    }
+
+    #[test]
+    fn test_verification_report_fields() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        // 証明は正しいが検証者の期間より古い行動（下限も行動の時刻から決まるので古くなる）
+        let old_activity = ActivityData {
+            timestamp: Utc::now() - Duration::days(31),
+            activity_hash: hash_activity("report_stale"),
+            user_secret: test_secret(18),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_old, proof_old) = prover.generate_proof_deterministic(&old_activity, [18u8; 32]).unwrap();
+        let before = Utc::now();
+        let report = verifier.verify_activity(&public_old, &proof_old).unwrap();
+        assert!(report.proof_valid);
+        assert!(!report.timestamp_fresh);
+        assert!(!report.cutoff_accepted);
+        assert_eq!(report.nullifier, None);
+        assert!(report.checked_at >= before && report.checked_at <= Utc::now());
+        assert!(matches!(report.into_result(), Err(ZkError::StaleTimestamp)));
+
+        // 新しい行動だが公開入力を改ざんした偽造
+        let recent_activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("report_forged"),
+            user_secret: test_secret(18),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (mut forged, proof) = prover.prove_activity_with_rng(&recent_activity, rng).unwrap();
+        let nullifier = forged.nullifier;
+        forged.nullifier += Fr::from(1u64);
+        let report = verifier.verify_activity(&forged, &proof).unwrap();
+        assert!(!report.proof_valid);
+        assert!(report.timestamp_fresh);
+        assert!(report.cutoff_accepted);
+        assert_eq!(report.nullifier, None);
+        assert!(matches!(report.into_result(), Err(ZkError::VerificationFailed)));
+        assert!(!verifier.verify_activity_bool(&forged, &proof));
+
+        // 改ざんしなければすべての検査に通り、nullifierが返る
+        forged.nullifier = nullifier;
+        let report = verifier.verify_activity(&forged, &proof).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.nullifier, Some(nullifier));
+    }
+
+    #[test]
+    fn test_constant_time_result_matches_bool() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("constant_time"),
+            user_secret: test_secret(16),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let valid_inputs = public_activity.public_inputs().unwrap().to_vec();
+        let mut invalid_inputs = valid_inputs.clone();
+        invalid_inputs[4] += Fr::from(1u64);
+
+        for inputs in [&valid_inputs, &invalid_inputs] {
+            let choice = verifier.verify_proof_ct(&proof, inputs).unwrap();
+            assert_eq!(bool::from(choice), verifier.verify_proof(&proof, inputs).unwrap());
+        }
+        assert!(bool::from(verifier.verify_proof_ct(&proof, &valid_inputs).unwrap()));
+        assert!(!bool::from(verifier.verify_proof_ct(&proof, &invalid_inputs).unwrap()));
+    }
+
+    #[test]
+    fn test_custom_freshness_window() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let verifier = verifier.with_freshness_window(Duration::days(7));
+        assert_eq!(verifier.freshness_window(), Duration::days(7));
+
+        // 下限は証明者が決めるので、証明者も同じ期間を使う
+        let prover = prover.with_freshness_window(Duration::days(7));
+
+        // 7日の期間では10日前の行動は拒否される
+        let old_activity = ActivityData {
+            timestamp: Utc::now() - Duration::days(10),
+            activity_hash: hash_activity("ten_days_ago"),
+            user_secret: test_secret(10),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.generate_proof_deterministic(&old_activity, [10u8; 32]).unwrap();
+        assert!(matches!(
+            verifier.verify_activity(&public_activity, &proof).and_then(VerificationReport::into_result),
+            Err(ZkError::StaleTimestamp)
+        ));
+
+        // 3日前の行動は受理される
+        let recent_activity = ActivityData {
+            timestamp: Utc::now() - Duration::days(3),
+            activity_hash: hash_activity("three_days_ago"),
+            user_secret: test_secret(10),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&recent_activity, rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+    }
+
+    #[test]
+    fn test_borrowed_inputs_match_vec_path() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("borrowed"), test_secret(41), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 配列を借りた検証とVecでの検証は、正しい公開入力でも改ざんした公開入力でも同じ結果になる
+        let valid = public_activity.public_inputs().unwrap().to_array();
+        let mut tampered = valid;
+        tampered[4] += Fr::from(1u64);
+        for public_inputs in [valid, tampered] {
+            let owned: Vec<Fr> = public_inputs.to_vec();
+            assert_eq!(
+                verifier.verify_proof_slice(&proof, &public_inputs).unwrap(),
+                verifier.verify_proof(&proof, &owned).unwrap()
+            );
+        }
+        assert!(verifier.verify_proof_slice(&proof, &valid).unwrap());
+        assert!(matches!(
+            verifier.verify_proof_slice(&proof, &valid[..NUM_PUBLIC_INPUTS - 1]),
+            Err(ZkError::PublicInputArity { .. })
+        ));
+
+        // 報告を組み立てない簡易版も、新しい行動と古い行動のどちらでも報告と同じ判定になる
+        let old =
+            ActivityData::new(Utc::now() - Duration::days(2), hash_activity("borrowed"), test_secret(41), [0u8; 32]);
+        let (old_public, old_proof) = prover.prove_activity_with_rng(&old, rng).unwrap();
+        let verifier = verifier.with_freshness_window(Duration::days(1));
+        for (public_activity, proof) in [(&public_activity, &proof), (&old_public, &old_proof)] {
+            assert_eq!(
+                verifier.verify_activity_bool(public_activity, proof),
+                verifier.verify_activity(public_activity, proof).unwrap().is_valid()
+            );
+        }
+        assert!(!verifier.verify_activity_bool(&old_public, &old_proof));
+    }
+
+    #[test]
+    fn test_time_arithmetic_overflow_is_an_error() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("extreme"), test_secret(35), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 表せる最も古い時刻の近くを検証者の時計にすると、鮮度の期間を遡れないのでパニックせずにエラーになる
+        let near_min = DateTime::<Utc>::MIN_UTC + Duration::days(1);
+        assert!(matches!(
+            verifier.verify_activity_with_clock(&public_activity, &proof, near_min),
+            Err(ZkError::TimeArithmetic)
+        ));
+
+        // 証明者も、行動の時刻から鮮度の期間を遡れなければ下限を決められない
+        let ancient = ActivityData {
+            timestamp: near_min,
+            ..activity
+        };
+        assert!(matches!(
+            prover.generate_proof_deterministic(&ancient, [35u8; 32]),
+            Err(ZkError::TimeArithmetic)
+        ));
+
+        // 表せる最も新しい時刻の近くでは、未来かどうかを確かめるために許容誤差を足せない
+        let near_max = DateTime::<Utc>::MAX_UTC - Duration::minutes(1);
+        assert!(matches!(
+            check_not_future(near_max, near_max, DEFAULT_CLOCK_TOLERANCE),
+            Err(ZkError::TimeArithmetic)
+        ));
+        assert!(matches!(
+            ActivityData::builder().activity("extreme").commitment(test_secret(35)).build_with_clock(near_max),
+            Err(ZkError::TimeArithmetic)
+        ));
+    }
+
+    #[test]
+    fn test_freshness_window_boundary() {
+        let verifier = shared_test_verifier();
+        let verifier = verifier
+            .with_freshness_window(Duration::days(7))
+            .with_clock_tolerance(Duration::zero());
+
+        let now = Utc::now();
+        let edge = now - Duration::days(7);
+
+        // 許容誤差が0なら、境界ちょうどは受理し、1秒でも古ければ拒否する
+        assert!(verifier.check_freshness(edge, now).is_ok());
+        assert!(matches!(
+            verifier.check_freshness(edge - Duration::seconds(1), now),
+            Err(ZkError::StaleTimestamp)
+        ));
+    }
+
+    #[test]
+    fn test_day_granularity_is_stable_within_a_day() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let tolerance = verifier.clock_tolerance();
+
+        // 朝に検証した時点でちょうど30日前の行動（下限は行動の時刻そのもの）
+        let morning = DateTime::from_timestamp(1_767_000_000, 0).unwrap();
+        let morning = start_of_day(morning).unwrap() + Duration::hours(6);
+        let evening = morning + Duration::hours(14);
+        let activity =
+            ActivityData::new(morning - Duration::days(30), hash_activity("daily"), test_secret(23), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_with_cutoff(&activity, activity.timestamp, rng).unwrap();
+
+        // 瞬間を基準にすると、同じ日でも夕方には古い行動になる
+        let verify = |verifier: &ActivityVerifier, now| {
+            verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap().is_valid()
+        };
+        assert!(verify(&verifier, morning));
+        assert!(!verify(&verifier, evening));
+
+        // 日単位なら、同じ日のうちは判定が一致し、翌日の0時（UTC）ちょうどに古くなる
+        // 許容誤差の内側の00:05でも、前日の範囲のままにはならない
+        let verifier = verifier.with_day_granularity();
+        assert!(verify(&verifier, morning));
+        assert!(verify(&verifier, evening));
+        let midnight = start_of_day(morning).unwrap() + Duration::days(1);
+        assert!(Duration::minutes(5) < tolerance);
+        assert!(verify(&verifier, midnight - Duration::seconds(1)));
+        assert!(!verify(&verifier, midnight));
+        assert!(!verify(&verifier, midnight + Duration::minutes(5)));
+
+        // 下限は0時のままで、許容誤差の分だけ前日に食い込むことはない
+        let day_before = start_of_day(activity.timestamp).unwrap() - Duration::seconds(1);
+        let late = ActivityData::new(day_before, hash_activity("daily"), test_secret(23), [0u8; 32]);
+        let (late_activity, late_proof) = prover.prove_with_cutoff(&late, late.timestamp, rng).unwrap();
+        let report = verifier.verify_activity_with_clock(&late_activity, &late_proof, morning).unwrap();
+        assert!(report.proof_valid);
+        assert!(!report.timestamp_fresh);
+    }
+
+    #[test]
+    fn test_is_fresh_matches_verify_activity() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("prefilter"), test_secret(29), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_with_cutoff(&activity, activity.timestamp, rng).unwrap();
+
+        // 鮮度の期間の内と外の時計で、証明を使わない判定と検証の報告が一致する
+        let verifier = verifier.with_clock(FixedClock(activity.timestamp + Duration::days(1)));
+        assert!(verifier.is_fresh(activity.timestamp));
+        assert!(verifier.verify_activity(&public_activity, &proof).unwrap().timestamp_fresh);
+
+        let stale = activity.timestamp + DEFAULT_FRESHNESS_WINDOW + DEFAULT_CLOCK_TOLERANCE + Duration::seconds(1);
+        let verifier = verifier.with_clock(FixedClock(stale));
+        assert!(!verifier.is_fresh(activity.timestamp));
+        assert!(!verifier.verify_activity(&public_activity, &proof).unwrap().timestamp_fresh);
+
+        // 境界ちょうどは受理する
+        let edge = stale - Duration::seconds(1);
+        assert!(verifier.is_fresh_at(activity.timestamp, edge));
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, edge).unwrap();
+        assert!(report.timestamp_fresh);
+    }
+
+    #[test]
+    fn test_default_freshness_window_is_thirty_days() {
+        let verifier = shared_test_verifier();
+
+        assert_eq!(verifier.freshness_window(), Duration::days(30));
+        assert_eq!(verifier.clock_tolerance(), DEFAULT_CLOCK_TOLERANCE);
+    }
+
+    #[test]
+    fn test_cutoff_tolerance_boundary() {
+        let (prover, verifier) = shared_test_setup();
+        let tolerance = Duration::minutes(5);
+        let verifier = verifier.with_clock_tolerance(tolerance);
+
+        // 証明した時点の下限は「行動の時刻 - 30日」
+        let activity = ActivityData {
+            timestamp: Utc::now().trunc_subsecs(0),
+            activity_hash: hash_activity("cutoff_tolerance"),
+            user_secret: test_secret(19),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.generate_proof_deterministic(&activity, [19u8; 32]).unwrap();
+        assert_eq!(public_activity.cutoff, activity.timestamp - DEFAULT_FRESHNESS_WINDOW);
+
+        // 証明してから許容誤差ちょうど経過した時点までは受理する
+        let edge = activity.timestamp + tolerance;
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, edge).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.checked_at, edge);
+
+        // 1秒でも超えると、下限が検証者の期待より古いとして拒否する
+        let late = edge + Duration::seconds(1);
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, late).unwrap();
+        assert!(report.proof_valid);
+        assert!(report.timestamp_fresh);
+        assert!(!report.cutoff_accepted);
+        assert!(matches!(report.into_result(), Err(ZkError::StaleCutoff)));
+
+        // 検証者の時計が許容誤差の範囲で遅れていても、期待より新しい（厳しい）下限は受理する
+        let early = activity.timestamp - tolerance;
+        assert!(verifier
+            .verify_activity_with_clock(&public_activity, &proof, early)
+            .unwrap()
+            .is_valid());
+    }
+
+    #[test]
+    fn test_verify_any_returns_index_of_accepting_key() {
+        let rng = &mut seeded_rng();
+        let (_, old_verifier) = Setup::generate_with_rng(rng);
+        let (new_prover, new_verifier) = Setup::generate_with_rng(rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("rotation"), test_secret(34), [0u8; 32]);
+        let (public_activity, proof) = new_prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 猶予期間中は古い鍵と新しい鍵の両方を受け入れ、新しい鍵で作った証明は2番目の鍵で通る
+        let vks = [old_verifier.verifying_key.clone(), new_verifier.verifying_key.clone()];
+        assert_eq!(old_verifier.verify_any(&public_activity, &proof, &vks).unwrap(), Some(1));
+
+        // どの鍵でも通らない場合や、公開部分を改ざんした場合はNone
+        assert_eq!(old_verifier.verify_any(&public_activity, &proof, &vks[..1]).unwrap(), None);
+        let mut tampered = public_activity.clone();
+        tampered.nullifier += Fr::from(1u64);
+        assert_eq!(old_verifier.verify_any(&tampered, &proof, &vks).unwrap(), None);
+
+        // 正しい証明でも、検証者の鮮度の期間を過ぎた行動はどの鍵も試さずにNone
+        let later = activity.timestamp + Duration::days(31);
+        assert_eq!(old_verifier.verify_any_with_clock(&public_activity, &proof, &vks, later).unwrap(), None);
+        let lenient = ActivityVerifier::from_prepared_key(old_verifier.verifying_key.clone())
+            .with_freshness_window(Duration::days(90));
+        assert_eq!(lenient.verify_any_with_clock(&public_activity, &proof, &vks, later).unwrap(), Some(1));
+
+        // 自分の検証キーと公開入力の数が異なっても、一覧の鍵は試す
+        let (_, aggregate_verifier) = Setup::<Bn254>::generate_for_n_activities::<2, _>(rng);
+        let other_arity = ActivityVerifier::from_prepared_key(aggregate_verifier.verifying_key().clone());
+        assert_ne!(num_public_inputs(&other_arity.verifying_key), NUM_PUBLIC_INPUTS);
+        assert_eq!(other_arity.verify_any(&public_activity, &proof, &vks).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_future_timestamp_is_rejected() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now();
+
+        // 1時間後のタイムスタンプは証明としては正しくても、未来の行動として拒否する
+        let future = ActivityData::new(now + Duration::hours(1), hash_activity("future"), test_secret(33), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&future, rng).unwrap();
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap();
+        assert!(report.proof_valid && report.timestamp_fresh && report.cutoff_accepted);
+        assert!(!report.timestamp_not_future);
+        assert_eq!(report.nullifier, None);
+        assert!(matches!(report.into_result(), Err(ZkError::FutureTimestamp)));
+
+        // 時計のずれとして許容誤差（デフォルトは10分）の範囲内なら受理する
+        let skewed = ActivityData::new(now + Duration::minutes(5), hash_activity("future"), test_secret(33), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&skewed, rng).unwrap();
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap();
+        assert!(report.timestamp_not_future);
+        assert!(report.is_valid());
+
+        // 境界ちょうどは受理し、1秒でも超えれば拒否する
+        let edge = now + DEFAULT_CLOCK_TOLERANCE;
+        assert!(check_not_future(edge, now, DEFAULT_CLOCK_TOLERANCE).is_ok());
+        assert!(matches!(
+            check_not_future(edge + Duration::seconds(1), now, DEFAULT_CLOCK_TOLERANCE),
+            Err(ZkError::FutureTimestamp)
+        ));
+    }
+
+    #[test]
+    fn test_batch_verification_reports_each_proof() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let mut items = Vec::new();
+        for i in 0..4u8 {
+            let activity = ActivityData {
+                timestamp: Utc::now(),
+                activity_hash: hash_activity(&format!("batch_activity_{}", i)),
+                user_secret: test_secret(20 + i),
+                salt: [0u8; 32],
+                hash_salt: None,
+            };
+            let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+            items.push((proof, public_activity.public_inputs().unwrap().to_vec()));
+        }
+
+        // すべて有効なら一括検証だけで全件trueになる
+        assert_eq!(verifier.verify_batch_with_rng(&items, rng), vec![true; 4]);
+
+        // 不正な証明が混ざっていても、その位置だけがfalseになる
+        items[1].1[0] += Fr::from(1u64);
+        items[3].0 = items[2].0.clone();
+        assert_eq!(
+            verifier.verify_batch_with_rng(&items, rng),
+            vec![true, false, true, false]
+        );
+
+        // 公開入力の数が合わない証明もその位置だけがfalseになる
+        items[3].1.clear();
+        assert_eq!(verifier.verify_batch(&items), vec![true, false, true, false]);
+
+        assert!(verifier.verify_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_verifier_from_ceremony_vk_bytes() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+
+        let activity = ActivityData {
+            timestamp: Utc::now(),
+            activity_hash: hash_activity("ceremony_vk"),
+            user_secret: test_secret(20),
+            salt: [0u8; 32],
+            hash_salt: None,
+        };
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // arkworksの圧縮・非圧縮どちらの形式の検証キーからも、同じ証明を検証できる
+        let vk = &verifier.verifying_key.vk;
+        let mut compressed = Vec::new();
+        vk.serialize_compressed(&mut compressed).unwrap();
+        let mut uncompressed = Vec::new();
+        vk.serialize_uncompressed(&mut uncompressed).unwrap();
+        for bytes in [&compressed, &uncompressed] {
+            let imported = Bn254Verifier::from_vk_bytes(bytes).unwrap();
+            assert!(imported.verify_activity_bool(&public_activity, &proof));
+        }
+
+        // 余分なバイトが付いた鍵や、途中で切れた鍵は読み込めない
+        let mut trailing = compressed.clone();
+        trailing.push(0);
+        assert!(matches!(Bn254Verifier::from_vk_bytes(&trailing), Err(SetupError::Serialization(_))));
+        assert!(matches!(
+            Bn254Verifier::from_vk_bytes(&compressed[..compressed.len() - 1]),
+            Err(SetupError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_ceremony_vk_for_other_circuit_is_rejected() {
+        let rng = &mut seeded_rng();
+
+        // 公開入力が1つしかない回路（Merkle木）の検証キー
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(MerkleActivityCircuit::blank(1), rng)
+            .unwrap();
+        let mut bytes = Vec::new();
+        pk.vk.serialize_compressed(&mut bytes).unwrap();
+        assert!(matches!(
+            Bn254Verifier::from_vk_bytes(&bytes),
+            Err(SetupError::PublicInputCount {
+                expected: NUM_PUBLIC_INPUTS,
+                found: 1
+            })
+        ));
+
+        // 別の曲線（BLS12-381）の検証キーはBN254の鍵として解釈できない
+        let (_, bls_verifier) = Setup::<Bls12_381>::generate_for_curve(rng);
+        let mut bytes = Vec::new();
+        bls_verifier.verifying_key.vk.serialize_compressed(&mut bytes).unwrap();
+        assert!(Bn254Verifier::from_vk_bytes(&bytes).is_err());
+        assert!(Bls12Verifier::from_vk_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_allowlisted_activity_verifies_without_revealing_hash() {
+        let rng = &mut seeded_rng();
+        let allowed = [hash_activity("walk"), hash_activity("run"), hash_activity("swim")];
+        let (prover, verifier) = Setup::<Bn254>::generate_with_allowlist(&allowed, rng);
+
+        // 許可された行動は、行動ハッシュを明かさずに検証できる
+        let activity = ActivityData::new(Utc::now(), hash_activity("run"), test_secret(21), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        assert_eq!(public_activity.activity_hash, [0u8; 32]);
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        // 別の許可リストを使う検証者には受理されない
+        let other_verifier = ActivityVerifier::from_prepared_key(verifier.verifying_key.clone())
+            .with_allowlist(&[hash_activity("walk"), hash_activity("swim")]);
+        let report = other_verifier.verify_activity(&public_activity, &proof).unwrap();
+        assert!(!report.proof_valid);
+    }
+
+    #[test]
+    fn test_disallowed_activity_cannot_be_proven() {
+        let rng = &mut seeded_rng();
+        let allowed = [hash_activity("walk"), hash_activity("run")];
+        let (prover, _) = Setup::<Bn254>::generate_with_allowlist(&allowed, rng);
+
+        // リストにない行動は証明を生成する前に拒否される
+        let activity = ActivityData::new(Utc::now(), hash_activity("dance"), test_secret(22), [0u8; 32]);
+        assert!(matches!(
+            prover.prove_activity_with_rng(&activity, rng),
+            Err(ZkError::NotAllowlisted)
+        ));
+
+        // 許可された行動の経路を流用しても、回路の制約を満たさない
+        let mut circuit = ActivityCircuit::<Fr>::new(
+            activity.timestamp,
+            activity.activity_hash,
+            activity.user_secret,
+            activity.salt,
+            activity.timestamp - Duration::days(30),
+        )
+        .unwrap();
+        circuit.allowlist = Allowlist::new(&allowed).membership(&allowed[0]);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_its_challenge() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("session"), test_secret(23), [0u8; 32]);
+
+        // チャレンジAに対する証明は、Aでは検証を通る
+        let challenge_a = verifier.issue_challenge_with_rng(rng);
+        let challenge_b = verifier.issue_challenge_with_rng(rng);
+        assert_ne!(challenge_a, challenge_b);
+        let (public_activity, proof) = prover.prove_activity_for_challenge_with_rng(&activity, challenge_a, rng).unwrap();
+        assert_eq!(public_activity.challenge, challenge_a);
+        assert!(verifier.verify_activity_for_challenge(&public_activity, &proof, challenge_a).unwrap().is_valid());
+
+        // 別のセッションのチャレンジBや、チャレンジなしとしては受理されない
+        let report = verifier.verify_activity_for_challenge(&public_activity, &proof, challenge_b).unwrap();
+        assert!(!report.proof_valid);
+        let without_challenge = PublicActivity {
+            challenge: Fr::zero(),
+            ..public_activity
+        };
+        assert!(!verifier.verify_activity_bool(&without_challenge, &proof));
+    }
+
+    #[test]
+    fn test_v1_proof_verifies_with_defaulted_inputs() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("v1_client"), test_secret(48), [0u8; 32]);
+
+        // V1のクライアントはチャレンジも利用者のIDも付けずに証明する
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // デフォルトでは最新の形式しか受け付けず、V1と明示された証明は検証する前に拒否する
+        assert!(matches!(
+            verifier.verify_activity_versioned(PublicInputVersion::V1, &public_activity, &proof),
+            Err(ZkError::InputVersionRejected { minimum: 3, found: 1 })
+        ));
+
+        // V1を受け付ける方針なら、送られてこなかったフィールドを0で埋めて検証を通す
+        let verifier = verifier.with_minimum_input_version(PublicInputVersion::V1);
+        let stray = PublicActivity {
+            challenge: Fr::from(5u64),
+            ..public_activity.clone()
+        };
+        let report = verifier.verify_activity_versioned(PublicInputVersion::V1, &stray, &proof).unwrap();
+        assert!(report.is_valid());
+
+        // 同じ証明をV2として送っても、0でないチャレンジを求められれば拒否する
+        let report = verifier.verify_activity_versioned(PublicInputVersion::V2, &stray, &proof).unwrap();
+        assert!(!report.proof_valid);
+        let challenge = verifier.issue_challenge_with_rng(rng);
+        let report = verifier.verify_activity_for_challenge(&public_activity, &proof, challenge).unwrap();
+        assert!(!report.proof_valid);
+    }
+
+    #[test]
+    fn test_proof_expiry_is_enforced() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now().trunc_subsecs(0);
+        let activity = ActivityData::new(now, hash_activity("expiring"), test_secret(49), [0u8; 32]);
+
+        // 期限内の証明は受理し、期限は公開部分と報告の監査用の値に残る
+        let expiry = now + Duration::hours(1);
+        let (public_activity, proof) = prover.prove_activity_until_with_rng(&activity, expiry, rng).unwrap();
+        assert_eq!(public_activity.expiry, expiry.timestamp() as u64);
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, now).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.context.expiry, expiry.timestamp().to_string());
+
+        // 行動と下限が鮮度の期間内でも、期限を許容誤差より長く過ぎた証明は拒否する
+        let verifier = verifier.with_freshness_window(DEFAULT_FRESHNESS_WINDOW + Duration::days(1));
+        let later = expiry + DEFAULT_CLOCK_TOLERANCE + Duration::seconds(1);
+        let report = verifier.verify_activity_with_clock(&public_activity, &proof, later).unwrap();
+        assert!(report.proof_valid && report.timestamp_fresh && report.cutoff_accepted);
+        assert!(!report.proof_not_expired);
+        assert!(matches!(report.into_result(), Err(ZkError::ExpiredProof)));
+
+        // 期限を延ばした公開部分では証明が検証を通らない
+        let extended = PublicActivity {
+            expiry: NO_EXPIRY,
+            ..public_activity
+        };
+        assert!(!verifier.verify_activity_with_clock(&extended, &proof, later).unwrap().proof_valid);
+
+        // 行動より前の期限では証明できない
+        assert!(matches!(
+            prover.prove_activity_until_with_rng(&activity, now - Duration::seconds(1), rng),
+            Err(ZkError::ExpiredProof)
+        ));
+    }
+
+    #[test]
+    fn test_canonical_hash_policy_rejects_reduced_hashes() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let verifier = verifier.with_canonical_hashes();
+
+        // 位数未満の行動ハッシュは通常どおり検証する
+        let find = |canonical: bool| {
+            (0u8..)
+                .map(|i| hash_activity(&format!("canonical_{}", i)))
+                .find(|hash| is_canonical_be::<Fr>(hash) == canonical)
+                .unwrap()
+        };
+        let activity = ActivityData::new(Utc::now(), find(true), test_secret(54), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        assert!(verifier.verify_activity_bool(&public_activity, &proof));
+
+        // 簡約される行動ハッシュは、証明を検証する前に拒否する
+        let activity = ActivityData::new(Utc::now(), find(false), test_secret(54), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        assert!(matches!(verifier.verify_activity(&public_activity, &proof), Err(ZkError::NonCanonicalField)));
+    }
+
+    #[test]
+    fn test_builder_applies_options() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("builder"), test_secret(55), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_with_cutoff(&activity, activity.timestamp, rng).unwrap();
+
+        let built = ActivityVerifier::builder()
+            .verifying_key(verifier.verifying_key().clone())
+            .freshness_window(Duration::days(7))
+            .clock_tolerance(Duration::minutes(1))
+            .exact_timestamps()
+            .block_window(100)
+            .cache(4)
+            .clock(FixedClock(activity.timestamp + Duration::days(7)))
+            .build()
+            .unwrap();
+        assert_eq!(built.freshness_window(), Duration::days(7));
+        assert_eq!(built.clock_tolerance(), Duration::minutes(1));
+        assert_eq!(built.block_window(), 100);
+
+        // 固定した時計は鮮度の期間ちょうどの時点なので受理し、結果はキャッシュに残る
+        assert!(built.verify_activity_bool(&public_activity, &proof));
+        assert_eq!(built.cached_results(), 1);
+    }
+
+    #[test]
+    fn test_builder_rejects_conflicting_options() {
+        let vk = shared_test_verifier().verifying_key().clone();
+        let epoch = EpochConfig {
+            start: DateTime::from_timestamp(0, 0).unwrap(),
+            length: Duration::days(1),
+        };
+        let builder = || Bn254Verifier::builder().verifying_key(vk.clone());
+
+        assert!(matches!(
+            Bn254Verifier::builder().build(),
+            Err(ZkError::MissingField("verifying_key"))
+        ));
+        assert!(matches!(
+            builder().epoch(epoch).exact_timestamps().build(),
+            Err(ZkError::InvalidVerifierConfig(_))
+        ));
+        assert!(matches!(builder().epoch(epoch).block_window(10).build(), Err(ZkError::BlockWithEpoch)));
+        assert!(matches!(
+            builder().allowlist(&[hash_activity("walk")]).canonical_hashes().build(),
+            Err(ZkError::InvalidVerifierConfig(_))
+        ));
+        assert!(matches!(
+            builder().freshness_window(Duration::days(-1)).build(),
+            Err(ZkError::InvalidVerifierConfig(_))
+        ));
+        let empty_epoch = EpochConfig {
+            length: Duration::zero(),
+            ..epoch
+        };
+        assert!(matches!(builder().epoch(empty_epoch).build(), Err(ZkError::InvalidEpoch)));
+        assert!(matches!(
+            builder().range().epoch(epoch).build(),
+            Err(ZkError::InvalidVerifierConfig(_))
+        ));
+        assert!(matches!(
+            builder().range().block_window(10).build(),
+            Err(ZkError::InvalidVerifierConfig(_))
+        ));
+
+        // 公開入力の数が回路と合わない鍵では組み立てない
+        let mut extra = vk.clone();
+        extra.gamma_abc_g1.push(extra.gamma_abc_g1[0]);
+        assert!(matches!(
+            Bn254Verifier::builder().verifying_key(extra).build(),
+            Err(ZkError::PublicInputArity { got, .. }) if got == NUM_PUBLIC_INPUTS + 1
+        ));
+
+        // エポックだけなら組み立てられる
+        assert!(builder().epoch(epoch).build().is_ok());
+    }
+
+    #[test]
+    fn test_builder_sets_range_mode() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = Setup::<Bn254>::generate_with_range(rng);
+        let built = Bn254Verifier::builder()
+            .verifying_key(verifier.verifying_key().clone())
+            .range()
+            .build()
+            .unwrap();
+
+        // 組み立てた検証者は範囲付きの証明を受理し、範囲のない検証はRangeMismatchにする
+        let now = Utc::now();
+        let (start, end) = (now - Duration::days(1), now + Duration::days(1));
+        let activity = ActivityData::new(now, hash_activity("builder_range"), test_secret(10), test_secret(11));
+        let (public_activity, proof) = prover.prove_in_range(&activity, start, end, rng).unwrap();
+        assert!(built.verify_in_range(&public_activity, &proof, start, end).unwrap());
+        assert!(matches!(built.verify_activity(&public_activity, &proof), Err(ZkError::RangeMismatch)));
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_its_user() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("per_user"), test_secret(24), [0u8; 32]);
+        let (user_a, user_b) = (UserId(1), UserId(2));
+
+        // 利用者Aとして作った証明は、Aとしては検証を通るが、Bとしては通らない
+        let (public_activity, proof) = prover.prove_activity_for_user_with_rng(&activity, user_a, rng).unwrap();
+        assert_eq!(public_activity.user_id, user_a.to_field());
+        assert!(verifier.verify_for_user(user_a, &public_activity, &proof).unwrap().is_valid());
+        let report = verifier.verify_for_user(user_b, &public_activity, &proof).unwrap();
+        assert!(!report.proof_valid);
+
+        // 一括検証でも、指定した利用者と一致する項目だけが通る
+        let (other_activity, other_proof) = prover.prove_activity_for_user_with_rng(&activity, user_b, rng).unwrap();
+        let items = [
+            (user_a, public_activity.clone(), proof.clone()),
+            (user_a, other_activity.clone(), other_proof.clone()),
+            (user_b, other_activity, other_proof),
+            (user_b, public_activity, proof),
+        ];
+        let results = verifier.verify_batch_for_users_with_rng(&items, Utc::now(), rng);
+        assert_eq!(results, [true, false, true, false]);
+    }
+
+    #[test]
+    fn test_exported_vk_json_matches_affine_points() {
+        let verifier = shared_test_verifier();
+        let vk = verifier.verifying_key();
+        assert_eq!(vk, &verifier.verifying_key.vk);
+
+        let json: serde_json::Value = serde_json::from_str(&verifier.export_vk_json()).unwrap();
+        assert_eq!(json["protocol"], "groth16");
+        assert_eq!(json["nPublic"], NUM_PUBLIC_INPUTS);
+
+        // 各座標の10進文字列を読み戻すと、鍵のアフィン座標と一致する
+        let fq = |value: &serde_json::Value| Fq::from_str(value.as_str().unwrap()).unwrap();
+        let g1 = |value: &serde_json::Value, point: &G1Affine| {
+            assert_eq!((fq(&value[0]), fq(&value[1])), (point.x, point.y));
+            assert_eq!(value[2], "1");
+        };
+        let g2 = |value: &serde_json::Value, point: &G2Affine| {
+            assert_eq!((fq(&value[0][0]), fq(&value[0][1])), (point.x.c0, point.x.c1));
+            assert_eq!((fq(&value[1][0]), fq(&value[1][1])), (point.y.c0, point.y.c1));
+        };
+        g1(&json["vk_alpha_1"], &vk.alpha_g1);
+        g2(&json["vk_beta_2"], &vk.beta_g2);
+        g2(&json["vk_gamma_2"], &vk.gamma_g2);
+        g2(&json["vk_delta_2"], &vk.delta_g2);
+        let ic = json["IC"].as_array().unwrap();
+        assert_eq!(ic.len(), vk.gamma_abc_g1.len());
+        for (value, point) in ic.iter().zip(&vk.gamma_abc_g1) {
+            g1(value, point);
+        }
+    }
+
+    #[test]
+    fn test_public_input_arity_is_checked() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("arity"), test_secret(24), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 空の公開入力と、余分な値を付け足した公開入力はどの検証方法でもエラーになる
+        let mut too_long = public_activity.public_inputs().unwrap().to_vec();
+        too_long.push(Fr::from(1u64));
+        for (public_inputs, got) in [(Vec::new(), 0), (too_long, NUM_PUBLIC_INPUTS + 1)] {
+            let arity = |result: Result<_, ZkError>| match result {
+                Err(ZkError::PublicInputArity { expected, got: n }) => expected == NUM_PUBLIC_INPUTS && n == got,
+                _ => false,
+            };
+            assert!(arity(verifier.verify_proof(&proof, &public_inputs)));
+            assert!(arity(verifier.verify_proof_ct(&proof, &public_inputs).map(bool::from)));
+            assert!(arity(verifier.verify_combined(&[(proof.clone(), public_inputs)], rng)));
+        }
+    }
+
+    #[test]
+    fn test_verify_with_vk_matches_prepared_key() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("stateless"), test_secret(25), [0u8; 32]);
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 正しい公開入力・改ざんした公開入力・数の合わない公開入力のいずれでも、前処理済みの鍵と同じ結果になる
+        let valid = public_activity.public_inputs().unwrap().to_vec();
+        let mut tampered = valid.clone();
+        tampered[2] += Fr::from(1u64);
+        for public_inputs in [valid, tampered] {
+            assert_eq!(
+                verify_with_vk(verifier.verifying_key(), &proof, &public_inputs).unwrap(),
+                verifier.verify_proof(&proof, &public_inputs).unwrap()
+            );
+        }
+        let public_inputs = public_activity.public_inputs().unwrap().to_vec();
+        assert!(verify_with_vk(verifier.verifying_key(), &proof, &public_inputs).unwrap());
+        assert!(matches!(
+            verify_with_vk(verifier.verifying_key(), &proof, &[]),
+            Err(ZkError::PublicInputArity { .. })
+        ));
+    }
+
+    #[test]
+    fn test_stream_reports_line_up_with_inputs() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now();
+        let activity =
+            |name: &str, timestamp| ActivityData::new(timestamp, hash_activity(name), test_secret(26), [0u8; 32]);
+
+        // 正しい証明・改ざんした公開入力・古い行動・正しい証明の順に流す
+        let valid = prover.prove_activity_with_rng(&activity("a", now), rng).unwrap();
+        let (mut tampered, tampered_proof) = prover.prove_activity_with_rng(&activity("b", now), rng).unwrap();
+        tampered.nullifier += Fr::from(1u64);
+        let stale = prover
+            .generate_proof_deterministic(&activity("c", now - Duration::days(40)), [3u8; 32])
+            .unwrap();
+        let last = prover.prove_activity_with_rng(&activity("d", now), rng).unwrap();
+        let items = vec![valid, (tampered, tampered_proof), stale, last];
+
+        let reports: Vec<_> = verifier.verify_stream(items.clone()).map(Result::unwrap).collect();
+        assert_eq!(reports.len(), 4);
+        assert!(reports[0].is_valid());
+        assert!(!reports[1].proof_valid);
+        assert!(reports[2].proof_valid && !reports[2].timestamp_fresh);
+        assert!(reports[3].is_valid());
+        for (report, (activity, _)) in reports.iter().zip(&items) {
+            assert_eq!(report.nullifier.is_some(), report.is_valid());
+            if let Some(nullifier) = report.nullifier {
+                assert_eq!(nullifier, activity.nullifier);
+            }
+        }
+
+        // 必要な分だけ入力を取り出す
+        let mut pulled = 0;
+        let first = verifier
+            .verify_stream(items.into_iter().inspect(|_| pulled += 1))
+            .next()
+            .unwrap();
+        assert!(first.unwrap().is_valid());
+        assert_eq!(pulled, 1);
+    }
+
+    #[test]
+    fn test_range_proof_covers_both_boundaries() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = Setup::<Bn254>::generate_with_range(rng);
+
+        // 2024年の第1四半期（1月1日 00:00:00 から 3月31日 23:59:59 まで）
+        let start = DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+        let end = DateTime::from_timestamp(1_711_929_599, 0).unwrap();
+        let activity_at =
+            |timestamp| ActivityData::new(timestamp, hash_activity("q1_activity"), test_secret(8), test_secret(9));
+
+        // 両端ちょうどと内側の時刻は証明でき、範囲を決めた検証者が受理する
+        for timestamp in [start, end, start + Duration::days(45)] {
+            let (public_activity, proof) = prover.prove_in_range(&activity_at(timestamp), start, end, rng).unwrap();
+            assert_eq!((public_activity.cutoff, public_activity.timestamp), (start, end));
+            assert!(verifier.verify_in_range(&public_activity, &proof, start, end).unwrap(), "{}", timestamp);
+
+            // 同じ証明で、より狭い範囲を主張することはできない
+            let narrower = verifier.verify_in_range(&public_activity, &proof, start + Duration::seconds(1), end);
+            assert!(!narrower.unwrap());
+        }
+
+        // 範囲の外の時刻は1秒でも制約を満たさない
+        for timestamp in [start - Duration::seconds(1), end + Duration::seconds(1)] {
+            assert!(matches!(
+                prover.prove_in_range(&activity_at(timestamp), start, end, rng),
+                Err(ZkError::Unsatisfied)
+            ));
+        }
+
+        // 始まりが終わりより後の範囲はエラー
+        let activity = activity_at(start);
+        assert!(matches!(prover.prove_in_range(&activity, end, start, rng), Err(ZkError::InvalidRange)));
+        let (public_activity, proof) = prover.prove_in_range(&activity, start, end, rng).unwrap();
+        assert!(matches!(
+            verifier.verify_in_range(&public_activity, &proof, end, start),
+            Err(ZkError::InvalidRange)
+        ));
+    }
+
+    #[test]
+    fn test_range_mode_must_match_key() {
+        let rng = &mut seeded_rng();
+        let (range_prover, range_verifier) = Setup::<Bn254>::generate_with_range(rng);
+        let (prover, verifier) = shared_test_setup();
+        let now = Utc::now();
+        let (start, end) = (now - Duration::days(1), now);
+        let activity = ActivityData::new(now, hash_activity("range_mode"), test_secret(8), test_secret(9));
+
+        // 範囲なしの鍵では範囲を証明できず、範囲付きの鍵では範囲以外を証明できない（どちらも証明を作る前に拒否する）
+        assert!(matches!(prover.prove_in_range(&activity, start, end, rng), Err(ZkError::RangeMismatch)));
+        assert!(matches!(range_prover.prove_activity_with_rng(&activity, rng), Err(ZkError::RangeMismatch)));
+        assert!(matches!(range_prover.check_satisfied(&activity), Err(ZkError::RangeMismatch)));
+
+        // 範囲なしの検証者では範囲の証明を検証できず、範囲付きの検証者では範囲以外を検証できない
+        let (range_public, range_proof) = range_prover.prove_in_range(&activity, start, end, rng).unwrap();
+        assert!(matches!(
+            verifier.verify_in_range(&range_public, &range_proof, start, end),
+            Err(ZkError::RangeMismatch)
+        ));
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        assert!(matches!(range_verifier.verify_activity(&public_activity, &proof), Err(ZkError::RangeMismatch)));
+        assert!(!range_verifier.verify_activity_bool(&public_activity, &proof));
+        assert!(range_verifier.verify_in_range(&range_public, &range_proof, start, end).unwrap());
+    }
+
+    #[test]
+    fn test_context_records_checked_public_inputs() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("audited"), test_secret(10), test_secret(11));
+        let (public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        let report = verifier.verify_activity(&public_activity, &proof).unwrap();
+        assert!(report.is_valid());
+        let context = &report.context;
+
+        // 記録した時刻を読み戻すと、行動の時刻と下限に一致する
+        assert_eq!(DateTime::parse_from_rfc3339(&context.timestamp).unwrap(), public_activity.timestamp);
+        assert_eq!(context.timestamp, public_activity.timestamp.to_rfc3339());
+        assert_eq!(DateTime::parse_from_rfc3339(&context.cutoff).unwrap(), public_activity.cutoff);
+
+        // 公開入力は検証に渡した値そのもので、16進文字列はその値を表す
+        assert_eq!(context.public_inputs, public_activity.public_inputs().unwrap().to_vec());
+        assert_eq!(context.activity_hash, hash_to_hex(&public_activity.activity_hash));
+        assert_eq!(
+            Fr::from_be_bytes_mod_order(&hex::decode(&context.nullifier).unwrap()),
+            public_activity.nullifier
+        );
+        assert_eq!(context.challenge, "00".repeat(32));
+        assert_eq!(context.checked_at, report.checked_at);
+    }
+
+    #[test]
+    fn test_rotated_key_rejects_old_proofs() {
+        let rng = &mut seeded_rng();
+        let (old_prover, mut verifier) = Setup::generate_with_rng(rng);
+        let (new_prover, new_verifier) = Setup::generate_with_rng(rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("rotated"), test_secret(12), test_secret(13));
+        let (old_public, old_proof) = old_prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let (new_public, new_proof) = new_prover.prove_activity_with_rng(&activity, rng).unwrap();
+        let clock_tolerance = Duration::minutes(5);
+        verifier = verifier.with_clock_tolerance(clock_tolerance);
+        assert!(verifier.verify_activity_bool(&old_public, &old_proof));
+        assert!(!verifier.verify_activity_bool(&new_public, &new_proof));
+
+        // 差し替えた後は新しい鍵の証明だけを受理し、設定は引き継ぐ
+        verifier.rotate_vk(new_verifier.verifying_key().clone()).unwrap();
+        assert!(!verifier.verify_activity_bool(&old_public, &old_proof));
+        assert!(verifier.verify_activity_bool(&new_public, &new_proof));
+        assert_eq!(verifier.verifying_key(), new_verifier.verifying_key());
+        assert_eq!(verifier.clock_tolerance(), clock_tolerance);
+
+        // 公開入力の数が違う鍵には差し替えず、新しい鍵のまま残す
+        let mut wrong_arity = new_verifier.verifying_key().clone();
+        wrong_arity.gamma_abc_g1.pop();
+        assert!(matches!(
+            verifier.rotate_vk(wrong_arity),
+            Err(SetupError::PublicInputCount { found, .. }) if found == NUM_PUBLIC_INPUTS - 1
+        ));
+        assert!(verifier.verify_activity_bool(&new_public, &new_proof));
+    }
+
+    // 記録された1つのイベント（メッセージ, [(フィールド名, 値)]）
+    #[cfg(feature = "tracing")]
+    type CapturedEvent = (String, Vec<(String, String)>);
+
+    // 記録されたイベントを集める購読者
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct CapturedEvents(std::sync::Arc<std::sync::Mutex<Vec<CapturedEvent>>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for CapturedEvents {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Fields(Vec<(String, String)>);
+            impl tracing::field::Visit for Fields {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn core::fmt::Debug) {
+                    self.0.push((field.name().into(), format!("{:?}", value)));
+                }
+            }
+            let mut fields = Fields(Vec::new());
+            event.record(&mut fields);
+            let message = fields.0.iter().find(|(name, _)| name == "message").map(|(_, value)| value.clone());
+            self.0.lock().unwrap().push((message.unwrap_or_default(), fields.0));
+        }
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_verification_emits_outcome_event() {
+        let rng = &mut seeded_rng();
+        let (prover, verifier) = shared_test_setup();
+        let activity = ActivityData::new(Utc::now(), hash_activity("traced"), test_secret(31), test_secret(32));
+        let (mut public_activity, proof) = prover.prove_activity_with_rng(&activity, rng).unwrap();
+
+        // 正しい証明と改ざんした公開入力の検証で、それぞれの結果がイベントに記録される
+        let events = CapturedEvents::default();
+        tracing::subscriber::with_default(events.clone(), || {
+            assert!(verifier.verify_activity_bool(&public_activity, &proof));
+            public_activity.challenge = Fr::from(1u64);
+            assert!(!verifier.verify_activity_bool(&public_activity, &proof));
+            let _ = prover.prove_activity_with_rng(&activity, rng).unwrap();
+        });
+        let events = events.0.lock().unwrap();
+        let outcomes: Vec<_> = events
+            .iter()
+            .filter(|(message, _)| message == "verified proof")
+            .map(|(_, fields)| fields.iter().find(|(name, _)| name == "valid").unwrap().1.clone())
+            .collect();
+        assert_eq!(outcomes, ["true", "false"]);
+
+        // 証明の生成のイベントには回路の大きさだけが記録され、秘密の値は現れない
+        let (_, fields) = events.iter().find(|(message, _)| message == "generated proof").unwrap();
+        let names: Vec<_> = fields.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["message", "elapsed_ms", "constraints"]);
+    }
+}
@@ -0,0 +1,158 @@
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use ark_groth16::prepare_verifying_key;
+use chrono::{DateTime, Utc};
+use wasm_bindgen::prelude::*;
+
+use crate::activity::{hash_activity, hash_to_hex, hex_to_hash, ActivityData, PublicActivity};
+use crate::circuit::NO_EXPIRY;
+use crate::error::ZkError;
+use crate::prover::Bn254Prover;
+use crate::serialization::{deserialize_proof, serialize_proof};
+use crate::setup::{key_from_bytes, key_to_bytes, Setup};
+use crate::verifier::{Bn254Verifier, VerificationReport};
+use crate::wire::{datetime_from_unix, field_to_hex, hex_to_field};
+
+// ブラウザから使うためのセットアップ結果（鍵はUint8Arrayとして受け渡す）
+#[wasm_bindgen]
+pub struct WasmKeys {
+    proving_key: Vec<u8>,
+    verifying_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl WasmKeys {
+    #[wasm_bindgen(getter, js_name = provingKey)]
+    pub fn proving_key(&self) -> Vec<u8> {
+        self.proving_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = verifyingKey)]
+    pub fn verifying_key(&self) -> Vec<u8> {
+        self.verifying_key.clone()
+    }
+}
+
+// 証明と、検証に必要な公開入力（時刻はUnix秒、体の元は16進文字列）
+#[wasm_bindgen]
+pub struct WasmProof {
+    proof: Vec<u8>,
+    timestamp: f64,
+    activity_hash: String,
+    commitment: String,
+    cutoff: f64,
+    nullifier: String,
+}
+
+#[wasm_bindgen]
+impl WasmProof {
+    #[wasm_bindgen(getter)]
+    pub fn proof(&self) -> Vec<u8> {
+        self.proof.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+
+    #[wasm_bindgen(getter, js_name = activityHash)]
+    pub fn activity_hash(&self) -> String {
+        self.activity_hash.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nullifier(&self) -> String {
+        self.nullifier.clone()
+    }
+}
+
+// トラステッドセットアップを実行し、証明キーと検証キーを返す
+#[wasm_bindgen]
+pub fn setup() -> WasmKeys {
+    let (prover, verifier) = Setup::generate();
+    WasmKeys {
+        proving_key: key_to_bytes(prover.proving_key()),
+        verifying_key: key_to_bytes(&verifier.verifying_key.vk),
+    }
+}
+
+// 現在時刻の行動として証明を生成する（秘密値とソルトは32バイト）
+#[wasm_bindgen]
+pub fn prove(proving_key: &[u8], activity: &str, secret: &[u8], salt: &[u8]) -> Result<WasmProof, JsError> {
+    let prover = Bn254Prover::from_proving_key(key_from_bytes(proving_key)?);
+    let activity_data = ActivityData::new(
+        Utc::now(),
+        hash_activity(activity),
+        to_bytes32(secret)?,
+        to_bytes32(salt)?,
+    );
+
+    let (public_activity, proof) = prover.prove_activity(&activity_data)?;
+    Ok(WasmProof {
+        proof: serialize_proof(&proof),
+        timestamp: public_activity.timestamp.timestamp() as f64,
+        activity_hash: hash_to_hex(&public_activity.activity_hash),
+        commitment: field_to_hex(&public_activity.commitment),
+        cutoff: public_activity.cutoff.timestamp() as f64,
+        nullifier: field_to_hex(&public_activity.nullifier),
+    })
+}
+
+// 証明を検証する（証明が正しくなければfalse、入力の形式が不正ならエラー）
+#[wasm_bindgen]
+pub fn verify(
+    verifying_key: &[u8],
+    proof: &[u8],
+    timestamp: f64,
+    activity_hash: &str,
+    commitment: &str,
+    cutoff: f64,
+    nullifier: &str,
+) -> Result<bool, JsError> {
+    let verifier = Bn254Verifier::from_prepared_key(prepare_verifying_key(&key_from_bytes(verifying_key)?));
+    let proof = deserialize_proof(proof)?;
+    let activity = PublicActivity {
+        timestamp: from_unix(timestamp)?,
+        activity_hash: hex_to_hash(activity_hash)?,
+        commitment: hex_to_field(commitment)?,
+        cutoff: from_unix(cutoff)?,
+        nullifier: hex_to_field(nullifier)?,
+        // proveはチャレンジと利用者のID、有効期限なしで証明する
+        challenge: Fr::zero(),
+        user_id: Fr::zero(),
+        expiry: NO_EXPIRY,
+    };
+
+    match verifier.verify_activity(&activity, &proof).and_then(VerificationReport::into_result) {
+        Ok(_) => Ok(true),
+        Err(
+            ZkError::VerificationFailed
+            | ZkError::StaleTimestamp
+            | ZkError::FutureTimestamp
+            | ZkError::StaleCutoff
+            | ZkError::ExpiredProof,
+        ) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn to_bytes32(bytes: &[u8]) -> Result<[u8; 32], JsError> {
+    bytes
+        .try_into()
+        .map_err(|_| JsError::new(&format!("Expected 32 bytes, got {}", bytes.len())))
+}
+
+fn from_unix(seconds: f64) -> Result<DateTime<Utc>, JsError> {
+    Ok(datetime_from_unix(seconds as i64)?)
+}
@@ -0,0 +1,58 @@
+use alloc::string::String;
+
+use ark_ff::PrimeField;
+use chrono::{DateTime, Utc};
+
+use crate::activity::{field_to_bytes_be, hash_to_field_be_checked, hex_to_hash};
+use crate::error::ZkError;
+
+// JSON-RPC・gRPC・wasm・CLIが公開部分をやり取りするときの変換（どの入り口でも同じ規則で読み書きする）
+
+// 体の元を32バイトのビッグエンディアンの16進文字列にする（BN254とBLS12-381のスカラー体はどちらも32バイト）
+pub fn field_to_hex<F: PrimeField>(value: &F) -> String {
+    hex::encode(field_to_bytes_be(value))
+}
+
+// field_to_hexの逆（32バイトでなければInvalidHashLength、体の位数以上の値は簡約せずNonCanonicalField）
+pub fn hex_to_field<F: PrimeField>(value: &str) -> Result<F, ZkError> {
+    hash_to_field_be_checked(&hex_to_hash(value)?)
+}
+
+// 32バイトのビッグエンディアンのバイト列を体の元にする（hex_to_fieldと同じ規則。gRPCのbytesフィールド用）
+#[cfg(any(feature = "server", test))]
+pub(crate) fn bytes_to_field<F: PrimeField>(bytes: &[u8]) -> Result<F, ZkError> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| ZkError::InvalidHashLength(bytes.len()))?;
+    hash_to_field_be_checked(&bytes)
+}
+
+// Unix秒を時刻にする（DateTimeで表せない範囲ならTimeArithmetic）
+pub fn datetime_from_unix(seconds: i64) -> Result<DateTime<Utc>, ZkError> {
+    DateTime::from_timestamp(seconds, 0).ok_or(ZkError::TimeArithmetic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn test_field_hex_round_trip_rejects_non_canonical() {
+        let value = Fr::from(12345u64);
+        let hex = field_to_hex(&value);
+        assert_eq!(hex.len(), 64);
+        assert_eq!(hex_to_field::<Fr>(&hex).unwrap(), value);
+        assert_eq!(bytes_to_field::<Fr>(&hex::decode(&hex).unwrap()).unwrap(), value);
+
+        // 体の位数以上の値は簡約して受け付けず、32バイト以外の長さもエラー
+        assert!(matches!(hex_to_field::<Fr>(&"ff".repeat(32)), Err(ZkError::NonCanonicalField)));
+        assert!(matches!(bytes_to_field::<Fr>(&[0xff; 32]), Err(ZkError::NonCanonicalField)));
+        assert!(matches!(hex_to_field::<Fr>(&hex[2..]), Err(ZkError::InvalidHashLength(31))));
+        assert!(matches!(bytes_to_field::<Fr>(&[0; 33]), Err(ZkError::InvalidHashLength(33))));
+    }
+
+    #[test]
+    fn test_datetime_from_unix_range() {
+        assert_eq!(datetime_from_unix(1_700_000_000).unwrap().timestamp(), 1_700_000_000);
+        assert!(matches!(datetime_from_unix(i64::MAX), Err(ZkError::TimeArithmetic)));
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// ビルド済みのCLIバイナリを実行する
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zk_sample"))
+        .args(args)
+        .output()
+        .expect("Failed to run the CLI binary")
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zk_sample_cli_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// proveの出力（"key: value"の行）をverifyの引数に変換する
+fn verify_args(prove_output: &Output, proof: &Path, keys: &Path) -> Vec<String> {
+    let mut args = vec![
+        "verify".to_string(),
+        "--proof".to_string(),
+        proof.display().to_string(),
+        "--keys".to_string(),
+        keys.display().to_string(),
+    ];
+    for line in String::from_utf8_lossy(&prove_output.stdout).lines() {
+        let (key, value) = line.split_once(": ").expect("Unexpected prove output");
+        args.push(format!("--{}", key));
+        args.push(value.to_string());
+    }
+    args
+}
+
+#[test]
+fn test_cli_setup_prove_verify() {
+    let dir = temp_dir("setup_prove_verify");
+    let keys = dir.join("keys");
+    let proof = dir.join("proof.bin");
+    let keys_arg = keys.display().to_string();
+    let proof_arg = proof.display().to_string();
+
+    let setup = run(&["setup", "--out-dir", &keys_arg]);
+    assert!(setup.status.success());
+
+    let secret = "01".repeat(32);
+    let prove = run(&[
+        "prove",
+        "--activity",
+        "cli_activity",
+        "--commitment",
+        &secret,
+        "--keys",
+        &keys_arg,
+        "--out",
+        &proof_arg,
+    ]);
+    assert!(prove.status.success());
+
+    // 正しい公開入力なら終了コード0
+    let args = verify_args(&prove, &proof, &keys);
+    let verify = run(&args.iter().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!(verify.status.code(), Some(0));
+
+    // 別の行動ハッシュに対しては検証に失敗し、終了コードは0以外
+    let mut forged = args.clone();
+    let index = forged.iter().position(|arg| arg == "--activity-hash").unwrap();
+    forged[index + 1] = "02".repeat(32);
+    let verify = run(&forged.iter().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!(verify.status.code(), Some(1));
+
+    // 読み込めない証明ファイルはエラーとして区別される
+    fs::write(&proof, [0u8; 8]).unwrap();
+    let verify = run(&args.iter().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!(verify.status.code(), Some(2));
+
+    fs::remove_dir_all(dir).unwrap();
+}
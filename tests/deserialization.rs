@@ -0,0 +1,148 @@
+// 攻撃者が渡せるバイト列（証明・検証キー・鍵ファイル）を任意に壊しても、パニックせずErrを返すことを確かめる
+// 見つかったarkworks内部のパニック: Vecのデシリアライズは書き換えた長さのまま領域を確保するので、
+// 容量の溢れでパニック、または確保の失敗でプロセスが異常終了する。鍵の読み込みは長さを確かめる独自の実装で防いでいる
+// （証明は固定長で長さの情報を含まないので影響しない）
+// もう1つ、証明の点の無限遠点のフラグを立てると座標を無視して読めてしまい、同じ証明に別の表現ができていた。
+// deserialize_proofは正規の表現以外を拒否するので、読めた場合は元のバイト列と一致することも確かめる
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use ark_bn254::Bn254;
+use ark_serialize::CanonicalSerialize;
+use chrono::Utc;
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use zk_sample::{
+    deserialize_proof, hash_activity, key_info, serialize_proof, ActivityData, Bn254Prover, Bn254Verifier, Setup,
+};
+
+// 正しい証明と鍵のバイト列（壊す元として1回だけ作る）
+struct Samples {
+    proof: Vec<u8>,
+    vk_compressed: Vec<u8>,
+    prover_key: Vec<u8>,
+    verifier_key: Vec<u8>,
+}
+
+fn samples() -> &'static Samples {
+    static SAMPLES: OnceLock<Samples> = OnceLock::new();
+    SAMPLES.get_or_init(|| {
+        let rng = &mut ChaCha20Rng::seed_from_u64(53);
+        let (prover, verifier) = Setup::<Bn254>::generate_with_rng(rng);
+        let activity = ActivityData::new(Utc::now(), hash_activity("fuzz"), [1u8; 32], [0u8; 32]);
+        let proof = prover.generate_proof(&activity, rng).unwrap();
+
+        let dir = temp_dir("samples");
+        Setup::<Bn254>::save_keys(&prover, &verifier, dir.join("prover.key"), dir.join("verifier.key")).unwrap();
+        let mut vk_compressed = Vec::new();
+        verifier.verifying_key().serialize_compressed(&mut vk_compressed).unwrap();
+        let samples = Samples {
+            proof: serialize_proof(&proof),
+            vk_compressed,
+            prover_key: fs::read(dir.join("prover.key")).unwrap(),
+            verifier_key: fs::read(dir.join("verifier.key")).unwrap(),
+        };
+        fs::remove_dir_all(dir).unwrap();
+        samples
+    })
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zk_sample_fuzz_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// 位置posから8バイトを任意の値で上書きする（長さの欄を書き換える場合を含む）
+fn overwrite(bytes: &[u8], pos: usize, value: u64) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    let pos = pos % bytes.len().saturating_sub(8).max(1);
+    let end = (pos + 8).min(bytes.len());
+    bytes[pos..end].copy_from_slice(&value.to_le_bytes()[..end - pos]);
+    bytes
+}
+
+// ファイル経由で鍵を読み込む（読み込めた場合は正しい鍵のはずなので、読み込めたかだけを返す）
+fn load_key_file(name: &str, bytes: &[u8], load: impl FnOnce(&PathBuf) -> bool) -> bool {
+    let dir = temp_dir(name);
+    let path = dir.join("key");
+    fs::write(&path, bytes).unwrap();
+    let loaded = load(&path);
+    fs::remove_dir_all(dir).unwrap();
+    loaded
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_never_panic_as_proof(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+        // 偶然曲線上の点になるバイト列もあるので、読めた場合は正規の表現であることだけを確かめる
+        if let Ok(proof) = deserialize_proof::<Bn254>(&bytes) {
+            prop_assert_eq!(serialize_proof(&proof), bytes);
+        }
+    }
+
+    #[test]
+    fn corrupted_proof_never_panics(pos in any::<usize>(), mask in 1u8..) {
+        let mut bytes = samples().proof.clone();
+        let pos = pos % bytes.len();
+        bytes[pos] ^= mask;
+        if let Ok(proof) = deserialize_proof::<Bn254>(&bytes) {
+            prop_assert_eq!(serialize_proof(&proof), bytes);
+        }
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic_as_verifying_key(bytes in prop::collection::vec(any::<u8>(), 0..1024)) {
+        prop_assert!(Bn254Verifier::from_vk_bytes(&bytes).is_err());
+        let _ = key_info(&bytes);
+    }
+
+    #[test]
+    fn corrupted_verifying_key_never_panics(pos in any::<usize>(), value in any::<u64>()) {
+        // 書き換えた値によっては正しい鍵のままのこともある（外部の鍵として読めれば受け入れてよい）
+        let bytes = overwrite(&samples().vk_compressed, pos, value);
+        let _ = Bn254Verifier::from_vk_bytes(&bytes);
+    }
+}
+
+proptest! {
+    // 鍵ファイルはファイルの読み書きと点の検証に時間がかかるので件数を減らす
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn corrupted_key_files_never_panic(pos in any::<usize>(), value in any::<u64>()) {
+        let samples = samples();
+        let verifier_key = overwrite(&samples.verifier_key, pos, value);
+        load_key_file("verifier", &verifier_key, |path| Bn254Verifier::load(path).is_ok());
+        let prover_key = overwrite(&samples.prover_key, pos, value);
+        load_key_file("prover", &prover_key, |path| Bn254Prover::load(path).is_ok());
+    }
+
+    #[test]
+    fn arbitrary_key_bodies_are_rejected(body in prop::collection::vec(any::<u8>(), 0..512)) {
+        // ヘッダーは正しく、本体だけが任意のバイト列
+        let samples = samples();
+        let verifier_key = [&samples.verifier_key[..9], &body].concat();
+        prop_assert!(!load_key_file("verifier_body", &verifier_key, |path| Bn254Verifier::load(path).is_ok()));
+        let prover_key = [&samples.prover_key[..9], &body].concat();
+        prop_assert!(!load_key_file("prover_body", &prover_key, |path| Bn254Prover::load(path).is_ok()));
+    }
+}
+
+#[test]
+fn huge_declared_length_is_rejected_before_allocating() {
+    // 検証キーの点の列の長さ（G1が1つとG2が3つの後の8バイト）をu64の最大値にする
+    let samples = samples();
+    let length_pos = 32 + 3 * 64;
+    let bytes = overwrite(&samples.vk_compressed, length_pos, u64::MAX);
+    assert!(Bn254Verifier::from_vk_bytes(&bytes).is_err());
+
+    // 鍵ファイルでも同じ位置（ヘッダーの後、非圧縮なのでG1が64バイト・G2が128バイト）
+    let length_pos = 9 + 64 + 3 * 128;
+    let bytes = overwrite(&samples.verifier_key, length_pos, u64::MAX);
+    assert!(!load_key_file("huge_length", &bytes, |path| Bn254Verifier::load(path).is_ok()));
+}
@@ -0,0 +1,85 @@
+// cargo test --test jsonrpc --features jsonrpc で実行する
+#![cfg(feature = "jsonrpc")]
+
+use serde_json::{json, Value};
+use zk_sample::jsonrpc::{
+    JsonRpcRequest, JsonRpcResponse, JsonRpcService, ProveResult, VerifyResult, INVALID_PARAMS, METHOD_NOT_FOUND,
+    PARSE_ERROR,
+};
+use zk_sample::Setup;
+
+// 要求をJSON文字列にして送り、応答のJSON文字列を読み戻す
+fn call(service: &JsonRpcService, method: &str, params: Value, id: u64) -> JsonRpcResponse {
+    let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": id });
+    let response: JsonRpcResponse = serde_json::from_str(&service.handle(&request.to_string()).unwrap()).unwrap();
+    assert_eq!(response.jsonrpc, "2.0");
+    assert_eq!(response.id, json!(id));
+    response
+}
+
+#[test]
+fn test_prove_and_verify_over_json_rpc() {
+    let (prover, verifier) = Setup::generate();
+    let service = JsonRpcService::new(prover, verifier);
+
+    // 証明を生成し、返ってきた証明と公開入力をそのまま検証に回す
+    let params = json!({ "activity": "rpc_activity", "user_secret": "05".repeat(32) });
+    let proved: ProveResult = serde_json::from_value(call(&service, "zk_prove", params, 1).result.unwrap()).unwrap();
+    let params = json!({ "proof": proved.proof, "public": proved.public });
+    let verified: VerifyResult =
+        serde_json::from_value(call(&service, "zk_verify", params, 2).result.unwrap()).unwrap();
+    assert!(verified.valid, "{:?}", verified.reason);
+
+    // 公開入力を書き換えると、エラーではなく不正な証明として判定される
+    let mut public = proved.public.clone();
+    public.nullifier = "00".repeat(32);
+    let params = json!({ "proof": proved.proof, "public": public });
+    let verified: VerifyResult =
+        serde_json::from_value(call(&service, "zk_verify", params, 3).result.unwrap()).unwrap();
+    assert!(!verified.valid);
+    assert!(verified.reason.is_some());
+
+    // base64urlとして読めない証明と、読めても証明として復元できない証明は別のコードのエラーになる
+    let params = json!({ "proof": "not base64!", "public": proved.public });
+    let invalid_base64 = call(&service, "zk_verify", params, 4).error.unwrap();
+    assert_eq!(invalid_base64.data, Some(json!("InvalidBase64")));
+    let params = json!({ "proof": "AAAA", "public": proved.public });
+    let malformed = call(&service, "zk_verify", params, 5).error.unwrap();
+    assert_eq!(malformed.data, Some(json!("Serialization")));
+    assert_ne!(malformed.code, invalid_base64.code);
+    assert!((-32099..=-32000).contains(&malformed.code));
+
+    // 体の位数以上の値は簡約して別の値として検証せず、エラーにする
+    let mut public = proved.public.clone();
+    public.commitment = "ff".repeat(32);
+    let params = json!({ "proof": proved.proof, "public": public });
+    assert_eq!(call(&service, "zk_verify", params, 9).error.unwrap().data, Some(json!("NonCanonicalField")));
+
+    // 16進として読めない秘密値、引数の不足、未知のメソッド
+    let params = json!({ "activity": "rpc_activity", "user_secret": "zz" });
+    assert_eq!(call(&service, "zk_prove", params, 6).error.unwrap().data, Some(json!("InvalidHex")));
+    let missing = call(&service, "zk_prove", json!({ "activity": "rpc_activity" }), 7).error.unwrap();
+    assert_eq!(missing.code, INVALID_PARAMS);
+    assert_eq!(call(&service, "zk_unknown", Value::Null, 8).error.unwrap().code, METHOD_NOT_FOUND);
+
+    // idのない通知には、エラーになる要求でも応答しない
+    assert_eq!(service.handle(r#"{"jsonrpc": "2.0", "method": "zk_unknown"}"#), None);
+    assert_eq!(service.handle(r#"{"jsonrpc": "1.0", "method": "zk_verify", "params": {}}"#), None);
+
+    // idがnullの要求や、JSONとして読めない要求には、idをnullとして応答する
+    let response = service.handle(r#"{"jsonrpc": "2.0", "method": "zk_unknown", "id": null}"#).unwrap();
+    let response: JsonRpcResponse = serde_json::from_str(&response).unwrap();
+    assert_eq!((response.id, response.error.unwrap().code), (Value::Null, METHOD_NOT_FOUND));
+    let response: JsonRpcResponse = serde_json::from_str(&service.handle("{").unwrap()).unwrap();
+    assert_eq!((response.id, response.error.unwrap().code), (Value::Null, PARSE_ERROR));
+}
+
+#[test]
+fn test_request_debug_hides_params() {
+    // 引数の秘密値はDebugに出さない
+    let secret = "07".repeat(32);
+    let request = json!({ "jsonrpc": "2.0", "method": "zk_prove", "params": { "user_secret": secret }, "id": 1 });
+    let debug = format!("{:?}", serde_json::from_value::<JsonRpcRequest>(request).unwrap());
+    assert!(debug.contains("zk_prove"));
+    assert!(!debug.contains(&secret));
+}
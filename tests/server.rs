@@ -0,0 +1,114 @@
+// cargo test --test server --features server で実行する
+#![cfg(feature = "server")]
+
+use ark_serialize::CanonicalSerialize;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+use chrono::Utc;
+use zk_sample::server::{ActivityClient, ActivityServer, ActivityService, ProveRequest, PublicInputs, VerifyRequest};
+use zk_sample::{hash_activity, serialize_proof, ActivityData, ActivityVerifier, Setup};
+
+fn key_bytes(verifier: &ActivityVerifier) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    verifier.verifying_key().serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prove_and_verify_over_grpc() {
+    let (prover, verifier) = Setup::generate();
+    let verifying_key = key_bytes(&verifier);
+    // 別のセットアップの鍵は、サーバーが受け付ける検証者として追加したものだけを使える
+    let (other_prover, other_verifier) = Setup::generate();
+    let other_key = key_bytes(&other_verifier);
+    let (_, unknown_verifier) = Setup::generate();
+    let unknown_key = key_bytes(&unknown_verifier);
+
+    // 空いているポートでサーバーを起動する
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(ActivityServer::new(
+                ActivityService::new(prover, verifier).with_accepted_verifier(other_verifier),
+            ))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    let mut client = ActivityClient::connect(format!("http://{}", address)).await.unwrap();
+
+    // 証明を生成し、返ってきた証明と公開入力をそのまま検証に回す
+    let proved = client
+        .prove(ProveRequest {
+            activity: "grpc_activity".to_string(),
+            user_secret: vec![5u8; 32],
+            salt: vec![0u8; 32],
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let verified = client
+        .verify(VerifyRequest {
+            proof: proved.proof.clone(),
+            public: proved.public.clone(),
+            verifying_key: Vec::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(verified.valid, "{}", verified.reason);
+
+    // 検証キーをバイト列で渡しても検証できる
+    let verified = client
+        .verify(VerifyRequest {
+            proof: proved.proof.clone(),
+            public: proved.public.clone(),
+            verifying_key,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(verified.valid, "{}", verified.reason);
+
+    // 受け付けている別の鍵で作った証明は、その鍵を指定すれば検証できる
+    let activity = ActivityData::new(Utc::now(), hash_activity("grpc_activity"), [5u8; 32], [0u8; 32]);
+    let (public_activity, proof) = other_prover.prove_activity(&activity).unwrap();
+    let other_request = |verifying_key| VerifyRequest {
+        proof: serialize_proof(&proof),
+        public: Some(PublicInputs::from(&public_activity)),
+        verifying_key,
+    };
+    let verified = client.verify(other_request(other_key)).await.unwrap().into_inner();
+    assert!(verified.valid, "{}", verified.reason);
+    let verified = client.verify(other_request(Vec::new())).await.unwrap().into_inner();
+    assert!(!verified.valid);
+
+    // 受け付けていない鍵は、その鍵で正しい証明でも検証せずに拒否する
+    let status = client.verify(other_request(unknown_key)).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // 公開入力を書き換えると不正と判定される
+    let mut public = proved.public.clone().unwrap();
+    public.nullifier[31] ^= 1;
+    let verified = client
+        .verify(VerifyRequest {
+            proof: proved.proof.clone(),
+            public: Some(public),
+            verifying_key: Vec::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!verified.valid);
+
+    // 形式が不正な要求はエラーになる
+    let status = client
+        .prove(ProveRequest {
+            activity: "grpc_activity".to_string(),
+            user_secret: vec![7u8; 31],
+            salt: vec![0u8; 32],
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
@@ -0,0 +1,52 @@
+// wasm-pack test --headless --firefox --features wasm で実行する
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use wasm_bindgen_test::*;
+use zk_sample::wasm::{prove, setup, verify};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn test_proof_round_trip_in_browser() {
+    let keys = setup();
+    let proof = prove(&keys.proving_key(), "browser_activity", &[3u8; 32], &[4u8; 32]).unwrap();
+
+    // Uint8Arrayで受け渡した鍵と証明で検証できる
+    assert!(verify(
+        &keys.verifying_key(),
+        &proof.proof(),
+        proof.timestamp(),
+        &proof.activity_hash(),
+        &proof.commitment(),
+        proof.cutoff(),
+        &proof.nullifier(),
+    )
+    .unwrap());
+
+    // 別の行動ハッシュに対しては検証に失敗する
+    assert!(!verify(
+        &keys.verifying_key(),
+        &proof.proof(),
+        proof.timestamp(),
+        &"00".repeat(32),
+        &proof.commitment(),
+        proof.cutoff(),
+        &proof.nullifier(),
+    )
+    .unwrap());
+
+    // 32バイトでないコミットメントやnullifierは、体の元に簡約せずエラーにする
+    let verify_with = |commitment: &str, nullifier: &str| {
+        verify(
+            &keys.verifying_key(),
+            &proof.proof(),
+            proof.timestamp(),
+            &proof.activity_hash(),
+            commitment,
+            proof.cutoff(),
+            nullifier,
+        )
+    };
+    assert!(verify_with(&format!("00{}", proof.commitment()), &proof.nullifier()).is_err());
+    assert!(verify_with(&proof.commitment(), &proof.nullifier()[2..]).is_err());
+}